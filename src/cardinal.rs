@@ -1,94 +1,38 @@
-use std::cmp::{max, min};
-use windows::Win32::Foundation::{POINT, RECT};
+use crate::geometry::{Point, Rect};
 
+/// Named points of a `Rect` besides the `top_left`, `bottom_right` and `center` it already has.
 pub trait Cardinal {
-    fn width(&self) -> i32;
-    fn height(&self) -> i32;
+    fn north(&self) -> Point;
+    fn south(&self) -> Point;
+    fn east(&self) -> Point;
+    fn west(&self) -> Point;
 
-    fn north(&self) -> POINT;
-    fn south(&self) -> POINT;
-    fn east(&self) -> POINT;
-    fn west(&self) -> POINT;
-
-    fn top_left(&self) -> POINT;
-    fn top_right(&self) -> POINT;
-    fn bottom_left(&self) -> POINT;
-    fn bottom_right(&self) -> POINT;
-
-    fn center(&self) -> POINT;
-
-    fn from_points(p0: POINT, p1: POINT) -> Self;
+    fn top_right(&self) -> Point;
+    fn bottom_left(&self) -> Point;
 }
 
-impl Cardinal for RECT {
-    fn width(&self) -> i32 {
-        self.right - self.left
-    }
-
-    fn height(&self) -> i32 {
-        self.bottom - self.top
-    }
-
-    fn north(&self) -> POINT {
-        POINT {
-            x: self.center().x,
-            y: self.top,
-        }
-    }
-
-    fn south(&self) -> POINT {
-        POINT {
-            x: self.center().x,
-            y: self.bottom,
-        }
-    }
-
-    fn east(&self) -> POINT {
-        POINT {
-            x: self.right,
-            y: self.center().y,
-        }
-    }
-
-    fn west(&self) -> POINT {
-        POINT {
-            x: self.left,
-            y: self.center().y,
-        }
-    }
-
-    fn top_left(&self) -> POINT {
-        POINT { x: self.left, y: self.top }
+impl Cardinal for Rect {
+    fn north(&self) -> Point {
+        Point::new(self.center().x, self.top)
     }
 
-    fn top_right(&self) -> POINT {
-        POINT { x: self.right, y: self.top }
+    fn south(&self) -> Point {
+        Point::new(self.center().x, self.bottom)
     }
 
-    fn bottom_left(&self) -> POINT {
-        POINT { x: self.left, y: self.bottom }
+    fn east(&self) -> Point {
+        Point::new(self.right, self.center().y)
     }
 
-    fn bottom_right(&self) -> POINT {
-        POINT {
-            x: self.right,
-            y: self.bottom,
-        }
+    fn west(&self) -> Point {
+        Point::new(self.left, self.center().y)
     }
 
-    fn center(&self) -> POINT {
-        POINT {
-            x: (self.left + self.right) / 2,
-            y: (self.top + self.bottom) / 2,
-        }
+    fn top_right(&self) -> Point {
+        Point::new(self.right, self.top)
     }
 
-    fn from_points(p0: POINT, p1: POINT) -> RECT {
-        RECT {
-            left: min(p0.x, p1.x),
-            right: max(p0.x, p1.x),
-            top: min(p0.y, p1.y),
-            bottom: max(p0.y, p1.y),
-        }
+    fn bottom_left(&self) -> Point {
+        Point::new(self.left, self.bottom)
     }
 }
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+use eyre::eyre;
+
+use crate::config::{self, Config};
+use crate::help::{self, Format};
+use crate::hotkey_action::{Action, ACTION_INFO};
+use crate::instance::{self, Request};
+use crate::{load_config, run_tray};
+
+const USAGE: &str = "Usage:
+    grist                          Run the tray app
+    grist run <action>             Apply an action once, e.g. 'MonitorLeft' or '!OnMonitor {x: 0, y: 0, w: 0, h: 0}'
+    grist check-config <path>      Validate a config file without installing hooks
+    grist list-actions             Print every action and its parameters
+    grist cheat-sheet [format]     Print the actions and their bound chords as text, markdown or html
+    grist dump-config              Print the effective config, including the built-in keymap
+    grist reload                   Reload the running instance's config
+    grist pause                    Pause the running instance's hotkeys
+    grist resume                   Resume the running instance's hotkeys
+    grist quit                     Quit the running instance
+
+'run' is forwarded to the running instance if there is one, and otherwise applied with the settings in the config
+file. Forwarded actions with a Delay run in the background, so their failures show as tray notifications rather
+than here. Either way ignore rules only apply to the windows RescueWindows and GatherAllToMonitor move, since the
+others name bindings rather than actions.";
+
+pub enum Command {
+    Tray,
+    Run(Action),
+    CheckConfig(PathBuf),
+    ListActions,
+    CheatSheet(Format),
+    DumpConfig,
+    Send(Request),
+}
+
+pub fn parse(mut args: impl Iterator<Item = String>) -> eyre::Result<Command> {
+    let command = match args.next().as_deref() {
+        None => return Ok(Command::Tray),
+        Some("run") => {
+            // Accept an unquoted action, e.g. `grist run !OnMonitor {x: 0, y: 0, w: 0, h: 0}`
+            let action = args.by_ref().collect::<Vec<String>>().join(" ");
+            if action.is_empty() {
+                return Err(eyre!("Missing action\n\n{}", USAGE));
+            }
+            Command::Run(serde_yaml::from_str(&action)?)
+        }
+        Some("check-config") => match args.next() {
+            Some(path) => Command::CheckConfig(PathBuf::from(path)),
+            None => return Err(eyre!("Missing config path\n\n{}", USAGE)),
+        },
+        Some("list-actions") => Command::ListActions,
+        Some("cheat-sheet") => match args.next().as_deref() {
+            None | Some("text") => Command::CheatSheet(Format::Text),
+            Some("markdown") => Command::CheatSheet(Format::Markdown),
+            Some("html") => Command::CheatSheet(Format::Html),
+            Some(format) => return Err(eyre!("Unknown format '{}'\n\n{}", format, USAGE)),
+        },
+        Some("dump-config") => Command::DumpConfig,
+        Some("reload") => Command::Send(Request::Reload),
+        Some("pause") => Command::Send(Request::Pause),
+        Some("resume") => Command::Send(Request::Resume),
+        Some("quit") => Command::Send(Request::Quit),
+        Some(command) => return Err(eyre!("Unknown command '{}'\n\n{}", command, USAGE)),
+    };
+
+    match args.next() {
+        Some(arg) => Err(eyre!("Unexpected argument '{}'\n\n{}", arg, USAGE)),
+        None => Ok(command),
+    }
+}
+
+pub fn run(command: Command) -> eyre::Result<()> {
+    match command {
+        Command::Tray => run_tray(),
+        Command::Run(action) => match instance::find_running() {
+            Some(hwnd) => instance::forward(hwnd, &Request::Run(action)),
+            None => {
+                // As the running instance would, e.g. for `RescueWindows`' `min_visible_percent`
+                load_config()?;
+                Ok(action.apply()?)
+            }
+        },
+        Command::CheckConfig(path) => check_config(&path),
+        Command::ListActions => {
+            ACTION_INFO
+                .iter()
+                .for_each(|info| println!("{}\n    {}", info, info.description));
+            Ok(())
+        }
+        Command::CheatSheet(format) => {
+            let config = config::load(Path::new(config::CONFIG_FILE))?;
+            print!("{}", help::render(&config.keymap(), format));
+            Ok(())
+        }
+        Command::DumpConfig => {
+            let config = config::load(Path::new(config::CONFIG_FILE))?;
+            let effective = Config {
+                default_actions: false,
+                actions: config.keymap(),
+                ..config
+            };
+            print!("{}", serde_yaml::to_string(&effective)?);
+            Ok(())
+        }
+        Command::Send(request) => match instance::find_running() {
+            Some(hwnd) => instance::forward(hwnd, &request),
+            None => Err(eyre!("Grist isn't running")),
+        },
+    }
+}
+
+fn check_config(path: &Path) -> eyre::Result<()> {
+    let problems = config::validate(&config::read(path)?);
+    if problems.is_empty() {
+        println!("{}: ok", path.display());
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("{}: {}", path.display(), problem);
+    }
+    Err(eyre!("{} problem(s) found in {}", problems.len(), path.display()))
+}
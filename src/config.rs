@@ -0,0 +1,398 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::create_actions;
+use crate::error::ErrorKind;
+use crate::hotkey_action::{HotkeyAction, VK};
+use crate::log::LogConfig;
+use crate::window_match::{WindowInfo, WindowMatch};
+
+pub const CONFIG_FILE: &str = "grist.yaml";
+
+/// Actions that move every window, which ignore rules can name as well as bindings.
+#[cfg(windows)]
+const WINDOW_SWEEPS: [&str; 2] = ["RescueWindows", "GatherAllToMonitor"];
+
+/// Error kinds muted from a notification, kept out of the config file so that it's never rewritten.
+#[cfg(windows)]
+const MUTED_ERRORS_FILE: &str = "muted_errors.yaml";
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Start from the built-in keymap before adding `actions`.
+    pub default_actions: bool,
+    pub actions: Vec<HotkeyAction>,
+    pub ignore: Vec<IgnoreRule>,
+    /// Windows that get every key, e.g. remote desktops and VM consoles that want Windows key chords.
+    pub passthrough: Vec<WindowMatch>,
+    /// Named sets of bindings added after `actions` while the profile is active.
+    pub profiles: BTreeMap<String, Vec<HotkeyAction>>,
+    /// The profile that is active at startup.
+    pub profile: Option<String>,
+    /// Serve line-delimited JSON IPC on `127.0.0.1:<ipc_port>`. Any local process can connect, so it's off by
+    /// default.
+    pub ipc_port: Option<u16>,
+    pub cheat_sheet: CheatSheet,
+    pub log: LogConfig,
+    pub notifications: Notifications,
+    pub rescue: Rescue,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            default_actions: true,
+            actions: Vec::new(),
+            ignore: vec![IgnoreRule {
+                window: WindowMatch::exe("Zoom.exe"),
+                actions: vec!["Minimize".to_owned()],
+            }],
+            passthrough: vec![WindowMatch::exe("mstsc.exe"), WindowMatch::exe("vmconnect.exe")],
+            profiles: BTreeMap::new(),
+            profile: None,
+            ipc_port: None,
+            cheat_sheet: CheatSheet::default(),
+            log: LogConfig::default(),
+            notifications: Notifications::default(),
+            rescue: Rescue::default(),
+        }
+    }
+}
+
+/// Tray notifications for actions that fail.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Notifications {
+    pub enabled: bool,
+    /// The minimum time between two notifications of the same kind.
+    pub interval_secs: u64,
+    pub muted: BTreeSet<ErrorKind>,
+}
+
+impl Default for Notifications {
+    fn default() -> Notifications {
+        Notifications {
+            enabled: true,
+            interval_secs: 30,
+            muted: BTreeSet::new(),
+        }
+    }
+}
+
+/// Bringing back windows left mostly outside every work area, e.g. after a monitor is unplugged.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Rescue {
+    /// Rescue windows whenever monitors are added, removed or rearranged, not just on `RescueWindows`.
+    pub on_monitor_change: bool,
+    /// Windows with less of their frame than this on every work area are rescued.
+    pub min_visible_percent: u32,
+}
+
+impl Rescue {
+    pub const fn new() -> Rescue {
+        Rescue {
+            on_monitor_change: false,
+            min_visible_percent: 25,
+        }
+    }
+}
+
+impl Default for Rescue {
+    fn default() -> Rescue {
+        Rescue::new()
+    }
+}
+
+/// Holding one of `keys` on its own for `hold_ms` shows an overlay listing every available chord.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct CheatSheet {
+    /// An empty list turns the overlay off.
+    pub keys: BTreeSet<VK>,
+    pub hold_ms: u64,
+}
+
+impl Default for CheatSheet {
+    fn default() -> CheatSheet {
+        CheatSheet {
+            keys: BTreeSet::from([VK::LeftWindows, VK::RightWindows]),
+            hold_ms: 1000,
+        }
+    }
+}
+
+/// Windows that actions should leave alone.
+///
+/// `actions` holds `HotkeyAction` names, or `RescueWindows` and `GatherAllToMonitor`, which check each window they
+/// move rather than the foreground one. When it's empty the rule applies to every action.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct IgnoreRule {
+    #[serde(flatten)]
+    pub window: WindowMatch,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<String>,
+}
+
+impl IgnoreRule {
+    pub fn ignores(&self, action_name: &str, info: &WindowInfo) -> bool {
+        (self.actions.is_empty() || self.actions.iter().any(|name| name == action_name)) && self.window.matches(info)
+    }
+}
+
+impl Config {
+    /// The bindings the keyboard hook uses, built-in ones first.
+    pub fn keymap(&self) -> Vec<HotkeyAction> {
+        let mut actions = match self.default_actions {
+            true => create_actions(),
+            false => Vec::new(),
+        };
+        actions.extend(self.actions.iter().cloned());
+        if let Some(profile) = self.profile.as_ref().and_then(|profile| self.profiles.get(profile)) {
+            actions.extend(profile.iter().cloned());
+        }
+        actions
+    }
+}
+
+/// `%LOCALAPPDATA%\grist`, or the temp directory if it isn't set, for logs and state grist writes itself.
+pub fn data_dir() -> PathBuf {
+    data_dir_in(std::env::var_os("LOCALAPPDATA"))
+}
+
+fn data_dir_in(local_app_data: Option<OsString>) -> PathBuf {
+    local_app_data
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("grist")
+}
+
+/// The error kinds muted at runtime, on top of `Notifications::muted`.
+#[cfg(windows)]
+pub fn load_muted_errors() -> BTreeSet<ErrorKind> {
+    std::fs::read_to_string(data_dir().join(MUTED_ERRORS_FILE))
+        .ok()
+        .and_then(|text| serde_yaml::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(windows)]
+pub fn save_muted_errors(muted: &BTreeSet<ErrorKind>) -> eyre::Result<()> {
+    std::fs::create_dir_all(data_dir())?;
+    std::fs::write(data_dir().join(MUTED_ERRORS_FILE), serde_yaml::to_string(muted)?)?;
+    Ok(())
+}
+
+/// Loads the config file, falling back to the defaults if it doesn't exist.
+pub fn load(path: &Path) -> eyre::Result<Config> {
+    match read(path) {
+        Err(error)
+            if error.downcast_ref::<std::io::Error>().map(|e| e.kind()) == Some(std::io::ErrorKind::NotFound) =>
+        {
+            Ok(Config::default())
+        }
+        result => result,
+    }
+}
+
+/// Loads the config file, which must exist.
+pub fn read(path: &Path) -> eyre::Result<Config> {
+    let text = std::fs::read_to_string(path)?;
+    serde_yaml::from_str(&text).map_err(eyre::Report::from)
+}
+
+/// Writes `default_actions` and `actions` back to the config file, keeping the rest of it, comments included, as
+/// the user wrote it. Comments inside the `actions` list are lost.
+#[cfg(windows)]
+pub fn save_actions(path: &Path, config: &Config) -> eyre::Result<()> {
+    let text = match std::fs::read_to_string(path) {
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+        result => result?,
+    };
+    let text = replace_top_level_key(&text, "default_actions", &serde_yaml::to_string(&config.default_actions)?);
+    let text = replace_top_level_key(&text, "actions", &serde_yaml::to_string(&config.actions)?);
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+/// Replaces the lines of a top level mapping entry, or appends it if there isn't one. The key may be quoted.
+///
+/// Comments inside the entry are dropped with it, but comments just before the next entry are left to that entry.
+#[cfg(any(windows, test))]
+fn replace_top_level_key(text: &str, key: &str, value: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let is_key = |line: &str| !line.is_empty() && !line.starts_with([' ', '\t', '-', '#']);
+    let spellings = [key.to_owned(), format!("\"{}\"", key), format!("'{}'", key)];
+    let is_this_key = |line: &str| {
+        spellings.iter().any(|spelling| {
+            line.strip_prefix(spelling.as_str())
+                .is_some_and(|rest| rest.trim_start().starts_with(':'))
+        })
+    };
+
+    // Lists and maps go on the lines after the key, scalars on the same line
+    let entry = match value.trim_end() {
+        value if value.contains('\n') || value.starts_with("- ") => format!("{}:\n{}", key, value),
+        value => format!("{}: {}", key, value),
+    };
+
+    let Some(start) = lines.iter().position(|line| is_this_key(line)) else {
+        let mut text = text.to_owned();
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&entry);
+        text.push('\n');
+        return text;
+    };
+    let mut end = lines[start + 1..]
+        .iter()
+        .position(|line| is_key(line))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    while end > start + 1 && (lines[end - 1].trim().is_empty() || lines[end - 1].starts_with('#')) {
+        end -= 1;
+    }
+
+    let mut result: Vec<&str> = lines[..start].to_vec();
+    result.push(&entry);
+    result.extend_from_slice(&lines[end..]);
+    result.join("\n") + "\n"
+}
+
+/// Finds mistakes that would make bindings or rules silently do nothing.
+#[cfg(windows)]
+pub fn validate(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let keymap = config.keymap();
+
+    for (i, action) in keymap.iter().enumerate() {
+        if action.trigger.is_empty() {
+            problems.push(format!("'{}' has no trigger keys", action.name));
+        }
+        if action.when.as_ref().is_some_and(|when| when.specificity() == 0) {
+            problems.push(format!("'{}' has an empty 'when' clause and will never fire", action.name));
+        }
+        if let Some(other) = keymap[..i].iter().find(|other| other.conflicts_with(action)) {
+            problems.push(format!(
+                "'{}' is bound to {:?}, which is already taken by '{}'",
+                action.name, action.trigger, other.name
+            ));
+        }
+    }
+
+    for rule in &config.ignore {
+        if rule.window.specificity() == 0 {
+            problems.push("An ignore rule has no exe, class or title and will never match".to_owned());
+        }
+        for name in rule.actions.iter().filter(|name| {
+            !keymap.iter().any(|action| action.name == **name) && !WINDOW_SWEEPS.contains(&name.as_str())
+        }) {
+            problems.push(format!("An ignore rule refers to unknown action '{}'", name));
+        }
+    }
+
+    if let Some(profile) = config
+        .profile
+        .as_ref()
+        .filter(|profile| !config.profiles.contains_key(*profile))
+    {
+        problems.push(format!("The active profile '{}' isn't defined in 'profiles'", profile));
+    }
+
+    if config.passthrough.iter().any(|window| window.specificity() == 0) {
+        problems.push("A passthrough window has no exe, class or title and will never match".to_owned());
+    }
+
+    if !(1..=100).contains(&config.rescue.min_visible_percent) {
+        problems.push("'rescue.min_visible_percent' must be between 1 and 100".to_owned());
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zoom() -> WindowInfo {
+        WindowInfo {
+            exe: r"C:\Program Files\Zoom\bin\Zoom.exe".to_owned(),
+            ..Default::default()
+        }
+    }
+
+    const ACTIONS: &str = "- name: Left\n  action: MonitorLeft\n  trigger: [LeftWindows, Left]\n";
+
+    #[test]
+    fn replaces_a_key_between_others_keeping_their_comments() {
+        let text = "# grist\nactions:\n  # old\n  - name: Old\n\n# Zoom\nignore: []\n";
+        assert_eq!(
+            replace_top_level_key(text, "actions", ACTIONS),
+            format!("# grist\nactions:\n{}\n# Zoom\nignore: []\n", ACTIONS)
+        );
+    }
+
+    #[test]
+    fn replaces_a_quoted_key() {
+        for text in ["\"actions\": []\nipc_port: 7777\n", "'actions' : []\nipc_port: 7777\n"] {
+            let text = replace_top_level_key(text, "actions", ACTIONS);
+            assert_eq!(text, format!("actions:\n{}ipc_port: 7777\n", ACTIONS));
+            assert!(serde_yaml::from_str::<Config>(&text).is_ok());
+        }
+    }
+
+    #[test]
+    fn replaces_the_last_key() {
+        let text = "ipc_port: 7777\ndefault_actions: true\n# the end\n";
+        assert_eq!(
+            replace_top_level_key(text, "default_actions", "false\n"),
+            "ipc_port: 7777\ndefault_actions: false\n# the end\n"
+        );
+    }
+
+    #[test]
+    fn appends_a_missing_key() {
+        assert_eq!(
+            replace_top_level_key("ipc_port: 7777", "actions", ACTIONS),
+            format!("ipc_port: 7777\nactions:\n{}", ACTIONS)
+        );
+        assert_eq!(
+            replace_top_level_key("", "default_actions", "false\n"),
+            "default_actions: false\n"
+        );
+    }
+
+    #[test]
+    fn ignore_rules_only_apply_to_their_actions() {
+        let rule = IgnoreRule {
+            window: WindowMatch::exe("Zoom.exe"),
+            actions: vec!["Minimize".to_owned()],
+        };
+        assert!(rule.ignores("Minimize", &zoom()));
+        assert!(!rule.ignores("Maximize", &zoom()));
+        assert!(!rule.ignores("Minimize", &WindowInfo::default()));
+    }
+
+    #[test]
+    fn ignore_rules_without_actions_apply_to_all_of_them() {
+        let rule = IgnoreRule {
+            window: WindowMatch::exe("Zoom.exe"),
+            actions: Vec::new(),
+        };
+        assert!(rule.ignores("Minimize", &zoom()));
+        assert!(rule.ignores("Maximize", &zoom()));
+    }
+
+    #[test]
+    fn data_is_kept_in_local_app_data_or_the_temp_directory() {
+        assert_eq!(
+            data_dir_in(Some(OsString::from(r"C:\Users\me\AppData\Local"))),
+            Path::new(r"C:\Users\me\AppData\Local").join("grist")
+        );
+        assert_eq!(data_dir_in(None), std::env::temp_dir().join("grist"));
+    }
+}
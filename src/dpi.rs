@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use windows::Win32::Foundation::{HWND, LPARAM, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{FW_NORMAL, HFONT, HGDIOBJ};
+use windows::Win32::UI::HiDpi::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2;
+use windows::Win32::UI::WindowsAndMessaging::{
+    GWLP_USERDATA, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOZORDER, WM_GETFONT, WM_SETFONT,
+};
+
+use crate::log;
+use crate::safe_win32::{
+    create_font, enum_child_windows, get_dpi_for_window, get_parent, get_window_long_ptr, get_window_rect,
+    map_window_points, send_message, set_process_dpi_awareness_context, set_window_long_ptr, set_window_pos,
+};
+use crate::window_system::{self, DEFAULT_DPI};
+
+const FONT_FACE: &str = "Segoe UI";
+/// 9 point, the size of the Windows message font at 100% scale.
+const FONT_HEIGHT: i32 = 12;
+
+/// Fonts by DPI. There are only ever a few, so they're never deleted.
+static FONTS: Mutex<BTreeMap<u32, isize>> = Mutex::new(BTreeMap::new());
+
+/// Makes rects physical pixels on every monitor, instead of scaled for whichever one Windows picks, and lets
+/// grist's windows scale themselves when they move to a monitor with a different DPI.
+pub fn set_per_monitor_aware() {
+    if let Err(error) = set_process_dpi_awareness_context(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) {
+        // Before Windows 10 1703, or already set by a manifest
+        log!(Warn, App, "Couldn't make grist Per-Monitor-V2 DPI aware: {:?}", error);
+    }
+}
+
+/// The message font at `dpi`, for `WM_SETFONT`. Stock fonts like `DEFAULT_GUI_FONT` stay at 100%.
+pub fn font(dpi: u32) -> HGDIOBJ {
+    let mut fonts = FONTS.lock().unwrap();
+    let font = fonts
+        .entry(dpi)
+        .or_insert_with(|| create_font(-window_system::scale(FONT_HEIGHT, dpi), FW_NORMAL, FONT_FACE).0 as isize);
+    HFONT(*font as *mut _).into()
+}
+
+/// The DPI the window's children were last laid out for.
+pub fn window_dpi(hwnd: HWND) -> u32 {
+    match get_window_long_ptr(hwnd, GWLP_USERDATA) {
+        Ok(dpi) if dpi > 0 => dpi as u32,
+        _ => DEFAULT_DPI,
+    }
+}
+
+/// Scales a window created with a size laid out at 100% for the DPI of its monitor, and remembers the DPI for
+/// laying out its children. Call from `WM_CREATE`.
+pub fn init(hwnd: HWND) {
+    let dpi = get_dpi_for_window(hwnd);
+    let _ = set_window_long_ptr(hwnd, GWLP_USERDATA, dpi as isize);
+    if dpi == DEFAULT_DPI {
+        return;
+    }
+    if let Ok(rect) = get_window_rect(hwnd) {
+        let width = window_system::scale(rect.right - rect.left, dpi);
+        let height = window_system::scale(rect.bottom - rect.top, dpi);
+        let _ = set_window_pos(hwnd, None, 0, 0, width, height, SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE);
+    }
+}
+
+/// Scales a length laid out at 100% for the window's DPI.
+pub fn scale(hwnd: HWND, pixels: i32) -> i32 {
+    window_system::scale(pixels, window_dpi(hwnd))
+}
+
+/// Handles `WM_DPICHANGED`, which only Per-Monitor aware windows get.
+///
+/// Scales the children by the change and gives those using the message font the one for the new DPI, then moves
+/// the window to the rect Windows suggests, which keeps it the same size on screen.
+pub fn on_dpi_changed(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) {
+    let (old, new) = (window_dpi(hwnd), (wparam.0 & 0xFFFF) as u32);
+    let _ = set_window_long_ptr(hwnd, GWLP_USERDATA, new as isize);
+    let (old_font, new_font) = (font(old), font(new));
+    let resize = |pixels: i32| pixels * new as i32 / old as i32;
+
+    // Only direct children, since controls position their own
+    for child in enum_child_windows(hwnd) {
+        if get_parent(child).ok() != Some(hwnd) {
+            continue;
+        }
+        if let Ok(rect) = get_window_rect(child) {
+            let mut points = [
+                POINT { x: rect.left, y: rect.top },
+                POINT {
+                    x: rect.right,
+                    y: rect.bottom,
+                },
+            ];
+            map_window_points(None, Some(hwnd), &mut points);
+            let [top_left, bottom_right] = points;
+            let _ = set_window_pos(
+                child,
+                None,
+                resize(top_left.x),
+                resize(top_left.y),
+                resize(bottom_right.x - top_left.x),
+                resize(bottom_right.y - top_left.y),
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+        }
+        if send_message(child, WM_GETFONT, WPARAM(0), LPARAM(0)).0 == old_font.0 as isize {
+            send_message(child, WM_SETFONT, WPARAM(new_font.0 as usize), LPARAM(1));
+        }
+    }
+
+    let suggested = unsafe { &*(lparam.0 as *const RECT) };
+    let _ = set_window_pos(
+        hwnd,
+        None,
+        suggested.left,
+        suggested.top,
+        suggested.right - suggested.left,
+        suggested.bottom - suggested.top,
+        SWP_NOZORDER | SWP_NOACTIVATE,
+    );
+}
@@ -0,0 +1,218 @@
+#[cfg(any(windows, test))]
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(any(windows, test))]
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use windows::Win32::Foundation::E_ACCESSDENIED;
+
+use crate::hotkey_action::Action;
+
+/// What went wrong, without the details, so that notifications can be rate limited and muted per kind.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum ErrorKind {
+    AccessDenied,
+    WindowPlacement,
+    Monitors,
+    Launch,
+}
+
+impl ErrorKind {
+    /// Names the kind in the "don't show again" prompt.
+    #[cfg(windows)]
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorKind::AccessDenied => "windows that can't be moved because they're running as administrator",
+            ErrorKind::WindowPlacement => "windows that can't be moved or resized",
+            ErrorKind::Monitors => "monitors that can't be listed",
+            ErrorKind::Launch => "programs that can't be launched",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ActionError {
+    /// Windows doesn't let an unelevated process move an elevated process' windows.
+    AccessDenied(eyre::Report),
+    WindowPlacement(eyre::Report),
+    Monitors(eyre::Report),
+    Launch {
+        command: String,
+        source: eyre::Report,
+    },
+    SequenceStep {
+        step: usize,
+        source: Box<ActionError>,
+    },
+}
+
+impl ActionError {
+    /// Categorizes a failure of `action`, which isn't a `Sequence`.
+    pub fn new(action: &Action, report: eyre::Report) -> ActionError {
+        if is_access_denied(&report) {
+            return ActionError::AccessDenied(report);
+        }
+        match action {
+            Action::Launch { command, .. } | Action::FocusOrLaunch { command, .. } => ActionError::Launch {
+                command: command.clone(),
+                source: report,
+            },
+            Action::MoveNextMonitor
+            | Action::MovePrevMonitor
+            | Action::GatherAllToMonitor { .. }
+            | Action::RescueWindows => ActionError::Monitors(report),
+            _ => ActionError::WindowPlacement(report),
+        }
+    }
+
+    #[cfg(windows)]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ActionError::AccessDenied(_) => ErrorKind::AccessDenied,
+            ActionError::WindowPlacement(_) => ErrorKind::WindowPlacement,
+            ActionError::Monitors(_) => ErrorKind::Monitors,
+            ActionError::Launch { .. } => ErrorKind::Launch,
+            ActionError::SequenceStep { source, .. } => source.kind(),
+        }
+    }
+}
+
+fn is_access_denied(report: &eyre::Report) -> bool {
+    report.chain().any(|error| {
+        is_win32_access_denied(error)
+            || error
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|error| error.kind() == std::io::ErrorKind::PermissionDenied)
+    })
+}
+
+#[cfg(windows)]
+fn is_win32_access_denied(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<windows::core::Error>()
+        .is_some_and(|error| error.code() == E_ACCESSDENIED)
+}
+
+#[cfg(not(windows))]
+fn is_win32_access_denied(_error: &(dyn std::error::Error + 'static)) -> bool {
+    false
+}
+
+impl std::fmt::Display for ActionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionError::AccessDenied(_) => {
+                write!(
+                    f,
+                    "Windows doesn't allow moving this window, probably because it's running as administrator"
+                )
+            }
+            ActionError::WindowPlacement(source) => write!(f, "Couldn't move the window: {}", source),
+            ActionError::Monitors(source) => write!(f, "Couldn't list the monitors: {}", source),
+            ActionError::Launch { command, source } => write!(f, "Couldn't launch '{}': {}", command, source),
+            ActionError::SequenceStep { step, source } => write!(f, "Sequence step {} failed: {}", step, source),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ActionError::AccessDenied(source)
+            | ActionError::WindowPlacement(source)
+            | ActionError::Monitors(source)
+            | ActionError::Launch { source, .. } => Some(source.as_ref()),
+            ActionError::SequenceStep { source, .. } => Some(source.as_ref()),
+        }
+    }
+}
+
+/// Decides which errors become notifications: at most one per kind per `interval`, and none for muted kinds.
+#[cfg(any(windows, test))]
+pub struct ErrorNotifier {
+    enabled: bool,
+    interval: Duration,
+    muted: BTreeSet<ErrorKind>,
+    last_shown: BTreeMap<ErrorKind, Instant>,
+}
+
+#[cfg(any(windows, test))]
+impl ErrorNotifier {
+    pub const fn new() -> ErrorNotifier {
+        ErrorNotifier {
+            enabled: true,
+            interval: Duration::from_secs(30),
+            muted: BTreeSet::new(),
+            last_shown: BTreeMap::new(),
+        }
+    }
+
+    pub fn configure(&mut self, enabled: bool, interval: Duration, muted: BTreeSet<ErrorKind>) {
+        self.enabled = enabled;
+        self.interval = interval;
+        self.muted = muted;
+    }
+
+    pub fn should_show(&mut self, kind: ErrorKind, now: Instant) -> bool {
+        if !self.enabled || self.muted.contains(&kind) {
+            return false;
+        }
+        if let Some(&last_shown) = self.last_shown.get(&kind) {
+            if now.duration_since(last_shown) < self.interval {
+                return false;
+            }
+        }
+        self.last_shown.insert(kind, now);
+        true
+    }
+
+    pub fn mute(&mut self, kind: ErrorKind) {
+        self.muted.insert(kind);
+    }
+
+    #[cfg(windows)]
+    pub fn muted(&self) -> &BTreeSet<ErrorKind> {
+        &self.muted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_secs(30);
+
+    fn notifier() -> ErrorNotifier {
+        let mut notifier = ErrorNotifier::new();
+        notifier.configure(true, INTERVAL, BTreeSet::new());
+        notifier
+    }
+
+    #[test]
+    fn repeats_of_a_kind_wait_for_the_interval() {
+        let mut notifier = notifier();
+        let start = Instant::now();
+        assert!(notifier.should_show(ErrorKind::WindowPlacement, start));
+        assert!(!notifier.should_show(ErrorKind::WindowPlacement, start + INTERVAL / 2));
+        assert!(notifier.should_show(ErrorKind::Launch, start + INTERVAL / 2));
+        assert!(notifier.should_show(ErrorKind::WindowPlacement, start + INTERVAL));
+    }
+
+    #[test]
+    fn muted_kinds_are_never_shown() {
+        let mut notifier = notifier();
+        notifier.mute(ErrorKind::AccessDenied);
+        let start = Instant::now();
+        assert!(!notifier.should_show(ErrorKind::AccessDenied, start));
+        assert!(!notifier.should_show(ErrorKind::AccessDenied, start + INTERVAL * 2));
+        assert!(notifier.should_show(ErrorKind::Monitors, start));
+    }
+
+    #[test]
+    fn nothing_is_shown_when_disabled() {
+        let mut notifier = ErrorNotifier::new();
+        notifier.configure(false, INTERVAL, BTreeSet::new());
+        assert!(!notifier.should_show(ErrorKind::Launch, Instant::now()));
+    }
+}
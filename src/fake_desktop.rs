@@ -0,0 +1,443 @@
+use std::cell::RefCell;
+
+use eyre::eyre;
+use serde::Deserialize;
+
+use crate::geometry::{Margins, Point, Rect, Size};
+use crate::window_match::WindowInfo;
+use crate::window_system::{
+    monitor_from_rect, Decorations, Layering, Monitor, ShowState, Translucency, WindowId, WindowSystem, DEFAULT_DPI,
+};
+
+/// Where Windows parks minimized windows.
+const MINIMIZED_RECT: Rect = Rect::from_size(-32000, -32000, 160, 28);
+
+/// A change `FakeDesktop` was asked to make, in the order they were made.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Call {
+    SetForegroundWindow(WindowId),
+    SetWindowRect(WindowId, Rect),
+    SetShowState(WindowId, ShowState),
+    BringToTop(WindowId),
+    SetTopmost(WindowId, bool),
+    SetBorderless(WindowId, bool),
+    SetLayering(WindowId, Layering),
+    SetTranslucency(WindowId, Translucency),
+    SetCursorPos(Point),
+}
+
+/// What a top level window is, for `WindowSystem::is_app_window`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowKind {
+    #[default]
+    App,
+    /// The desktop behind every window, as big as all the monitors together.
+    Desktop,
+    Taskbar,
+    Tool,
+    /// Owned by another window, e.g. a dialog.
+    Owned,
+    /// On another virtual desktop.
+    Cloaked,
+}
+
+#[derive(Clone, Debug)]
+pub struct FakeWindow {
+    pub id: WindowId,
+    pub info: WindowInfo,
+    /// Run as administrator, so its process can't be opened and `window_info` fails.
+    pub elevated: bool,
+    pub kind: WindowKind,
+    /// The window rect, including `border`.
+    pub rect: Rect,
+    /// The invisible resize borders between the window rect and the visible frame.
+    pub border: Margins,
+    pub show_state: ShowState,
+    /// Where the window goes when it's restored.
+    pub normal_rect: Rect,
+    pub visible: bool,
+    pub topmost: bool,
+    /// Without a caption and frame, and so without invisible borders either.
+    pub borderless: bool,
+    /// Layered by the app itself, or by being made see-through.
+    pub layered: bool,
+    pub translucency: Translucency,
+    /// The DPI of the monitor the window was last moved to, which its size and borders are scaled for.
+    pub dpi: u32,
+    /// The smallest and largest window rect the app accepts, including `border`.
+    pub min_size: Option<Size>,
+    pub max_size: Option<Size>,
+}
+
+impl FakeWindow {
+    pub fn frame_bounds(&self) -> Rect {
+        self.rect.inset(self.border)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    monitors: Vec<Monitor>,
+    /// In z-order from the top.
+    windows: Vec<FakeWindow>,
+    foreground: Option<WindowId>,
+    cursor: Point,
+    next_id: usize,
+    calls: Vec<Call>,
+}
+
+impl State {
+    fn window(&self, id: WindowId) -> eyre::Result<&FakeWindow> {
+        self.windows
+            .iter()
+            .find(|window| window.id == id)
+            .ok_or_else(|| eyre!("No window {:?}", id))
+    }
+
+    fn window_mut(&mut self, id: WindowId) -> eyre::Result<&mut FakeWindow> {
+        self.windows
+            .iter_mut()
+            .find(|window| window.id == id)
+            .ok_or_else(|| eyre!("No window {:?}", id))
+    }
+
+    fn monitor_from_rect(&self, rect: &Rect) -> eyre::Result<Monitor> {
+        monitor_from_rect(&self.monitors, rect).ok_or_else(|| eyre!("No monitors"))
+    }
+
+    /// Moves the window to the top of its band, topmost windows always staying above the others.
+    fn raise(&mut self, id: WindowId) -> eyre::Result<()> {
+        let i = self
+            .windows
+            .iter()
+            .position(|window| window.id == id)
+            .ok_or_else(|| eyre!("No window {:?}", id))?;
+        let window = self.windows.remove(i);
+        let position = match window.topmost {
+            true => 0,
+            false => self.windows.iter().take_while(|window| window.topmost).count(),
+        };
+        self.windows.insert(position, window);
+        Ok(())
+    }
+}
+
+/// An in-memory desktop for running actions without Windows.
+///
+/// It keeps just enough state to answer the queries actions make: window rects, show states and z-order, the
+/// foreground window and the cursor. Every change is recorded in `calls`.
+#[derive(Debug, Default)]
+pub struct FakeDesktop {
+    state: RefCell<State>,
+}
+
+impl FakeDesktop {
+    /// The first monitor is the primary one.
+    pub fn new(monitors: Vec<Monitor>) -> FakeDesktop {
+        FakeDesktop {
+            state: RefCell::new(State {
+                monitors,
+                next_id: 1,
+                ..State::default()
+            }),
+        }
+    }
+
+    /// Opens a visible window at `rect` on top of the others, and focuses it.
+    pub fn add_window(&self, info: WindowInfo, rect: Rect) -> WindowId {
+        let mut state = self.state.borrow_mut();
+        let dpi = state
+            .monitor_from_rect(&rect)
+            .map_or(DEFAULT_DPI, |monitor| monitor.dpi);
+        let id = WindowId(state.next_id);
+        state.next_id += 1;
+        let position = state.windows.iter().take_while(|window| window.topmost).count();
+        state.windows.insert(
+            position,
+            FakeWindow {
+                id,
+                info,
+                elevated: false,
+                kind: WindowKind::App,
+                rect,
+                border: Margins::default(),
+                show_state: ShowState::Normal,
+                normal_rect: rect,
+                visible: true,
+                topmost: false,
+                borderless: false,
+                layered: false,
+                translucency: Translucency::OPAQUE,
+                dpi,
+                min_size: None,
+                max_size: None,
+            },
+        );
+        state.foreground = Some(id);
+        id
+    }
+
+    /// Gives the window invisible resize borders, growing its window rect so the visible frame stays put.
+    pub fn set_border(&self, id: WindowId, border: Margins) -> eyre::Result<()> {
+        let mut state = self.state.borrow_mut();
+        let window = state.window_mut(id)?;
+        let frame = window.frame_bounds();
+        window.border = border;
+        window.rect = frame.outset(border);
+        if window.show_state == ShowState::Normal {
+            window.normal_rect = window.rect;
+        }
+        Ok(())
+    }
+
+    /// Limits the window's size, as an app does on `WM_GETMINMAXINFO`. It's not resized until it's next moved.
+    pub fn set_size_limits(&self, id: WindowId, min_size: Option<Size>, max_size: Option<Size>) -> eyre::Result<()> {
+        let mut state = self.state.borrow_mut();
+        let window = state.window_mut(id)?;
+        window.min_size = min_size;
+        window.max_size = max_size;
+        Ok(())
+    }
+
+    pub fn set_elevated(&self, id: WindowId, elevated: bool) -> eyre::Result<()> {
+        self.state
+            .borrow_mut()
+            .window_mut(id)
+            .map(|window| window.elevated = elevated)
+    }
+
+    pub fn set_kind(&self, id: WindowId, kind: WindowKind) -> eyre::Result<()> {
+        self.state.borrow_mut().window_mut(id).map(|window| window.kind = kind)
+    }
+
+    pub fn set_visible(&self, id: WindowId, visible: bool) -> eyre::Result<()> {
+        self.state
+            .borrow_mut()
+            .window_mut(id)
+            .map(|window| window.visible = visible)
+    }
+
+    /// Moves the cursor without recording a call, as the user would.
+    pub fn move_cursor(&self, point: Point) {
+        self.state.borrow_mut().cursor = point;
+    }
+
+    pub fn window(&self, id: WindowId) -> eyre::Result<FakeWindow> {
+        self.state.borrow().window(id).cloned()
+    }
+
+    pub fn foreground(&self) -> Option<WindowId> {
+        self.state.borrow().foreground
+    }
+
+    pub fn cursor(&self) -> Point {
+        self.state.borrow().cursor
+    }
+
+    pub fn calls(&self) -> Vec<Call> {
+        self.state.borrow().calls.clone()
+    }
+
+    fn record(&self, call: Call) {
+        self.state.borrow_mut().calls.push(call);
+    }
+}
+
+impl WindowSystem for FakeDesktop {
+    fn foreground_window(&self) -> eyre::Result<WindowId> {
+        self.state
+            .borrow()
+            .foreground
+            .ok_or_else(|| eyre!("No foreground window"))
+    }
+
+    fn set_foreground_window(&self, window: WindowId) -> eyre::Result<()> {
+        self.record(Call::SetForegroundWindow(window));
+        let mut state = self.state.borrow_mut();
+        state.raise(window)?;
+        state.foreground = Some(window);
+        Ok(())
+    }
+
+    fn monitors(&self) -> eyre::Result<Vec<Monitor>> {
+        Ok(self.state.borrow().monitors.clone())
+    }
+
+    fn monitor_from_window(&self, window: WindowId) -> eyre::Result<Monitor> {
+        let state = self.state.borrow();
+        state.monitor_from_rect(&state.window(window)?.rect)
+    }
+
+    fn window_rect(&self, window: WindowId) -> eyre::Result<Rect> {
+        self.state.borrow().window(window).map(|window| window.rect)
+    }
+
+    fn frame_bounds(&self, window: WindowId) -> eyre::Result<Rect> {
+        self.state.borrow().window(window).map(FakeWindow::frame_bounds)
+    }
+
+    fn set_window_rect(&self, window: WindowId, rect: Rect) -> eyre::Result<()> {
+        self.record(Call::SetWindowRect(window, rect));
+        let mut state = self.state.borrow_mut();
+        let dpi = state.monitor_from_rect(&rect)?.dpi;
+        let window = state.window_mut(window)?;
+        window.rect = rect;
+        // As a Per-Monitor DPI aware app does on WM_DPICHANGED, keep the size the window had at the old DPI
+        if dpi != window.dpi {
+            let scale = |pixels: i32| pixels * dpi as i32 / window.dpi as i32;
+            let border = window.border;
+            window.border =
+                Margins::new(scale(border.left), scale(border.top), scale(border.right), scale(border.bottom));
+            window.rect = Rect::from_size(rect.left, rect.top, scale(rect.width()), scale(rect.height()));
+            window.dpi = dpi;
+        }
+        // Windows clamps the size to the app's limits, keeping the top left corner
+        let clamp = |length: i32, min: Option<i32>, max: Option<i32>| {
+            let length = max.map_or(length, |max| length.min(max));
+            min.map_or(length, |min| length.max(min))
+        };
+        let width = clamp(
+            window.rect.width(),
+            window.min_size.map(|size| size.width),
+            window.max_size.map(|size| size.width),
+        );
+        let height = clamp(
+            window.rect.height(),
+            window.min_size.map(|size| size.height),
+            window.max_size.map(|size| size.height),
+        );
+        window.rect = Rect::from_size(window.rect.left, window.rect.top, width, height);
+        if window.show_state == ShowState::Normal {
+            window.normal_rect = window.rect;
+        }
+        Ok(())
+    }
+
+    fn show_state(&self, window: WindowId) -> ShowState {
+        self.state
+            .borrow()
+            .window(window)
+            .map_or(ShowState::Normal, |window| window.show_state)
+    }
+
+    fn set_show_state(&self, window: WindowId, show_state: ShowState) -> eyre::Result<()> {
+        self.record(Call::SetShowState(window, show_state));
+        let mut state = self.state.borrow_mut();
+        let normal_rect = state.window(window)?.normal_rect;
+        let rect = match show_state {
+            ShowState::Normal => normal_rect,
+            ShowState::Minimized => MINIMIZED_RECT,
+            ShowState::Maximized => {
+                let window = state.window(window)?;
+                let monitor = state.monitor_from_rect(&normal_rect)?;
+                monitor.work_area.outset(window.border)
+            }
+        };
+        let window = state.window_mut(window)?;
+        window.show_state = show_state;
+        window.rect = rect;
+        Ok(())
+    }
+
+    fn bring_to_top(&self, window: WindowId) -> eyre::Result<()> {
+        self.record(Call::BringToTop(window));
+        self.state.borrow_mut().raise(window)
+    }
+
+    fn set_topmost(&self, window: WindowId, topmost: bool) -> eyre::Result<()> {
+        self.record(Call::SetTopmost(window, topmost));
+        let mut state = self.state.borrow_mut();
+        state.window_mut(window)?.topmost = topmost;
+        state.raise(window)
+    }
+
+    fn is_topmost(&self, window: WindowId) -> bool {
+        self.state.borrow().window(window).is_ok_and(|window| window.topmost)
+    }
+
+    /// The decorations are the borders the frame had, which go with it.
+    fn remove_decorations(&self, window: WindowId) -> eyre::Result<Decorations> {
+        self.record(Call::SetBorderless(window, true));
+        let mut state = self.state.borrow_mut();
+        let window = state.window_mut(window)?;
+        let border = window.border;
+        window.borderless = true;
+        window.border = Margins::default();
+        Ok(Decorations(
+            [border.left, border.top, border.right, border.bottom]
+                .map(|margin| margin as u32)
+                .to_vec(),
+        ))
+    }
+
+    fn set_decorations(&self, window: WindowId, decorations: &Decorations) -> eyre::Result<()> {
+        self.record(Call::SetBorderless(window, false));
+        let [left, top, right, bottom] = decorations.0[..] else {
+            return Err(eyre!("Not fake decorations: {:?}", decorations));
+        };
+        let mut state = self.state.borrow_mut();
+        let window = state.window_mut(window)?;
+        window.borderless = false;
+        window.border = Margins::new(left as i32, top as i32, right as i32, bottom as i32);
+        Ok(())
+    }
+
+    /// The layering is whether the window is layered.
+    fn layering(&self, window: WindowId) -> eyre::Result<Layering> {
+        let layered = self.state.borrow().window(window)?.layered;
+        Ok(Layering(vec![layered as u32]))
+    }
+
+    fn set_layering(&self, window: WindowId, layering: &Layering) -> eyre::Result<()> {
+        self.record(Call::SetLayering(window, layering.clone()));
+        let mut state = self.state.borrow_mut();
+        let window = state.window_mut(window)?;
+        window.layered = layering.0 == [1];
+        window.translucency = Translucency::OPAQUE;
+        Ok(())
+    }
+
+    fn set_translucency(&self, window: WindowId, translucency: Translucency) -> eyre::Result<()> {
+        self.record(Call::SetTranslucency(window, translucency));
+        let mut state = self.state.borrow_mut();
+        let window = state.window_mut(window)?;
+        window.layered = true;
+        window.translucency = translucency;
+        Ok(())
+    }
+
+    fn cursor_pos(&self) -> eyre::Result<Point> {
+        Ok(self.state.borrow().cursor)
+    }
+
+    fn set_cursor_pos(&self, point: Point) -> eyre::Result<()> {
+        self.record(Call::SetCursorPos(point));
+        self.state.borrow_mut().cursor = point;
+        Ok(())
+    }
+
+    fn windows(&self) -> eyre::Result<Vec<WindowId>> {
+        Ok(self.state.borrow().windows.iter().map(|window| window.id).collect())
+    }
+
+    fn is_visible(&self, window: WindowId) -> bool {
+        self.state.borrow().window(window).is_ok_and(|window| window.visible)
+    }
+
+    fn is_app_window(&self, window: WindowId) -> bool {
+        self.state
+            .borrow()
+            .window(window)
+            .is_ok_and(|window| window.kind == WindowKind::App)
+    }
+
+    fn window_info(&self, window: WindowId) -> eyre::Result<WindowInfo> {
+        let state = self.state.borrow();
+        let window = state.window(window)?;
+        match window.elevated {
+            true => Err(eyre!("Access is denied to {:?}'s process", window.id)),
+            false => Ok(window.info.clone()),
+        }
+    }
+}
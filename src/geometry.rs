@@ -0,0 +1,631 @@
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use windows::Win32::Foundation::{POINT, RECT};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub const fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Size {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Size {
+    pub const fn new(width: i32, height: i32) -> Size {
+        Size { width, height }
+    }
+}
+
+/// A rectangle for layout arithmetic, independent of the Win32 `RECT` it converts to and from.
+///
+/// Rects are half-open: `right` and `bottom` are one past the last column and row, as they are in Win32. A rect with
+/// no width or height is empty.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+/// Space to take off, or add to, each side of a rect.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Margins {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Margins {
+    pub const fn new(left: i32, top: i32, right: i32, bottom: i32) -> Margins {
+        Margins { left, top, right, bottom }
+    }
+
+    #[allow(dead_code)]
+    pub const fn uniform(margin: i32) -> Margins {
+        Margins::new(margin, margin, margin, margin)
+    }
+}
+
+/// Which end of a region a rect keeps to along one axis when its size changes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Anchor {
+    Start,
+    Center,
+    End,
+}
+
+impl Anchor {
+    /// A span flush with only the start or only the end of `bounds` keeps to that end. One flush with both, or
+    /// with neither, keeps its center.
+    fn of((start, end): (i32, i32), (bounds_start, bounds_end): (i32, i32)) -> Anchor {
+        match (start == bounds_start, end == bounds_end) {
+            (true, false) => Anchor::Start,
+            (false, true) => Anchor::End,
+            _ => Anchor::Center,
+        }
+    }
+
+    /// Places a span of `length` at this end of the span from `start` to `end`, overflowing it at the other end.
+    fn place(&self, (start, end): (i32, i32), length: i32) -> (i32, i32) {
+        let start = match self {
+            Anchor::Start => start,
+            Anchor::Center => start + (end - start - length).div_euclid(2),
+            Anchor::End => end - length,
+        };
+        (start, start + length)
+    }
+}
+
+/// The direction a rect is split along. `Horizontal` gives side by side parts, `Vertical` stacked ones.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Rect {
+    pub const fn new(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect { left, top, right, bottom }
+    }
+
+    pub const fn from_size(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect::new(x, y, x + width, y + height)
+    }
+
+    /// The rect with `p0` and `p1` at opposite corners.
+    pub fn from_points(p0: Point, p1: Point) -> Rect {
+        Rect::new(p0.x.min(p1.x), p0.y.min(p1.y), p0.x.max(p1.x), p0.y.max(p1.y))
+    }
+
+    pub const fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    pub const fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    pub const fn size(&self) -> Size {
+        Size::new(self.width(), self.height())
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.width() <= 0 || self.height() <= 0
+    }
+
+    /// Zero for empty rects. Wide enough that no pair of `i32` sides overflows it.
+    pub fn area(&self) -> i64 {
+        match self.is_empty() {
+            true => 0,
+            false => self.width() as i64 * self.height() as i64,
+        }
+    }
+
+    /// Rounds toward the top left.
+    pub fn center(&self) -> Point {
+        Point::new(midpoint(self.left, self.right), midpoint(self.top, self.bottom))
+    }
+
+    pub const fn top_left(&self) -> Point {
+        Point::new(self.left, self.top)
+    }
+
+    pub const fn bottom_right(&self) -> Point {
+        Point::new(self.right, self.bottom)
+    }
+
+    pub const fn translate(&self, dx: i32, dy: i32) -> Rect {
+        Rect::new(self.left + dx, self.top + dy, self.right + dx, self.bottom + dy)
+    }
+
+    /// The overlapping part, or `None` if the rects only touch or don't meet at all.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let rect = Rect::new(
+            self.left.max(other.left),
+            self.top.max(other.top),
+            self.right.min(other.right),
+            self.bottom.min(other.bottom),
+        );
+        (!rect.is_empty()).then_some(rect)
+    }
+
+    /// Half-open, like `PtInRect`: the right and bottom edges are outside.
+    pub fn contains_point(&self, point: Point) -> bool {
+        point.x >= self.left && point.x < self.right && point.y >= self.top && point.y < self.bottom
+    }
+
+    /// The fraction of this rect that `other` covers, from 0 to 1. Zero for an empty rect.
+    pub fn overlap_ratio(&self, other: &Rect) -> f64 {
+        match (self.area(), self.intersection(other)) {
+            (0, _) | (_, None) => 0.0,
+            (area, Some(overlap)) => overlap.area() as f64 / area as f64,
+        }
+    }
+
+    /// Shrinks each side by its margin. A margin larger than the rect collapses it to zero size at the midpoint of
+    /// the sides, rather than turning it inside out.
+    pub fn inset(&self, margins: Margins) -> Rect {
+        let (left, right) = shrink(self.left + margins.left, self.right - margins.right);
+        let (top, bottom) = shrink(self.top + margins.top, self.bottom - margins.bottom);
+        Rect::new(left, top, right, bottom)
+    }
+
+    /// Grows each side by its margin.
+    pub fn outset(&self, margins: Margins) -> Rect {
+        self.inset(Margins::new(-margins.left, -margins.top, -margins.right, -margins.bottom))
+    }
+
+    /// Moves the rect inside `bounds`, shrinking it first if it doesn't fit.
+    pub fn clamp_within(&self, bounds: &Rect) -> Rect {
+        let width = self.width().min(bounds.width()).max(0);
+        let height = self.height().min(bounds.height()).max(0);
+        let left = self.left.clamp(bounds.left, (bounds.right - width).max(bounds.left));
+        let top = self.top.clamp(bounds.top, (bounds.bottom - height).max(bounds.top));
+        Rect::from_size(left, top, width, height)
+    }
+
+    /// The horizontal and vertical anchors of the rect within `bounds`, from the edges of `bounds` it's flush with.
+    pub fn anchors(&self, bounds: &Rect) -> (Anchor, Anchor) {
+        (
+            Anchor::of(self.span(Axis::Horizontal), bounds.span(Axis::Horizontal)),
+            Anchor::of(self.span(Axis::Vertical), bounds.span(Axis::Vertical)),
+        )
+    }
+
+    /// A rect of `size` in place of this one, keeping to the same edges of `bounds` this one is flush with.
+    ///
+    /// E.g. a window too wide for the right half of a monitor stays flush with its right edge and overflows to the
+    /// left, and one too small for the top left quarter stays in the corner.
+    pub fn reanchor(&self, size: Size, bounds: &Rect) -> Rect {
+        let (horizontal, vertical) = self.anchors(bounds);
+        let (left, right) = horizontal.place(self.span(Axis::Horizontal), size.width);
+        let (top, bottom) = vertical.place(self.span(Axis::Vertical), size.height);
+        Rect::new(left, top, right, bottom)
+    }
+
+    /// A rect of `size` inside this one, keeping to its edges as the anchors say, e.g. `(Anchor::End, Anchor::End)`
+    /// for the bottom right corner.
+    pub fn align(&self, size: Size, (horizontal, vertical): (Anchor, Anchor)) -> Rect {
+        let (left, right) = horizontal.place(self.span(Axis::Horizontal), size.width);
+        let (top, bottom) = vertical.place(self.span(Axis::Vertical), size.height);
+        Rect::new(left, top, right, bottom)
+    }
+
+    /// The shortest distance between the edges of the rects. Zero when they touch or overlap.
+    pub fn distance(&self, other: &Rect) -> f64 {
+        let dx = (other.left - self.right).max(self.left - other.right).max(0) as f64;
+        let dy = (other.top - self.bottom).max(self.top - other.bottom).max(0) as f64;
+        dx.hypot(dy)
+    }
+
+    fn span(&self, axis: Axis) -> (i32, i32) {
+        match axis {
+            Axis::Horizontal => (self.left, self.right),
+            Axis::Vertical => (self.top, self.bottom),
+        }
+    }
+
+    fn with_span(&self, axis: Axis, start: i32, end: i32) -> Rect {
+        match axis {
+            Axis::Horizontal => Rect::new(start, self.top, end, self.bottom),
+            Axis::Vertical => Rect::new(self.left, start, self.right, end),
+        }
+    }
+
+    fn split_at(&self, axis: Axis, position: i32) -> (Rect, Rect) {
+        let (start, end) = self.span(axis);
+        (self.with_span(axis, start, position), self.with_span(axis, position, end))
+    }
+}
+
+/// Operations no placement uses yet, for layout features to build on.
+#[allow(dead_code)]
+impl Rect {
+    /// The same size, with the top left corner at `point`.
+    pub const fn move_to(&self, point: Point) -> Rect {
+        Rect::from_size(point.x, point.y, self.width(), self.height())
+    }
+
+    /// The smallest rect containing both. Empty rects don't contribute.
+    pub fn union(&self, other: &Rect) -> Rect {
+        match (self.is_empty(), other.is_empty()) {
+            (true, _) => *other,
+            (_, true) => *self,
+            _ => Rect::new(
+                self.left.min(other.left),
+                self.top.min(other.top),
+                self.right.max(other.right),
+                self.bottom.max(other.bottom),
+            ),
+        }
+    }
+
+    pub fn contains(&self, other: &Rect) -> bool {
+        other.left >= self.left && other.top >= self.top && other.right <= self.right && other.bottom <= self.bottom
+    }
+
+    /// Splits into two parts, the first `ratio` of the way along `axis`. `ratio` is clamped to 0..=1.
+    pub fn split(&self, axis: Axis, ratio: f64) -> (Rect, Rect) {
+        let (start, end) = self.span(axis);
+        let length = (end - start).max(0);
+        let first = ((length as f64 * ratio.clamp(0.0, 1.0)).round() as i32).clamp(0, length);
+        self.split_at(axis, start + first)
+    }
+
+    /// Splits into `n` parts along `axis` that differ in size by at most one, the first ones taking the remainder,
+    /// so that together they cover the rect exactly.
+    pub fn split_n(&self, axis: Axis, n: usize) -> Vec<Rect> {
+        if n == 0 {
+            return Vec::new();
+        }
+        let (start, end) = self.span(axis);
+        let length = (end - start).max(0) as i64;
+        let (base, remainder) = (length / n as i64, length % n as i64);
+
+        let mut parts = Vec::with_capacity(n);
+        let mut position = start as i64;
+        for i in 0..n as i64 {
+            let size = base + if i < remainder { 1 } else { 0 };
+            parts.push(self.with_span(axis, position as i32, (position + size) as i32));
+            position += size;
+        }
+        parts
+    }
+
+    /// Maps the rect from its position relative to `from` to the same relative position in `to`, e.g. to carry a
+    /// window between monitors of different sizes.
+    pub fn scale(&self, from: &Rect, to: &Rect) -> Rect {
+        let x = |x: i32| scale_coordinate(x, from.left, from.width(), to.left, to.width());
+        let y = |y: i32| scale_coordinate(y, from.top, from.height(), to.top, to.height());
+        Rect::new(x(self.left), y(self.top), x(self.right), y(self.bottom))
+    }
+}
+
+/// Rounds toward zero, as `(a + b) / 2` always has for placements, without overflowing.
+fn midpoint(a: i32, b: i32) -> i32 {
+    ((a as i64 + b as i64) / 2) as i32
+}
+
+fn shrink(start: i32, end: i32) -> (i32, i32) {
+    match start <= end {
+        true => (start, end),
+        false => {
+            let middle = midpoint(start, end);
+            (middle, middle)
+        }
+    }
+}
+
+fn scale_coordinate(value: i32, from_start: i32, from_length: i32, to_start: i32, to_length: i32) -> i32 {
+    if from_length == 0 {
+        return to_start;
+    }
+    let offset = (value - from_start) as i64 * to_length as i64;
+    // Round to nearest, so that scaling back and forth doesn't drift
+    let scaled = (2 * offset + from_length as i64).div_euclid(2 * from_length as i64);
+    (to_start as i64 + scaled) as i32
+}
+
+#[cfg(windows)]
+impl From<RECT> for Rect {
+    fn from(rect: RECT) -> Rect {
+        Rect::new(rect.left, rect.top, rect.right, rect.bottom)
+    }
+}
+
+#[cfg(windows)]
+impl From<Rect> for RECT {
+    fn from(rect: Rect) -> RECT {
+        RECT {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
+
+#[cfg(windows)]
+impl From<POINT> for Point {
+    fn from(point: POINT) -> Point {
+        Point::new(point.x, point.y)
+    }
+}
+
+#[cfg(windows)]
+impl From<Point> for POINT {
+    fn from(point: Point) -> POINT {
+        POINT { x: point.x, y: point.y }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORK_AREA: Rect = Rect::new(0, 0, 1920, 1040);
+
+    /// A spread of rects, some empty, some off screen, for properties that should hold for any rect.
+    fn rects() -> Vec<Rect> {
+        let mut seed: u32 = 0x9E37_79B9;
+        let mut next = move |range: i32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            (seed % (2 * range as u32 + 1)) as i32 - range
+        };
+        (0..200)
+            .map(|_| Rect::from_size(next(4000), next(4000), next(2000).abs(), next(2000).abs()))
+            .collect()
+    }
+
+    #[test]
+    fn centers_round_toward_zero() {
+        // Halves of odd-sized monitors left of or above the primary one stay where they've always been
+        assert_eq!(Rect::new(0, 0, 1921, 1081).center(), Point::new(960, 540));
+        assert_eq!(Rect::new(-1921, -1081, 0, 0).center(), Point::new(-960, -540));
+        assert_eq!(Rect::new(-1921, 0, -1920, 1).center(), Point::new(-1920, 0));
+    }
+
+    #[test]
+    fn intersection_and_union() {
+        let a = Rect::new(0, 0, 100, 100);
+        let disjoint = Rect::new(200, 200, 300, 300);
+        let touching = Rect::new(100, 0, 200, 100);
+        let contained = Rect::new(25, 25, 75, 75);
+        let overlapping = Rect::new(50, 50, 150, 150);
+
+        assert_eq!(a.intersection(&disjoint), None);
+        assert_eq!(a.intersection(&touching), None);
+        assert_eq!(a.intersection(&contained), Some(contained));
+        assert_eq!(a.intersection(&overlapping), Some(Rect::new(50, 50, 100, 100)));
+
+        assert_eq!(a.union(&disjoint), Rect::new(0, 0, 300, 300));
+        assert_eq!(a.union(&touching), Rect::new(0, 0, 200, 100));
+        assert_eq!(a.union(&contained), a);
+        assert_eq!(a.union(&Rect::new(500, 500, 500, 600)), a);
+    }
+
+    #[test]
+    fn intersections_are_inside_both_and_unions_contain_both() {
+        let rects = rects();
+        for (a, b) in rects.iter().zip(rects.iter().rev()) {
+            if let Some(overlap) = a.intersection(b) {
+                assert!(a.contains(&overlap) && b.contains(&overlap), "{:?} {:?}", a, b);
+            }
+            let union = a.union(b);
+            assert!(a.is_empty() || union.contains(a), "{:?} {:?}", a, b);
+            assert!(b.is_empty() || union.contains(b), "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn contains_includes_the_edges_but_contains_point_does_not() {
+        let a = Rect::new(0, 0, 100, 100);
+        assert!(a.contains(&a));
+        assert!(a.contains(&Rect::new(0, 50, 100, 100)));
+        assert!(!a.contains(&Rect::new(-1, 0, 50, 50)));
+        assert!(!a.contains(&Rect::new(50, 50, 101, 100)));
+        assert!(a.contains_point(Point::new(0, 0)));
+        assert!(a.contains_point(Point::new(99, 99)));
+        assert!(!a.contains_point(Point::new(100, 50)));
+        assert!(!a.contains_point(Point::new(50, 100)));
+        assert!(!a.contains_point(Point::new(-1, 50)));
+    }
+
+    #[test]
+    fn split_divides_at_the_rounded_ratio() {
+        let a = Rect::new(0, 0, 1001, 10);
+        assert_eq!(
+            a.split(Axis::Horizontal, 0.5),
+            (Rect::new(0, 0, 501, 10), Rect::new(501, 0, 1001, 10))
+        );
+        assert_eq!(
+            a.split(Axis::Horizontal, 1.0 / 3.0),
+            (Rect::new(0, 0, 334, 10), Rect::new(334, 0, 1001, 10))
+        );
+        assert_eq!(
+            a.split(Axis::Vertical, 0.3),
+            (Rect::new(0, 0, 1001, 3), Rect::new(0, 3, 1001, 10))
+        );
+        // Ratios outside 0 to 1 leave one part empty rather than spilling outside the rect
+        assert_eq!(a.split(Axis::Horizontal, 1.5), (a, Rect::new(1001, 0, 1001, 10)));
+        assert_eq!(a.split(Axis::Horizontal, -1.0), (Rect::new(0, 0, 0, 10), a));
+    }
+
+    #[test]
+    fn scale_maps_between_reference_rects() {
+        let primary = Rect::new(0, 0, 1920, 1080);
+        let left = Rect::new(-3840, -1080, 0, 1080);
+        assert_eq!(primary.scale(&primary, &left), left);
+        assert_eq!(
+            Rect::new(960, 0, 1920, 540).scale(&primary, &left),
+            Rect::new(-1920, -1080, 0, 0)
+        );
+        // Halves round up whatever the sign, so a window lands the same way relative to any monitor
+        let from = Rect::new(0, 0, 4, 4);
+        assert_eq!(
+            Rect::new(1, 1, 3, 3).scale(&from, &Rect::new(-10, -10, -8, -8)),
+            Rect::new(-9, -9, -8, -8)
+        );
+        assert_eq!(
+            Rect::new(1, 1, 3, 3).scale(&from, &Rect::new(10, 10, 12, 12)),
+            Rect::new(11, 11, 12, 12)
+        );
+        assert_eq!(
+            Rect::new(-1, -1, 1, 1).scale(&from, &Rect::new(-10, -10, -8, -8)),
+            Rect::new(-10, -10, -9, -9)
+        );
+        // An empty reference rect maps everything to the start of the other
+        assert_eq!(
+            Rect::new(5, 5, 50, 50).scale(&Rect::new(0, 0, 0, 0), &left),
+            Rect::new(-3840, -1080, -3840, -1080)
+        );
+    }
+
+    #[test]
+    fn scaling_up_and_back_down_is_exact() {
+        let primary = Rect::new(0, 0, 1920, 1080);
+        let double = Rect::new(-3840, -2160, 0, 0);
+        for rect in rects() {
+            assert_eq!(rect.scale(&primary, &double).scale(&double, &primary), rect);
+        }
+    }
+
+    #[test]
+    fn split_n_gives_the_remainder_to_the_first_parts() {
+        let widths = |n| {
+            Rect::new(0, 0, 1001, 10)
+                .split_n(Axis::Horizontal, n)
+                .iter()
+                .map(Rect::width)
+                .collect::<Vec<i32>>()
+        };
+        assert_eq!(widths(3), [334, 334, 333]);
+        assert_eq!(widths(4), [251, 250, 250, 250]);
+        assert!(widths(0).is_empty());
+        assert_eq!(Rect::new(0, 0, 2, 2).split_n(Axis::Vertical, 3)[2], Rect::new(0, 2, 2, 2));
+    }
+
+    #[test]
+    fn split_n_covers_the_rect_exactly() {
+        for rect in rects() {
+            for n in 1..=5 {
+                let parts = rect.split_n(Axis::Vertical, n);
+                assert_eq!(parts.first().map(|part| part.top), Some(rect.top));
+                assert_eq!(parts.last().map(|part| part.bottom), Some(rect.bottom));
+                assert!(parts.windows(2).all(|pair| pair[0].bottom == pair[1].top));
+                assert!(parts.iter().all(|part| (part.height() - parts[0].height()).abs() <= 1));
+            }
+        }
+    }
+
+    #[test]
+    fn clamp_within_shrinks_rects_larger_than_their_bounds() {
+        assert_eq!(
+            Rect::new(-100, 500, 2500, 1500).clamp_within(&WORK_AREA),
+            Rect::new(0, 40, 1920, 1040)
+        );
+        assert_eq!(
+            Rect::new(1800, -50, 2600, 550).clamp_within(&WORK_AREA),
+            Rect::new(1120, 0, 1920, 600)
+        );
+        for rect in rects() {
+            assert!(WORK_AREA.contains(&rect.clamp_within(&WORK_AREA)), "{:?}", rect);
+        }
+    }
+
+    #[test]
+    fn outset_undoes_inset() {
+        let margins = Margins::new(7, 0, 7, 7);
+        for rect in rects()
+            .into_iter()
+            .filter(|rect| rect.width() >= 14 && rect.height() >= 7)
+        {
+            assert_eq!(rect.inset(margins).outset(margins), rect);
+            assert_eq!(rect.outset(margins).inset(margins), rect);
+        }
+        // Too small to take the margins, it collapses rather than turning inside out
+        assert_eq!(Rect::new(0, 0, 10, 10).inset(Margins::uniform(8)), Rect::new(5, 5, 5, 5));
+    }
+
+    #[test]
+    fn overlap_ratio_is_the_covered_fraction() {
+        let a = Rect::new(0, 0, 100, 100);
+        assert_eq!(a.overlap_ratio(&a), 1.0);
+        assert_eq!(a.overlap_ratio(&Rect::new(50, 0, 200, 100)), 0.5);
+        assert_eq!(a.overlap_ratio(&Rect::new(50, 50, 200, 200)), 0.25);
+        assert_eq!(a.overlap_ratio(&Rect::new(100, 0, 200, 100)), 0.0);
+        assert_eq!(Rect::new(10, 10, 10, 50).overlap_ratio(&a), 0.0);
+    }
+
+    #[test]
+    fn distance_is_between_the_nearest_edges() {
+        let a = Rect::new(0, 0, 100, 100);
+        assert_eq!(a.distance(&Rect::new(50, 50, 60, 60)), 0.0);
+        assert_eq!(a.distance(&Rect::new(100, 0, 200, 100)), 0.0);
+        assert_eq!(a.distance(&Rect::new(130, 20, 200, 80)), 30.0);
+        assert_eq!(a.distance(&Rect::new(-50, 140, -30, 200)), 50.0);
+        assert_eq!(a.distance(&Rect::new(130, 140, 200, 200)), 50.0);
+    }
+
+    #[test]
+    fn anchors_follow_the_edges_a_rect_is_flush_with() {
+        let anchors = |rect: Rect| rect.anchors(&WORK_AREA);
+        assert_eq!(anchors(Rect::new(960, 0, 1920, 1040)), (Anchor::End, Anchor::Center));
+        assert_eq!(anchors(Rect::new(0, 0, 960, 520)), (Anchor::Start, Anchor::Start));
+        assert_eq!(anchors(Rect::new(960, 520, 1920, 1040)), (Anchor::End, Anchor::End));
+        assert_eq!(anchors(Rect::new(16, 16, 1904, 1024)), (Anchor::Center, Anchor::Center));
+    }
+
+    #[test]
+    fn too_wide_for_the_right_half_stays_flush_right() {
+        let right_half = Rect::new(960, 0, 1920, 1040);
+        assert_eq!(
+            right_half.reanchor(Size::new(1200, 1040), &WORK_AREA),
+            Rect::new(720, 0, 1920, 1040)
+        );
+    }
+
+    #[test]
+    fn too_small_for_a_corner_stays_in_it() {
+        let bottom_right = Rect::new(960, 520, 1920, 1040);
+        assert_eq!(
+            bottom_right.reanchor(Size::new(600, 400), &WORK_AREA),
+            Rect::new(1320, 640, 1920, 1040)
+        );
+        let top_left = Rect::new(0, 0, 960, 520);
+        assert_eq!(top_left.reanchor(Size::new(600, 400), &WORK_AREA), Rect::new(0, 0, 600, 400));
+    }
+
+    #[test]
+    fn align_keeps_to_the_anchored_edges() {
+        let size = Size::new(480, 270);
+        assert_eq!(
+            WORK_AREA.align(size, (Anchor::End, Anchor::End)),
+            Rect::new(1440, 770, 1920, 1040)
+        );
+        assert_eq!(
+            WORK_AREA.align(size, (Anchor::Start, Anchor::Center)),
+            Rect::new(0, 385, 480, 655)
+        );
+    }
+
+    #[test]
+    fn unanchored_rects_keep_their_center() {
+        let gapped = Rect::new(16, 16, 1904, 1024);
+        assert_eq!(gapped.reanchor(Size::new(801, 600), &WORK_AREA), Rect::new(559, 220, 1360, 820));
+    }
+}
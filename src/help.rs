@@ -0,0 +1,194 @@
+#[cfg(windows)]
+use std::collections::BTreeSet;
+
+#[cfg(windows)]
+use crate::hotkey_action::{self, VK};
+use crate::hotkey_action::{ActionInfo, HotkeyAction, ACTION_INFO};
+#[cfg(windows)]
+use crate::window_match::WindowInfo;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Text,
+    Markdown,
+    Html,
+}
+
+/// An action and the bindings in the keymap that apply it.
+struct Entry<'a> {
+    info: &'static ActionInfo,
+    bindings: Vec<&'a HotkeyAction>,
+}
+
+fn entries(actions: &[HotkeyAction]) -> Vec<Entry<'_>> {
+    ACTION_INFO
+        .iter()
+        .map(|info| Entry {
+            info,
+            bindings: actions
+                .iter()
+                .filter(|action| action.action.name() == info.name)
+                .collect(),
+        })
+        .collect()
+}
+
+/// The chord, binding name and any `when` clause, e.g. `LeftWindows+Left (Left)`.
+fn describe_binding(binding: &HotkeyAction) -> String {
+    match &binding.when {
+        Some(when) => format!("{} ({}, when {})", binding.chord(), binding.name, when),
+        None => format!("{} ({})", binding.chord(), binding.name),
+    }
+}
+
+/// Describes every action and the chords bound to it in `actions`.
+pub fn render(actions: &[HotkeyAction], format: Format) -> String {
+    let entries = entries(actions);
+    match format {
+        Format::Text => render_text(&entries),
+        Format::Markdown => render_markdown(&entries),
+        Format::Html => render_html(&entries),
+    }
+}
+
+/// A line of the cheat sheet overlay.
+#[cfg(windows)]
+pub struct CheatSheetRow {
+    pub chord: String,
+    pub name: String,
+    /// The binding only applies to the foreground app.
+    pub scoped: bool,
+}
+
+/// The binding each chord would fire while `foreground` is active, in keymap order.
+#[cfg(windows)]
+pub fn cheat_sheet(actions: &[HotkeyAction], foreground: Option<&WindowInfo>) -> Vec<CheatSheetRow> {
+    let mut triggers: Vec<&BTreeSet<VK>> = Vec::new();
+    for action in actions {
+        if !triggers.contains(&&action.trigger) {
+            triggers.push(&action.trigger);
+        }
+    }
+
+    triggers
+        .into_iter()
+        .filter_map(|trigger| hotkey_action::find_binding(actions, trigger, foreground))
+        .map(|binding| CheatSheetRow {
+            chord: binding.chord(),
+            name: binding.name.clone(),
+            scoped: binding.when.is_some(),
+        })
+        .collect()
+}
+
+fn render_text(entries: &[Entry]) -> String {
+    let mut text = String::new();
+    for entry in entries {
+        text.push_str(&format!("{}\n    {}\n", entry.info, entry.info.description));
+        for binding in &entry.bindings {
+            text.push_str(&format!("    {}\n", describe_binding(binding)));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn render_markdown(entries: &[Entry]) -> String {
+    let mut text = String::from("# Grist Cheat Sheet\n\n| Action | Description | Bindings |\n| --- | --- | --- |\n");
+    for entry in entries {
+        let bindings = entry
+            .bindings
+            .iter()
+            .map(|binding| describe_binding(binding).replace('|', "\\|"))
+            .collect::<Vec<String>>()
+            .join("<br>");
+        text.push_str(&format!("| `{}` | {} | {} |\n", entry.info, entry.info.description, bindings));
+    }
+    text
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn render_html(entries: &[Entry]) -> String {
+    let mut text = String::from(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Grist Cheat Sheet</title>\n",
+        "<style>body { font-family: sans-serif; } td, th { padding: 4px 8px; text-align: left; vertical-align: top; }",
+        " code { white-space: nowrap; }</style>\n</head>\n<body>\n<h1>Grist Cheat Sheet</h1>\n<table>\n",
+        "<tr><th>Action</th><th>Description</th><th>Bindings</th></tr>\n",
+    ));
+    for entry in entries {
+        let bindings = entry
+            .bindings
+            .iter()
+            .map(|binding| escape_html(&describe_binding(binding)))
+            .collect::<Vec<String>>()
+            .join("<br>");
+        text.push_str(&format!(
+            "<tr><td><code>{}</code></td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&entry.info.to_string()),
+            escape_html(entry.info.description),
+            bindings
+        ));
+    }
+    text.push_str("</table>\n</body>\n</html>\n");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey_action::{Action, VK};
+    use crate::window_match::WindowMatch;
+
+    fn actions() -> Vec<HotkeyAction> {
+        vec![
+            HotkeyAction::new("Big & <bold>", Action::Maximize, &[VK::Up, VK::LeftWindows]),
+            HotkeyAction {
+                when: Some(WindowMatch::exe("a|b.exe")),
+                ..HotkeyAction::new("Hide", Action::Minimize, &[VK::LeftWindows, VK::Down])
+            },
+        ]
+    }
+
+    #[test]
+    fn text_lists_every_action_with_its_bindings() {
+        let text = render(&actions(), Format::Text);
+        assert_eq!(text.matches("\n\n").count(), ACTION_INFO.len());
+        assert!(text.contains("Maximize\n    Maximizes the window\n    LeftWindows+Up (Big & <bold>)\n\n"));
+        assert!(text.contains("Minimize\n    Minimizes the window\n    LeftWindows+Down (Hide, when exe: a|b.exe)\n\n"));
+        assert!(text.contains("MonitorBottom\n    Moves the window to the bottom half of its monitor\n\n"));
+    }
+
+    #[test]
+    fn markdown_is_a_table_row_per_action() {
+        let markdown = render(&actions(), Format::Markdown);
+        assert!(
+            markdown.starts_with("# Grist Cheat Sheet\n\n| Action | Description | Bindings |\n| --- | --- | --- |\n")
+        );
+        assert_eq!(
+            markdown.lines().filter(|line| line.starts_with("| `")).count(),
+            ACTION_INFO.len()
+        );
+        assert!(markdown.contains("| `MonitorBottom` | Moves the window to the bottom half of its monitor |  |\n"));
+        // Pipes would end the cell early
+        assert!(
+            markdown.contains("| `Minimize` | Minimizes the window | LeftWindows+Down (Hide, when exe: a\\|b.exe) |\n")
+        );
+    }
+
+    #[test]
+    fn html_escapes_names_and_matches() {
+        let html = render(&actions(), Format::Html);
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.ends_with("</table>\n</body>\n</html>\n"));
+        assert_eq!(html.matches("<tr><td>").count(), ACTION_INFO.len());
+        assert!(html.contains(
+            "<tr><td><code>Maximize</code></td><td>Maximizes the window</td>\
+             <td>LeftWindows+Up (Big &amp; &lt;bold&gt;)</td></tr>\n"
+        ));
+        assert!(!html.contains("<bold>"));
+        assert!(html.contains("<td><code>SetOpacity { percent: u32 }</code></td>"));
+    }
+}
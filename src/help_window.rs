@@ -0,0 +1,146 @@
+use std::ffi::c_void;
+use std::sync::atomic::AtomicPtr;
+use std::sync::Once;
+
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, ES_AUTOHSCROLL, ES_AUTOVSCROLL, ES_MULTILINE, ES_READONLY, HMENU, MB_OK,
+    MF_BYPOSITION, MF_POPUP, MF_STRING, SWP_NOZORDER, WINDOW_EX_STYLE, WINDOW_STYLE, WM_COMMAND, WM_CREATE, WM_DESTROY,
+    WM_DPICHANGED, WM_SETFONT, WM_SIZE, WNDCLASSW, WS_CHILD, WS_EX_CLIENTEDGE, WS_HSCROLL, WS_OVERLAPPEDWINDOW,
+    WS_VISIBLE, WS_VSCROLL,
+};
+
+use crate::dpi;
+use crate::help::{self, Format};
+use crate::safe_win32::{
+    create_menu, create_popup_menu, create_window, def_window_proc, get_dlg_item, get_module_handle,
+    get_save_file_name, insert_menu, message_box, register_class, send_message, set_foreground_window, set_window_pos,
+    set_window_text,
+};
+use crate::{log, ACTIONS};
+
+const CLASS_NAME: &str = "GristHelp";
+const EDIT_ID: i32 = 0x01;
+const MENU_EXPORT_MARKDOWN: usize = 0x01;
+const MENU_EXPORT_HTML: usize = 0x02;
+
+static HELP_HWND: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static REGISTER_CLASS: Once = Once::new();
+
+/// Opens the help window, or refreshes and raises it if it's already open.
+pub fn show() -> eyre::Result<()> {
+    let hwnd = HWND(HELP_HWND.load(std::sync::atomic::Ordering::Relaxed));
+    if !hwnd.is_invalid() {
+        refresh(hwnd)?;
+        return set_foreground_window(hwnd);
+    }
+
+    let hinstance = HINSTANCE(get_module_handle(PCWSTR::null())?.0);
+    let class_name = HSTRING::from(CLASS_NAME);
+    let mut registered = Ok(());
+    REGISTER_CLASS.call_once(|| {
+        registered = register_class(&WNDCLASSW {
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(wndproc),
+            hInstance: hinstance,
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            ..Default::default()
+        })
+    });
+    registered?;
+
+    let export_menu = create_popup_menu()?;
+    insert_menu(export_menu, 0, MF_BYPOSITION | MF_STRING, MENU_EXPORT_MARKDOWN, "Markdown...")?;
+    insert_menu(export_menu, 1, MF_BYPOSITION | MF_STRING, MENU_EXPORT_HTML, "HTML...")?;
+    let menu = create_menu()?;
+    insert_menu(menu, 0, MF_BYPOSITION | MF_STRING | MF_POPUP, export_menu.0 as usize, "Export")?;
+
+    create_window(
+        WINDOW_EX_STYLE::default(),
+        PCWSTR(class_name.as_ptr()),
+        PCWSTR(HSTRING::from("Grist Help").as_ptr()),
+        WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+        CW_USEDEFAULT,
+        CW_USEDEFAULT,
+        640,
+        720,
+        None,
+        Some(menu),
+        Some(hinstance),
+        std::ptr::null_mut(),
+    )?;
+    Ok(())
+}
+
+fn refresh(hwnd: HWND) -> eyre::Result<()> {
+    // Edit controls only break lines on CRLF
+    let text = help::render(&ACTIONS.read().unwrap(), Format::Text).replace('\n', "\r\n");
+    set_window_text(get_dlg_item(hwnd, EDIT_ID)?, &text)
+}
+
+fn create_edit(hwnd: HWND) -> eyre::Result<()> {
+    let style = (ES_MULTILINE | ES_READONLY | ES_AUTOVSCROLL | ES_AUTOHSCROLL) as u32;
+    let edit = create_window(
+        WS_EX_CLIENTEDGE,
+        PCWSTR(HSTRING::from("EDIT").as_ptr()),
+        PCWSTR::null(),
+        WS_CHILD | WS_VISIBLE | WS_VSCROLL | WS_HSCROLL | WINDOW_STYLE(style),
+        0,
+        0,
+        0,
+        0,
+        Some(hwnd),
+        Some(HMENU(EDIT_ID as isize as *mut c_void)),
+        None,
+        std::ptr::null_mut(),
+    )?;
+    let font = dpi::font(dpi::window_dpi(hwnd));
+    send_message(edit, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(0));
+    refresh(hwnd)
+}
+
+fn export(hwnd: HWND, format: Format) -> eyre::Result<()> {
+    let (filter, extension) = match format {
+        Format::Text => ("Text (*.txt)\0*.txt\0", "txt"),
+        Format::Markdown => ("Markdown (*.md)\0*.md\0", "md"),
+        Format::Html => ("HTML (*.html)\0*.html\0", "html"),
+    };
+    if let Some(path) = get_save_file_name(hwnd, filter, extension) {
+        std::fs::write(path, help::render(&ACTIONS.read().unwrap(), format))?;
+    }
+    Ok(())
+}
+
+extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_CREATE => {
+            HELP_HWND.store(hwnd.0, std::sync::atomic::Ordering::Relaxed);
+            dpi::init(hwnd);
+            if let Err(error) = create_edit(hwnd) {
+                log!(Error, Wndproc, "{:?}", error);
+            }
+        }
+        WM_SIZE => {
+            let (width, height) = (lparam.0 as u32 & 0xFFFF, (lparam.0 as u32 >> 16) & 0xFFFF);
+            if let Ok(edit) = get_dlg_item(hwnd, EDIT_ID) {
+                let _ = set_window_pos(edit, None, 0, 0, width as i32, height as i32, SWP_NOZORDER);
+            }
+        }
+        WM_DPICHANGED => dpi::on_dpi_changed(hwnd, wparam, lparam),
+        WM_COMMAND => {
+            let result = match wparam.0 {
+                MENU_EXPORT_MARKDOWN => export(hwnd, Format::Markdown),
+                MENU_EXPORT_HTML => export(hwnd, Format::Html),
+                _ => Ok(()),
+            };
+            if let Err(error) = result {
+                message_box(Some(hwnd), &format!("{:?}", error), "Grist Help", MB_OK);
+            }
+        }
+        WM_DESTROY => HELP_HWND.store(std::ptr::null_mut(), std::sync::atomic::Ordering::Relaxed),
+        _ => (),
+    }
+
+    def_window_proc(hwnd, msg, wparam, lparam)
+}
@@ -1,17 +1,31 @@
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
 use std::fmt::Debug;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
-use windows::Win32::Foundation::RECT;
 
-use crate::{monitor, window_actions};
+use crate::error::ActionError;
+use crate::geometry::{Anchor, Rect};
+#[cfg(windows)]
+use crate::ipc::{Event, EVENTS};
+use crate::launch::Launcher;
+#[cfg(windows)]
+use crate::launch::ProcessLauncher;
+use crate::window_match::{WindowInfo, WindowMatch};
+#[cfg(windows)]
+use crate::window_system::Win32WindowSystem;
+use crate::window_system::WindowSystem;
+use crate::{launch, log, monitor, window_actions, IGNORE, PAUSED, RESCUE};
 
 #[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
 pub struct HotkeyAction {
     pub name: String,
     pub action: Action,
     pub trigger: BTreeSet<VK>,
+    /// Only consider this binding while the foreground window matches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<WindowMatch>,
 }
 
 impl Debug for HotkeyAction {
@@ -19,6 +33,7 @@ impl Debug for HotkeyAction {
         f.debug_struct("HotkeyAction")
             .field("name", &self.name)
             .field("trigger", &self.trigger)
+            .field("when", &self.when)
             .finish()
     }
 }
@@ -29,8 +44,81 @@ impl HotkeyAction {
             name: name.to_owned(),
             action,
             trigger: keys.iter().cloned().collect(),
+            when: None,
         }
     }
+
+    fn specificity(&self) -> usize {
+        self.when.as_ref().map_or(0, WindowMatch::specificity)
+    }
+
+    /// Whether the two bindings fire on the same chord for the same windows, so that only one of them ever can.
+    #[cfg(windows)]
+    pub fn conflicts_with(&self, other: &HotkeyAction) -> bool {
+        self.trigger == other.trigger && self.when == other.when
+    }
+
+    /// The trigger as it's written in the config, modifiers first, e.g. `LeftWindows+LeftShift+P`.
+    #[cfg(any(windows, test))]
+    pub fn chord(&self) -> String {
+        let mut keys: Vec<VK> = self.trigger.iter().cloned().collect();
+        keys.sort_by_key(|key| key.modifier_rank());
+        keys.iter()
+            .map(|key| format!("{:?}", key))
+            .collect::<Vec<String>>()
+            .join("+")
+    }
+
+    /// Applies the action unless the foreground window is on the ignore list.
+    #[cfg(windows)]
+    pub fn apply(&self) -> Result<(), ActionError> {
+        self.apply_with(&Win32WindowSystem, &ProcessLauncher)
+    }
+
+    pub fn apply_with(&self, system: &dyn WindowSystem, launcher: &dyn Launcher) -> Result<(), ActionError> {
+        let info = system.foreground_window().and_then(|window| system.window_info(window));
+        if let Some(info) = info
+            .iter()
+            .find(|info| IGNORE.read().unwrap().iter().any(|rule| rule.ignores(&self.name, info)))
+        {
+            log!(Debug, Actions, "Ignoring '{}' for {:?}", self.name, info);
+            return Ok(());
+        }
+
+        log!(Debug, Actions, "Applying '{}'", self.name);
+        self.action.apply_with(system, launcher)?;
+        #[cfg(windows)]
+        if EVENTS.has_subscribers() {
+            EVENTS.publish(Event::ActionFired { name: self.name.clone() });
+        }
+        Ok(())
+    }
+}
+
+/// Finds the binding for `pressed`.
+///
+/// Bindings with a `when` clause are skipped unless it matches `foreground`. When several bindings share the
+/// trigger, the one with the most specific `when` clause wins, and the first one listed breaks ties.
+pub fn find_binding<'a>(
+    actions: &'a [HotkeyAction],
+    pressed: &BTreeSet<VK>,
+    foreground: Option<&WindowInfo>,
+) -> Option<&'a HotkeyAction> {
+    most_specific(actions.iter().filter(|action| action.trigger == *pressed), foreground)
+}
+
+/// Picks the binding whose `when` clause best matches `foreground`, preferring the first one listed.
+pub fn most_specific<'a>(
+    bindings: impl DoubleEndedIterator<Item = &'a HotkeyAction>,
+    foreground: Option<&WindowInfo>,
+) -> Option<&'a HotkeyAction> {
+    bindings
+        .filter(|action| match &action.when {
+            Some(when) => foreground.is_some_and(|info| when.matches(info)),
+            None => true,
+        })
+        .rev()
+        .max_by_key(|action| action.specificity())
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, FromPrimitive, Hash, PartialEq)]
@@ -237,6 +325,18 @@ impl VK {
     fn as_u32(&self) -> u32 {
         *self as u32
     }
+
+    /// Orders modifiers the way Windows writes chords: Windows, Control, Alt, Shift, then everything else.
+    #[cfg(any(windows, test))]
+    fn modifier_rank(&self) -> u32 {
+        match self {
+            VK::LeftWindows | VK::RightWindows => 0,
+            VK::Control | VK::LeftControl | VK::RightControl => 1,
+            VK::Menu | VK::LeftMenu | VK::RightMenu => 2,
+            VK::Shift | VK::LeftShift | VK::RightShift => 3,
+            _ => 4,
+        }
+    }
 }
 
 impl PartialOrd for VK {
@@ -253,9 +353,181 @@ impl Ord for VK {
     }
 }
 
+/// Describes an `Action` variant and its parameters, for `grist list-actions` and the help window.
+#[cfg(any(windows, test))]
+pub struct ActionInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Parameter names and types. A single unnamed parameter is a tuple variant.
+    pub params: &'static [(&'static str, &'static str)],
+}
+
+#[cfg(any(windows, test))]
+impl ActionInfo {
+    const fn new(
+        name: &'static str,
+        description: &'static str,
+        params: &'static [(&'static str, &'static str)],
+    ) -> ActionInfo {
+        ActionInfo { name, description, params }
+    }
+}
+
+#[cfg(any(windows, test))]
+impl std::fmt::Display for ActionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.params {
+            [] => write!(f, "{}", self.name),
+            [("", ty)] => write!(f, "{}({})", self.name, ty),
+            params => {
+                let params = params
+                    .iter()
+                    .map(|(name, ty)| format!("{}: {}", name, ty))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{} {{ {} }}", self.name, params)
+            }
+        }
+    }
+}
+
+#[cfg(any(windows, test))]
+const RECT_PARAMS: &[(&str, &str)] = &[("x", "i32"), ("y", "i32"), ("w", "i32"), ("h", "i32")];
+
+/// Every `Action` variant, in declaration order.
+#[cfg(any(windows, test))]
+pub const ACTION_INFO: &[ActionInfo] = &[
+    ActionInfo::new(
+        "AdjustOpacity",
+        "Makes the window more or less see-through by the given percentage",
+        &[("delta", "i32")],
+    ),
+    ActionInfo::new("ClearTop", "Stops the window from staying on top of others", &[]),
+    ActionInfo::new(
+        "CyclePictureInPicture",
+        "Moves the picture-in-picture window to the next corner, clockwise",
+        &[],
+    ),
+    ActionInfo::new("Delay", "Waits before the next step of a Sequence", &[("ms", "u64")]),
+    ActionInfo::new(
+        "FocusOrLaunch",
+        "Focuses the next matching window, or runs the command if there isn't one",
+        &[("match", "WindowMatch"), ("command", "String")],
+    ),
+    ActionInfo::new(
+        "GatherAllToMonitor",
+        "Moves every window to a monitor, counting from 0 for the primary one",
+        &[("index", "usize")],
+    ),
+    ActionInfo::new(
+        "Launch",
+        "Runs a program",
+        &[("command", "String"), ("args", "[String]"), ("cwd", "String?")],
+    ),
+    ActionInfo::new("Maximize", "Maximizes the window", &[]),
+    ActionInfo::new("Minimize", "Minimizes the window", &[]),
+    ActionInfo::new("MonitorBottom", "Moves the window to the bottom half of its monitor", &[]),
+    ActionInfo::new(
+        "MonitorBottomLeft",
+        "Moves the window to the bottom left quarter of its monitor",
+        &[],
+    ),
+    ActionInfo::new(
+        "MonitorBottomRight",
+        "Moves the window to the bottom right quarter of its monitor",
+        &[],
+    ),
+    ActionInfo::new("MonitorLeft", "Moves the window to the left half of its monitor", &[]),
+    ActionInfo::new("MonitorRight", "Moves the window to the right half of its monitor", &[]),
+    ActionInfo::new("MonitorTop", "Moves the window to the top half of its monitor", &[]),
+    ActionInfo::new("MonitorTopLeft", "Moves the window to the top left quarter of its monitor", &[]),
+    ActionInfo::new(
+        "MonitorTopRight",
+        "Moves the window to the top right quarter of its monitor",
+        &[],
+    ),
+    ActionInfo::new("MoveNextMonitor", "Moves the window to the next monitor", &[]),
+    ActionInfo::new("MovePrevMonitor", "Moves the window to the previous monitor", &[]),
+    ActionInfo::new(
+        "OnDesktop",
+        "Moves the window to a rectangle in desktop coordinates",
+        RECT_PARAMS,
+    ),
+    ActionInfo::new(
+        "OnMonitor",
+        "Offsets and grows the window's monitor work area by the given amounts",
+        &[
+            ("x", "i32"),
+            ("y", "i32"),
+            ("w", "i32"),
+            ("h", "i32"),
+            ("units", "Units?"),
+        ],
+    ),
+    ActionInfo::new(
+        "PictureInPicture",
+        "Shrinks the window into a corner of its monitor and keeps it on top, or puts it back",
+        &[("corner", "Corner"), ("size", "u32"), ("opacity", "u32?")],
+    ),
+    ActionInfo::new(
+        "RescueWindows",
+        "Moves windows that are mostly off screen onto the nearest monitor",
+        &[],
+    ),
+    ActionInfo::new(
+        "Sequence",
+        "Applies each action in order, stopping at the first failure",
+        &[("", "[Action]")],
+    ),
+    ActionInfo::new(
+        "SetOpacity",
+        "Makes the window see-through, from 10 to 100 percent opaque",
+        &[("percent", "u32")],
+    ),
+    ActionInfo::new(
+        "ToggleBorderlessFullscreen",
+        "Makes the window cover its whole monitor without a caption or frame, or puts it back",
+        &[],
+    ),
+    ActionInfo::new(
+        "ToggleClickThrough",
+        "Lets clicks go through the window to the ones below, or stops it",
+        &[],
+    ),
+    ActionInfo::new("ToggleMaximize", "Maximizes the window, or restores it to where it was", &[]),
+    ActionInfo::new("TogglePause", "Pauses or resumes every other binding", &[]),
+    ActionInfo::new("ToggleTopmost", "Keeps the window on top of others, or stops it", &[]),
+];
+
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub enum Action {
+    /// Adds `delta` percent to the window's opacity.
+    AdjustOpacity {
+        delta: i32,
+    },
     ClearTop,
+    /// Moves the picture-in-picture window to the next corner, clockwise.
+    CyclePictureInPicture,
+    /// Pauses a `Sequence` between steps.
+    Delay {
+        ms: u64,
+    },
+    FocusOrLaunch {
+        #[serde(rename = "match")]
+        window: WindowMatch,
+        command: String,
+    },
+    /// Moves every window to the monitor at `index` in `WindowSystem::monitors` order, the primary one first.
+    GatherAllToMonitor {
+        index: usize,
+    },
+    Launch {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        cwd: Option<String>,
+    },
     Maximize,
     Minimize,
     MonitorBottom,
@@ -268,38 +540,562 @@ pub enum Action {
     MonitorTopRight,
     MoveNextMonitor,
     MovePrevMonitor,
-    OnDesktop { x: i32, y: i32, w: i32, h: i32 },
-    OnMonitor { x: i32, y: i32, w: i32, h: i32 },
+    OnDesktop {
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+    },
+    OnMonitor {
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        #[serde(default, skip_serializing_if = "Units::is_pixels")]
+        units: Units,
+    },
+    /// Shrinks the window to `size` percent of its monitor's work area across, keeping its aspect ratio, in `corner`
+    /// and keeps it on top, or restores the window that's already in picture-in-picture.
+    PictureInPicture {
+        corner: Corner,
+        size: u32,
+        /// Made translucent too, unless it's `None`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        opacity: Option<u32>,
+    },
+    /// Moves windows left mostly outside every work area onto the nearest monitor.
+    RescueWindows,
+    /// Applies each action in order, stopping at the first one that fails.
+    Sequence(Vec<Action>),
+    SetOpacity {
+        percent: u32,
+    },
+    /// Strips the caption and frame and covers the whole monitor, or restores the styles and rect.
+    ToggleBorderlessFullscreen,
+    ToggleClickThrough,
+    /// Maximizes, or restores the rect the window had before.
+    ToggleMaximize,
+    /// Pauses or resumes every other binding.
+    TogglePause,
+    ToggleTopmost,
+}
+
+/// What `OnMonitor` measures its offsets in.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Units {
+    /// Physical pixels, the same on every monitor.
+    #[default]
+    Pixels,
+    /// Pixels at 100% scale, grown by the DPI of the window's monitor so that a margin looks the same on each.
+    Logical,
+}
+
+impl Units {
+    fn is_pixels(&self) -> bool {
+        *self == Units::Pixels
+    }
+}
+
+/// A corner of a monitor's work area.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomRight,
+    BottomLeft,
+}
+
+impl Corner {
+    /// The next corner clockwise.
+    pub fn next(&self) -> Corner {
+        match self {
+            Corner::TopLeft => Corner::TopRight,
+            Corner::TopRight => Corner::BottomRight,
+            Corner::BottomRight => Corner::BottomLeft,
+            Corner::BottomLeft => Corner::TopLeft,
+        }
+    }
+
+    /// The edges a rect in the corner keeps to, horizontally and vertically.
+    pub fn anchors(&self) -> (Anchor, Anchor) {
+        match self {
+            Corner::TopLeft => (Anchor::Start, Anchor::Start),
+            Corner::TopRight => (Anchor::End, Anchor::Start),
+            Corner::BottomRight => (Anchor::End, Anchor::End),
+            Corner::BottomLeft => (Anchor::Start, Anchor::End),
+        }
+    }
 }
 
 impl Action {
-    pub fn apply(&self) -> eyre::Result<()> {
+    /// The variant name, as listed in `ACTION_INFO`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::AdjustOpacity { .. } => "AdjustOpacity",
+            Action::ClearTop => "ClearTop",
+            Action::CyclePictureInPicture => "CyclePictureInPicture",
+            Action::Delay { .. } => "Delay",
+            Action::FocusOrLaunch { .. } => "FocusOrLaunch",
+            Action::GatherAllToMonitor { .. } => "GatherAllToMonitor",
+            Action::Launch { .. } => "Launch",
+            Action::Maximize => "Maximize",
+            Action::Minimize => "Minimize",
+            Action::MonitorBottom => "MonitorBottom",
+            Action::MonitorBottomLeft => "MonitorBottomLeft",
+            Action::MonitorBottomRight => "MonitorBottomRight",
+            Action::MonitorLeft => "MonitorLeft",
+            Action::MonitorRight => "MonitorRight",
+            Action::MonitorTop => "MonitorTop",
+            Action::MonitorTopLeft => "MonitorTopLeft",
+            Action::MonitorTopRight => "MonitorTopRight",
+            Action::MoveNextMonitor => "MoveNextMonitor",
+            Action::MovePrevMonitor => "MovePrevMonitor",
+            Action::OnDesktop { .. } => "OnDesktop",
+            Action::OnMonitor { .. } => "OnMonitor",
+            Action::PictureInPicture { .. } => "PictureInPicture",
+            Action::RescueWindows => "RescueWindows",
+            Action::Sequence(_) => "Sequence",
+            Action::SetOpacity { .. } => "SetOpacity",
+            Action::ToggleBorderlessFullscreen => "ToggleBorderlessFullscreen",
+            Action::ToggleClickThrough => "ToggleClickThrough",
+            Action::ToggleMaximize => "ToggleMaximize",
+            Action::TogglePause => "TogglePause",
+            Action::ToggleTopmost => "ToggleTopmost",
+        }
+    }
+
+    #[cfg(windows)]
+    pub fn apply(&self) -> Result<(), ActionError> {
+        self.apply_with(&Win32WindowSystem, &ProcessLauncher)
+    }
+
+    /// Whether applying the action sleeps, and so shouldn't run on the keyboard hook's thread.
+    #[cfg(windows)]
+    pub fn has_delay(&self) -> bool {
+        match self {
+            Action::Delay { .. } => true,
+            Action::Sequence(actions) => actions.iter().any(Action::has_delay),
+            _ => false,
+        }
+    }
+
+    pub fn apply_with(&self, system: &dyn WindowSystem, launcher: &dyn Launcher) -> Result<(), ActionError> {
         match *self {
-            Action::ClearTop => window_actions::clear_topmost(),
-            Action::Maximize => window_actions::maximize(),
-            Action::Minimize => window_actions::minimize(),
-            Action::MonitorBottom => window_actions::bottom(),
-            Action::MonitorBottomLeft => window_actions::bottom_left(),
-            Action::MonitorBottomRight => window_actions::bottom_right(),
-            Action::MonitorLeft => window_actions::left(),
-            Action::MonitorRight => window_actions::right(),
-            Action::MonitorTop => window_actions::top(),
-            Action::MonitorTopLeft => window_actions::top_left(),
-            Action::MonitorTopRight => window_actions::top_right(),
-            Action::MoveNextMonitor => monitor::move_to_next_monitor(),
-            Action::MovePrevMonitor => monitor::move_to_prev_monitor(),
-            Action::OnDesktop { x, y, w, h } => window_actions::set_window_pos_action(&move |_: &RECT| RECT {
+            Action::Sequence(ref actions) => actions.iter().enumerate().try_for_each(|(i, action)| {
+                action
+                    .apply_with(system, launcher)
+                    .map_err(|source| ActionError::SequenceStep {
+                        step: i + 1,
+                        source: Box::new(source),
+                    })
+            }),
+            _ => self
+                .apply_step(system, launcher)
+                .map_err(|report| ActionError::new(self, report)),
+        }
+    }
+
+    fn apply_step(&self, system: &dyn WindowSystem, launcher: &dyn Launcher) -> eyre::Result<()> {
+        match *self {
+            Action::AdjustOpacity { delta } => window_actions::adjust_opacity(system, delta),
+            Action::ClearTop => window_actions::clear_topmost(system),
+            Action::CyclePictureInPicture => window_actions::cycle_picture_in_picture(system),
+            Action::Delay { ms } => {
+                std::thread::sleep(Duration::from_millis(ms));
+                Ok(())
+            }
+            Action::FocusOrLaunch { ref window, ref command } => {
+                launch::focus_or_launch(system, launcher, window, command)
+            }
+            Action::GatherAllToMonitor { index } => monitor::gather_all_to_monitor(system, index),
+            Action::Launch {
+                ref command,
+                ref args,
+                ref cwd,
+            } => launch::launch(launcher, command, args, cwd.as_deref()),
+            Action::Maximize => window_actions::maximize(system),
+            Action::Minimize => window_actions::minimize(system),
+            Action::MonitorBottom => window_actions::bottom(system),
+            Action::MonitorBottomLeft => window_actions::bottom_left(system),
+            Action::MonitorBottomRight => window_actions::bottom_right(system),
+            Action::MonitorLeft => window_actions::left(system),
+            Action::MonitorRight => window_actions::right(system),
+            Action::MonitorTop => window_actions::top(system),
+            Action::MonitorTopLeft => window_actions::top_left(system),
+            Action::MonitorTopRight => window_actions::top_right(system),
+            Action::MoveNextMonitor => monitor::move_to_next_monitor(system),
+            Action::MovePrevMonitor => monitor::move_to_prev_monitor(system),
+            Action::OnDesktop { x, y, w, h } => window_actions::set_window_pos_action(system, &move |_: &Rect| Rect {
                 left: x,
                 top: y,
                 right: x + w,
                 bottom: y + h,
             }),
-            Action::OnMonitor { x, y, w, h } => window_actions::set_window_pos_action(&move |r: &RECT| RECT {
-                left: r.left + x,
-                top: r.top + y,
-                right: r.right + x + w,
-                bottom: r.bottom + y + h,
-            }),
+            Action::OnMonitor { x, y, w, h, units } => window_actions::on_monitor(system, x, y, w, h, units),
+            Action::PictureInPicture { corner, size, opacity } => {
+                window_actions::picture_in_picture(system, corner, size, opacity)
+            }
+            Action::RescueWindows => monitor::rescue_windows(system, RESCUE.read().unwrap().min_visible_percent),
+            Action::Sequence(_) => unreachable!("apply_with applies each step"),
+            Action::SetOpacity { percent } => window_actions::set_opacity(system, percent),
+            Action::ToggleBorderlessFullscreen => window_actions::toggle_borderless_fullscreen(system),
+            Action::ToggleClickThrough => window_actions::toggle_click_through(system),
+            Action::ToggleMaximize => window_actions::toggle_maximize(system),
+            Action::ToggleTopmost => window_actions::toggle_topmost(system),
+            Action::TogglePause => {
+                let paused = !PAUSED.load(std::sync::atomic::Ordering::Relaxed);
+                #[cfg(windows)]
+                crate::ui::set_paused(paused);
+                // Without the tray there's no icon to update
+                #[cfg(not(windows))]
+                PAUSED.store(paused, std::sync::atomic::Ordering::Relaxed);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::PoisonError;
+
+    use serde_yaml::Value;
+
+    use super::*;
+    use crate::config::IgnoreRule;
+    use crate::fake_desktop::{Call, FakeDesktop};
+    use crate::geometry::Margins;
+    use crate::launch::RecordingLauncher;
+    use crate::settings::{action_info, param_texts, parse_action};
+    use crate::window_system::{Monitor, ShowState, WindowId};
+    use crate::TEST_GLOBALS;
+
+    /// Two side by side monitors with a taskbar along the bottom, and a window with invisible borders on the left one.
+    fn desktop() -> (FakeDesktop, WindowId) {
+        let monitor = |left: i32| Monitor {
+            bounds: Rect::new(left, 0, left + 1920, 1080),
+            work_area: Rect::new(left, 0, left + 1920, 1040),
+            dpi: 96,
+        };
+        let desktop = FakeDesktop::new(vec![monitor(0), monitor(1920)]);
+        let info = WindowInfo {
+            exe: r"C:\Program Files\Zoom\bin\Zoom.exe".to_owned(),
+            ..Default::default()
+        };
+        let window = desktop.add_window(info, Rect::new(300, 200, 1100, 800));
+        desktop.set_border(window, Margins::new(7, 0, 7, 7)).unwrap();
+        (desktop, window)
+    }
+
+    #[test]
+    fn placement_actions_move_the_visible_frame() {
+        let placements = [
+            (Action::MonitorTopLeft, Rect::new(0, 0, 960, 520)),
+            (Action::MonitorTopRight, Rect::new(960, 0, 1920, 520)),
+            (Action::MonitorBottomLeft, Rect::new(0, 520, 960, 1040)),
+            (Action::MonitorBottomRight, Rect::new(960, 520, 1920, 1040)),
+            (Action::MonitorLeft, Rect::new(0, 0, 960, 1040)),
+            (Action::MonitorRight, Rect::new(960, 0, 1920, 1040)),
+            (Action::MonitorTop, Rect::new(0, 0, 1920, 520)),
+            (Action::MonitorBottom, Rect::new(0, 520, 1920, 1040)),
+            (
+                Action::OnDesktop {
+                    x: 100,
+                    y: 50,
+                    w: 800,
+                    h: 600,
+                },
+                Rect::new(100, 50, 900, 650),
+            ),
+            (
+                Action::OnMonitor {
+                    x: 10,
+                    y: 10,
+                    w: -20,
+                    h: -20,
+                    units: Units::Pixels,
+                },
+                Rect::new(10, 10, 1910, 1030),
+            ),
+            (Action::MoveNextMonitor, Rect::new(1920, 0, 2880, 520)),
+            (Action::MovePrevMonitor, Rect::new(1920, 0, 2880, 520)),
+        ];
+        for (action, frame) in placements {
+            let (desktop, window) = desktop();
+            action.apply_with(&desktop, &RecordingLauncher::default()).unwrap();
+            let actual = desktop.window(window).unwrap();
+            assert_eq!(actual.frame_bounds(), frame, "{:?}", action);
+            assert_eq!(actual.show_state, ShowState::Normal, "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn maximize_and_minimize_change_the_show_state() {
+        let (desktop, window) = desktop();
+        Action::Maximize
+            .apply_with(&desktop, &RecordingLauncher::default())
+            .unwrap();
+        let actual = desktop.window(window).unwrap();
+        assert_eq!(actual.show_state, ShowState::Maximized);
+        assert_eq!(actual.frame_bounds(), Rect::new(0, 0, 1920, 1040));
+
+        Action::Minimize
+            .apply_with(&desktop, &RecordingLauncher::default())
+            .unwrap();
+        assert_eq!(desktop.window(window).unwrap().show_state, ShowState::Minimized);
+    }
+
+    #[test]
+    fn ignored_windows_are_left_alone() {
+        let _globals = TEST_GLOBALS.lock().unwrap_or_else(PoisonError::into_inner);
+        *IGNORE.write().unwrap() = vec![IgnoreRule {
+            window: WindowMatch::exe("Zoom.exe"),
+            actions: vec!["Left".to_owned()],
+        }];
+        let (desktop, window) = desktop();
+        let launcher = RecordingLauncher::default();
+
+        let left = HotkeyAction::new("Left", Action::MonitorLeft, &[VK::LeftWindows, VK::Numpad4]);
+        left.apply_with(&desktop, &launcher).unwrap();
+        assert!(desktop.calls().is_empty());
+
+        let right = HotkeyAction::new("Right", Action::MonitorRight, &[VK::LeftWindows, VK::Numpad6]);
+        right.apply_with(&desktop, &launcher).unwrap();
+        assert!(desktop
+            .calls()
+            .iter()
+            .any(|call| matches!(call, Call::SetWindowRect(id, _) if *id == window)));
+        IGNORE.write().unwrap().clear();
+    }
+
+    #[test]
+    fn borderless_fullscreen_keeps_the_original_frame_when_moved_in_between() {
+        let _globals = TEST_GLOBALS.lock().unwrap_or_else(PoisonError::into_inner);
+        window_actions::forget_windows();
+        let (desktop, window) = desktop();
+        let launcher = RecordingLauncher::default();
+        let apply = |action: Action| action.apply_with(&desktop, &launcher).unwrap();
+
+        apply(Action::ToggleBorderlessFullscreen);
+        apply(Action::OnDesktop {
+            x: 100,
+            y: 50,
+            w: 800,
+            h: 600,
+        });
+        apply(Action::ToggleBorderlessFullscreen);
+        let actual = desktop.window(window).unwrap();
+        assert!(actual.borderless);
+        assert_eq!(actual.rect, Rect::new(0, 0, 1920, 1080));
+
+        apply(Action::ToggleBorderlessFullscreen);
+        let actual = desktop.window(window).unwrap();
+        assert!(!actual.borderless);
+        assert_eq!(actual.border, Margins::new(7, 0, 7, 7));
+        assert_eq!(actual.frame_bounds(), Rect::new(300, 200, 1100, 800));
+    }
+
+    #[test]
+    fn picture_in_picture_with_opacity_puts_topmost_and_layering_back() {
+        let _globals = TEST_GLOBALS.lock().unwrap_or_else(PoisonError::into_inner);
+        window_actions::forget_windows();
+        let (desktop, window) = desktop();
+        let launcher = RecordingLauncher::default();
+        let pip = Action::PictureInPicture {
+            corner: Corner::BottomRight,
+            size: 25,
+            opacity: Some(70),
+        };
+
+        pip.apply_with(&desktop, &launcher).unwrap();
+        let actual = desktop.window(window).unwrap();
+        assert!(actual.topmost);
+        assert!(actual.layered);
+        assert_eq!(actual.translucency.opacity, 70);
+
+        pip.apply_with(&desktop, &launcher).unwrap();
+        let actual = desktop.window(window).unwrap();
+        assert!(!actual.topmost);
+        assert!(!actual.layered);
+        assert_eq!(actual.translucency.opacity, 100);
+        assert_eq!(actual.frame_bounds(), Rect::new(300, 200, 1100, 800));
+
+        // Translucency is changed before topmost both ways, so that topmost is settled last
+        let calls = desktop.calls();
+        let position = |wanted: fn(&Call) -> bool| calls.iter().position(wanted).unwrap();
+        let last_position = |wanted: fn(&Call) -> bool| calls.iter().rposition(wanted).unwrap();
+        assert!(
+            position(|call| matches!(call, Call::SetTranslucency(..)))
+                < position(|call| matches!(call, Call::SetTopmost(_, true)))
+        );
+        assert!(
+            last_position(|call| matches!(call, Call::SetLayering(..)))
+                < last_position(|call| matches!(call, Call::SetTopmost(_, false)))
+        );
+    }
+
+    /// One of each variant, in declaration order, with optional fields left at their defaults.
+    fn samples() -> Vec<Action> {
+        let samples = vec![
+            Action::AdjustOpacity { delta: -10 },
+            Action::ClearTop,
+            Action::CyclePictureInPicture,
+            Action::Delay { ms: 100 },
+            Action::FocusOrLaunch {
+                window: WindowMatch::exe("notepad.exe"),
+                command: "notepad.exe".to_owned(),
+            },
+            Action::GatherAllToMonitor { index: 1 },
+            Action::Launch {
+                command: "notepad.exe".to_owned(),
+                args: vec!["notes.txt".to_owned()],
+                cwd: None,
+            },
+            Action::Maximize,
+            Action::Minimize,
+            Action::MonitorBottom,
+            Action::MonitorBottomLeft,
+            Action::MonitorBottomRight,
+            Action::MonitorLeft,
+            Action::MonitorRight,
+            Action::MonitorTop,
+            Action::MonitorTopLeft,
+            Action::MonitorTopRight,
+            Action::MoveNextMonitor,
+            Action::MovePrevMonitor,
+            Action::OnDesktop { x: 0, y: 0, w: 800, h: 600 },
+            Action::OnMonitor {
+                x: 8,
+                y: 8,
+                w: -16,
+                h: -16,
+                units: Units::Pixels,
+            },
+            Action::PictureInPicture {
+                corner: Corner::BottomRight,
+                size: 25,
+                opacity: None,
+            },
+            Action::RescueWindows,
+            Action::Sequence(vec![Action::Maximize, Action::Delay { ms: 100 }]),
+            Action::SetOpacity { percent: 80 },
+            Action::ToggleBorderlessFullscreen,
+            Action::ToggleClickThrough,
+            Action::ToggleMaximize,
+            Action::TogglePause,
+            Action::ToggleTopmost,
+        ];
+        // Fails to build until a new variant is added here, and so to the samples
+        for sample in &samples {
+            match sample {
+                Action::AdjustOpacity { .. }
+                | Action::ClearTop
+                | Action::CyclePictureInPicture
+                | Action::Delay { .. }
+                | Action::FocusOrLaunch { .. }
+                | Action::GatherAllToMonitor { .. }
+                | Action::Launch { .. }
+                | Action::Maximize
+                | Action::Minimize
+                | Action::MonitorBottom
+                | Action::MonitorBottomLeft
+                | Action::MonitorBottomRight
+                | Action::MonitorLeft
+                | Action::MonitorRight
+                | Action::MonitorTop
+                | Action::MonitorTopLeft
+                | Action::MonitorTopRight
+                | Action::MoveNextMonitor
+                | Action::MovePrevMonitor
+                | Action::OnDesktop { .. }
+                | Action::OnMonitor { .. }
+                | Action::PictureInPicture { .. }
+                | Action::RescueWindows
+                | Action::Sequence(_)
+                | Action::SetOpacity { .. }
+                | Action::ToggleBorderlessFullscreen
+                | Action::ToggleClickThrough
+                | Action::ToggleMaximize
+                | Action::TogglePause
+                | Action::ToggleTopmost => (),
+            }
+        }
+        samples
+    }
+
+    /// The largest and smallest values of a numeric parameter type.
+    fn extremes(ty: &str) -> Vec<String> {
+        match ty.trim_end_matches('?') {
+            "i32" => vec![i32::MIN.to_string(), i32::MAX.to_string()],
+            "u32" => vec![u32::MAX.to_string()],
+            "u64" => vec![u64::MAX.to_string()],
+            "usize" => vec![usize::MAX.to_string()],
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn action_info_lists_every_variant_in_order() {
+        let names = samples().iter().map(Action::name).collect::<Vec<&str>>();
+        let listed = ACTION_INFO.iter().map(|info| info.name).collect::<Vec<&str>>();
+        assert_eq!(names, listed);
+    }
+
+    #[test]
+    fn action_info_params_match_the_fields() {
+        for sample in samples() {
+            let info = action_info(sample.name()).unwrap();
+            let fields = match serde_yaml::to_value(&sample).unwrap() {
+                Value::Tagged(tagged) => match tagged.value {
+                    Value::Mapping(fields) => fields,
+                    _ => Default::default(),
+                },
+                _ => Default::default(),
+            };
+            if let [("", _)] = info.params {
+                continue;
+            }
+
+            // Fields left at their defaults may be left out, and are only allowed to be for optional params
+            for (name, value) in &fields {
+                let name = name.as_str().unwrap();
+                let ty = info.params.iter().find(|(param, _)| *param == name).map(|(_, ty)| *ty);
+                assert!(ty.is_some(), "{}.{} isn't in ACTION_INFO", info.name, name);
+                assert!(
+                    !value.is_null() || ty.is_some_and(|ty| ty.ends_with('?')),
+                    "{}.{} can be left out but isn't listed as optional",
+                    info.name,
+                    name
+                );
+            }
+            for (name, ty) in info.params {
+                assert!(
+                    fields.contains_key(*name) || ty.ends_with('?'),
+                    "{}.{} can be left out but isn't listed as optional",
+                    info.name,
+                    name
+                );
+            }
+
+            // The settings window round-trips the action, and accepts the whole range of each numeric type
+            let texts = param_texts(&sample);
+            assert_eq!(parse_action(info, &texts).unwrap(), sample, "{}", info.name);
+            for (i, (name, ty)) in info.params.iter().enumerate() {
+                for extreme in extremes(ty) {
+                    let mut texts = texts.clone();
+                    texts[i] = extreme.clone();
+                    assert!(
+                        parse_action(info, &texts).is_ok(),
+                        "{}.{} isn't a {}, it can't be {}",
+                        info.name,
+                        name,
+                        ty,
+                        extreme
+                    );
+                }
+            }
         }
     }
 }
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use windows::Win32::Foundation::{ERROR_ALREADY_EXISTS, HANDLE, HWND};
+#[cfg(windows)]
+use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+
+use crate::hotkey_action::Action;
+#[cfg(windows)]
+use crate::safe_win32::{close_handle, create_mutex, find_window, send_copy_data};
+
+#[cfg(windows)]
+const MUTEX_NAME: &str = "Local\\GristWindowManager";
+
+/// Identifies `WM_COPYDATA` messages that carry a `Request`.
+#[cfg(windows)]
+pub const COPYDATA_REQUEST: usize = 0x4752_4953;
+
+/// A command-line request forwarded from a second launch to the running instance.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Request {
+    Reload,
+    Run(Action),
+    Pause,
+    Resume,
+    SwitchProfile(String),
+    Quit,
+}
+
+impl Request {
+    /// JSON rather than YAML, which can't write an action with fields inside `Run`.
+    pub fn to_bytes(&self) -> eyre::Result<Vec<u8>> {
+        serde_json::to_vec(self).map_err(eyre::Report::from)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> eyre::Result<Request> {
+        serde_json::from_slice(bytes).map_err(eyre::Report::from)
+    }
+
+    /// Reads a request out of a `WM_COPYDATA` message, if it carries one.
+    ///
+    /// # Safety
+    /// `copy_data` must be the `COPYDATASTRUCT` pointer passed in the message's `LPARAM`.
+    #[cfg(windows)]
+    pub unsafe fn from_copy_data(copy_data: *const COPYDATASTRUCT) -> Option<eyre::Result<Request>> {
+        let copy_data = &*copy_data;
+        if copy_data.dwData != COPYDATA_REQUEST {
+            return None;
+        }
+        let bytes = std::slice::from_raw_parts(copy_data.lpData as *const u8, copy_data.cbData as usize);
+        Some(Request::from_bytes(bytes))
+    }
+}
+
+#[cfg(windows)]
+/// Held by the instance that owns the keyboard hook and tray icon.
+pub struct InstanceLock(HANDLE);
+
+#[cfg(windows)]
+impl InstanceLock {
+    /// Returns `None` if another instance already holds the lock.
+    pub fn acquire() -> eyre::Result<Option<InstanceLock>> {
+        let (handle, error) = create_mutex(MUTEX_NAME)?;
+        if error == ERROR_ALREADY_EXISTS {
+            let _ = close_handle(handle);
+            return Ok(None);
+        }
+        Ok(Some(InstanceLock(handle)))
+    }
+}
+
+#[cfg(windows)]
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = close_handle(self.0);
+    }
+}
+
+#[cfg(windows)]
+/// The tray window of the running instance, if there is one.
+pub fn find_running() -> Option<HWND> {
+    find_window("Grist", "Grist").ok()
+}
+
+#[cfg(windows)]
+pub fn forward(hwnd: HWND, request: &Request) -> eyre::Result<()> {
+    send_copy_data(hwnd, COPYDATA_REQUEST, &request.to_bytes()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey_action::{Corner, Units};
+
+    #[test]
+    fn every_request_round_trips() {
+        let requests = [
+            Request::Reload,
+            Request::Run(Action::MonitorLeft),
+            Request::Run(Action::Sequence(vec![
+                Action::Maximize,
+                Action::Delay { ms: 100 },
+                Action::OnMonitor {
+                    x: 0,
+                    y: 0,
+                    w: 0,
+                    h: 0,
+                    units: Units::Logical,
+                },
+            ])),
+            Request::Run(Action::PictureInPicture {
+                corner: Corner::BottomRight,
+                size: 25,
+                opacity: Some(80),
+            }),
+            Request::Run(Action::Launch {
+                command: "notepad.exe".to_owned(),
+                args: vec!["notes.txt".to_owned()],
+                cwd: None,
+            }),
+            Request::Pause,
+            Request::Resume,
+            Request::SwitchProfile("Presenting".to_owned()),
+            Request::Quit,
+        ];
+        for request in requests {
+            assert_eq!(Request::from_bytes(&request.to_bytes().unwrap()).unwrap(), request);
+        }
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        assert!(Request::from_bytes(b"\xff\xfe{{").is_err());
+        assert!(Request::from_bytes(br#"{"Run": "Nonsense"}"#).is_err());
+        assert!(Request::from_bytes(b"").is_err());
+    }
+}
@@ -0,0 +1,385 @@
+#[cfg(windows)]
+use std::io::BufReader;
+use std::io::{BufRead, Write};
+#[cfg(windows)]
+use std::net::{Ipv4Addr, TcpListener};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use windows::Win32::Foundation::{HWND, RECT};
+#[cfg(windows)]
+use windows::Win32::Graphics::Gdi::MONITORINFO;
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::MONITORINFOF_PRIMARY;
+
+use crate::hotkey_action::{Action, HotkeyAction, VK};
+#[cfg(windows)]
+use crate::safe_win32::{enum_display_monitors, enum_windows, get_window_rect, is_window_visible};
+#[cfg(windows)]
+use crate::window_match::WindowInfo;
+#[cfg(windows)]
+use crate::{instance, log};
+#[cfg(windows)]
+use crate::{ACTIONS, KEYBOARD, PAUSED};
+
+// Protocol
+//
+// Clients send one JSON `Request` per line and get one JSON `Response` per line back, in order. After a
+// `subscribe` request, `Response::Event`s are interleaved with the responses as they happen.
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    Monitors,
+    Windows,
+    Keymap,
+    PressedKeys,
+    Paused,
+    Apply { action: Action },
+    Reload,
+    Pause,
+    Resume,
+    SwitchProfile { name: String },
+    Subscribe,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    Error { message: String },
+    Monitors { monitors: Vec<MonitorState> },
+    Windows { windows: Vec<WindowState> },
+    Keymap { actions: Vec<HotkeyAction> },
+    PressedKeys { keys: Vec<VK> },
+    Paused { paused: bool },
+    Event { event: Event },
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    ActionFired { name: String },
+    FocusChanged { window: WindowState },
+    MonitorsChanged { monitors: Vec<MonitorState> },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Bounds {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+#[cfg(windows)]
+impl From<RECT> for Bounds {
+    fn from(rect: RECT) -> Bounds {
+        Bounds {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MonitorState {
+    pub bounds: Bounds,
+    pub work_area: Bounds,
+    pub primary: bool,
+}
+
+#[cfg(windows)]
+impl From<MONITORINFO> for MonitorState {
+    fn from(monitor_info: MONITORINFO) -> MonitorState {
+        MonitorState {
+            bounds: monitor_info.rcMonitor.into(),
+            work_area: monitor_info.rcWork.into(),
+            primary: monitor_info.dwFlags & MONITORINFOF_PRIMARY != 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WindowState {
+    pub hwnd: usize,
+    pub exe: String,
+    pub class: String,
+    pub title: String,
+    pub bounds: Bounds,
+}
+
+#[cfg(windows)]
+impl WindowState {
+    pub fn from_hwnd(hwnd: HWND) -> eyre::Result<WindowState> {
+        let info = WindowInfo::from_hwnd(hwnd)?;
+        Ok(WindowState {
+            hwnd: hwnd.0 as usize,
+            exe: info.exe,
+            class: info.class,
+            title: info.title,
+            bounds: get_window_rect(hwnd)?.into(),
+        })
+    }
+}
+
+/// Answers every request other than `Request::Subscribe`, which `serve` handles itself.
+pub trait Handler {
+    fn handle(&self, request: Request) -> Response;
+}
+
+/// Fans events out to every subscribed connection.
+pub struct EventBus {
+    subscribers: Mutex<Vec<Sender<Event>>>,
+}
+
+impl EventBus {
+    pub const fn new() -> EventBus {
+        EventBus {
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Lets publishers skip building events nobody will read.
+    pub fn has_subscribers(&self) -> bool {
+        !self.subscribers.lock().unwrap().is_empty()
+    }
+
+    pub fn publish(&self, event: Event) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(windows)]
+pub static EVENTS: EventBus = EventBus::new();
+
+fn write_line<W: Write>(writer: &Mutex<W>, response: &Response) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(response).map_err(std::io::Error::other)?;
+    line.push('\n');
+    let mut writer = writer.lock().unwrap();
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+/// Runs one client connection until it closes.
+pub fn serve<R, W>(reader: R, writer: W, handler: &dyn Handler, events: &EventBus) -> std::io::Result<()>
+where
+    R: BufRead,
+    W: Write + Send + 'static,
+{
+    let writer = Arc::new(Mutex::new(writer));
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Subscribe) => {
+                let receiver = events.subscribe();
+                let writer = writer.clone();
+                std::thread::spawn(move || {
+                    for event in receiver {
+                        if write_line(&writer, &Response::Event { event }).is_err() {
+                            break;
+                        }
+                    }
+                });
+                Response::Ok
+            }
+            Ok(request) => handler.handle(request),
+            Err(error) => Response::Error { message: error.to_string() },
+        };
+        write_line(&writer, &response)?;
+    }
+
+    Ok(())
+}
+
+// Server
+
+#[cfg(windows)]
+/// Answers requests from the live desktop and forwards commands to the tray window, so that they run on the
+/// same thread as the keyboard hook.
+struct GristHandler;
+
+#[cfg(windows)]
+impl GristHandler {
+    fn forward(&self, request: instance::Request) -> Response {
+        let result = instance::find_running()
+            .ok_or_else(|| eyre::eyre!("The tray window isn't running"))
+            .and_then(|hwnd| instance::forward(hwnd, &request));
+        match result {
+            Ok(()) => Response::Ok,
+            Err(error) => Response::Error {
+                message: format!("{:?}", error),
+            },
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Handler for GristHandler {
+    fn handle(&self, request: Request) -> Response {
+        match request {
+            Request::Monitors => match enum_display_monitors() {
+                Ok(monitors) => Response::Monitors {
+                    monitors: monitors.into_iter().map(MonitorState::from).collect(),
+                },
+                Err(error) => Response::Error {
+                    message: format!("{:?}", error),
+                },
+            },
+            Request::Windows => match enum_windows() {
+                Ok(windows) => Response::Windows {
+                    windows: windows
+                        .into_iter()
+                        .filter(|&hwnd| is_window_visible(hwnd))
+                        .filter_map(|hwnd| WindowState::from_hwnd(hwnd).ok())
+                        .collect(),
+                },
+                Err(error) => Response::Error {
+                    message: format!("{:?}", error),
+                },
+            },
+            Request::Keymap => Response::Keymap {
+                actions: ACTIONS.read().unwrap().clone(),
+            },
+            Request::PressedKeys => Response::PressedKeys {
+                keys: KEYBOARD.read().unwrap().pressed().iter().cloned().collect(),
+            },
+            Request::Paused => Response::Paused {
+                paused: PAUSED.load(std::sync::atomic::Ordering::Relaxed),
+            },
+            Request::Apply { action } => self.forward(instance::Request::Run(action)),
+            Request::Reload => self.forward(instance::Request::Reload),
+            Request::Pause => self.forward(instance::Request::Pause),
+            Request::Resume => self.forward(instance::Request::Resume),
+            Request::SwitchProfile { name } => self.forward(instance::Request::SwitchProfile(name)),
+            Request::Subscribe => Response::Error {
+                message: "subscribe is handled by the connection".to_owned(),
+            },
+        }
+    }
+}
+
+#[cfg(windows)]
+/// Accepts connections on `127.0.0.1:port` on a background thread.
+pub fn listen(port: u16) -> eyre::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            std::thread::spawn(move || {
+                let reader = match stream.try_clone() {
+                    Ok(reader) => BufReader::new(reader),
+                    Err(error) => return log!(Warn, App, "{:?}", error),
+                };
+                if let Err(error) = serve(reader, stream, &GristHandler, &EVENTS) {
+                    log!(Warn, App, "IPC connection closed: {:?}", error);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// A `Vec<u8>` that the test can still read once `serve` has taken it.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn responses(&self) -> Vec<Response> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    /// Reports being paused, and accepts any other request.
+    struct StubHandler;
+
+    impl Handler for StubHandler {
+        fn handle(&self, request: Request) -> Response {
+            match request {
+                Request::Paused => Response::Paused { paused: true },
+                _ => Response::Ok,
+            }
+        }
+    }
+
+    fn serve_lines(lines: &str, events: &EventBus) -> Output {
+        let output = Output::default();
+        serve(Cursor::new(lines.to_owned()), output.clone(), &StubHandler, events).unwrap();
+        output
+    }
+
+    #[test]
+    fn answers_each_request_in_order() {
+        let output = serve_lines(
+            "{\"request\": \"paused\"}\n\n{\"request\": \"apply\", \"action\": \"MonitorLeft\"}\n",
+            &EventBus::new(),
+        );
+        assert_eq!(output.responses(), [Response::Paused { paused: true }, Response::Ok]);
+    }
+
+    #[test]
+    fn malformed_lines_get_an_error_and_the_connection_stays_open() {
+        let output = serve_lines(
+            "{\"request\": \"paused\"\nnot json\n{\"request\": \"paused\"}\n",
+            &EventBus::new(),
+        );
+        let responses = output.responses();
+        assert_eq!(responses.len(), 3);
+        assert!(matches!(responses[0], Response::Error { .. }));
+        assert!(matches!(responses[1], Response::Error { .. }));
+        assert_eq!(responses[2], Response::Paused { paused: true });
+    }
+
+    #[test]
+    fn subscribers_get_published_events() {
+        let events = EventBus::new();
+        let output = serve_lines("{\"request\": \"subscribe\"}\n", &events);
+        assert!(events.has_subscribers());
+
+        let event = Event::ActionFired { name: "Maximize".to_owned() };
+        events.publish(event.clone());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while output.responses().len() < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(output.responses(), [Response::Ok, Response::Event { event }]);
+    }
+}
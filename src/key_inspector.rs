@@ -0,0 +1,313 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, AtomicPtr};
+use std::sync::{Mutex, Once};
+
+use num::FromPrimitive;
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Graphics::Gdi::{ANSI_FIXED_FONT, HGDIOBJ};
+use windows::Win32::UI::Controls::{EM_SCROLLCARET, EM_SETLIMITTEXT, EM_SETSEL};
+use windows::Win32::UI::WindowsAndMessaging::{
+    BS_PUSHBUTTON, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, ES_AUTOHSCROLL, ES_AUTOVSCROLL, ES_MULTILINE, ES_READONLY,
+    HMENU, KBDLLHOOKSTRUCT_FLAGS, LLKHF_INJECTED, MB_OK, SWP_NOZORDER, WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP,
+    WM_COMMAND, WM_CREATE, WM_DESTROY, WM_DPICHANGED, WM_SETFONT, WM_SIZE, WNDCLASSW, WS_CHILD, WS_EX_CLIENTEDGE,
+    WS_HSCROLL, WS_OVERLAPPEDWINDOW, WS_VISIBLE, WS_VSCROLL,
+};
+
+use crate::dpi;
+use crate::hotkey_action::VK;
+use crate::safe_win32::{
+    create_window, def_window_proc, get_dlg_item, get_module_handle, get_stock_object, message_box, post_message,
+    register_class, send_message, set_clipboard_text, set_foreground_window, set_window_pos, set_window_text,
+};
+use crate::{log, KEYBOARD};
+
+const CLASS_NAME: &str = "GristKeyInspector";
+const EDIT_ID: i32 = 0x01;
+const PAUSE_ID: i32 = 0x02;
+const CLEAR_ID: i32 = 0x03;
+const COPY_ID: i32 = 0x04;
+const PRESSED_ID: i32 = 0x05;
+const BUTTONS: [(i32, &str); 3] = [(PAUSE_ID, "Pause"), (CLEAR_ID, "Clear"), (COPY_ID, "Copy")];
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 24;
+const MARGIN: i32 = 4;
+/// Older events are dropped, so that a long session doesn't grow the edit control without bound.
+const MAX_EVENTS: usize = 1000;
+const WM_REFRESH: u32 = WM_APP + 1;
+
+static INSPECTOR_HWND: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static REGISTER_CLASS: Once = Once::new();
+static EVENTS: Mutex<VecDeque<KeyEvent>> = Mutex::new(VecDeque::new());
+static PAUSED: AtomicBool = AtomicBool::new(false);
+/// Coalesces refreshes, so that a burst of key events redraws the window once.
+static REFRESH_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// A key event as the low level keyboard hook saw it, and what Grist did with it.
+#[derive(Clone, Debug)]
+pub struct KeyEvent {
+    pub vk_code: u32,
+    pub scan_code: u32,
+    pub flags: KBDLLHOOKSTRUCT_FLAGS,
+    /// Milliseconds since boot, as `GetTickCount` counts them.
+    pub time: u32,
+    pub down: bool,
+    /// The keys that were down after this event.
+    pub pressed: BTreeSet<VK>,
+    /// The binding that matched, even if it was passed through because hotkeys were suspended.
+    pub binding: Option<String>,
+    /// Whether the event was kept from the foreground window.
+    pub swallowed: bool,
+}
+
+impl KeyEvent {
+    pub fn injected(&self) -> bool {
+        self.flags.contains(LLKHF_INJECTED)
+    }
+}
+
+impl std::fmt::Display for KeyEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = VK::from_u32(self.vk_code).map_or(String::from("?"), |vk| format!("{:?}", vk));
+        write!(
+            f,
+            "{:>10} {:<4} vk 0x{:02X} {:<16} scan 0x{:02X} flags 0x{:02X}{} pressed {:?}",
+            self.time,
+            if self.down { "down" } else { "up" },
+            self.vk_code,
+            name,
+            self.scan_code,
+            self.flags.0,
+            if self.injected() { " injected" } else { "" },
+            self.pressed,
+        )?;
+        if let Some(binding) = &self.binding {
+            write!(f, " binding '{}'", binding)?;
+        }
+        if self.swallowed {
+            write!(f, " swallowed")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn is_open() -> bool {
+    !HWND(INSPECTOR_HWND.load(std::sync::atomic::Ordering::Relaxed)).is_invalid()
+}
+
+/// While the inspector is open, logs the event and shows it unless it's paused. Called from the keyboard hook, so
+/// it only queues a refresh rather than updating the window itself.
+pub fn record(event: KeyEvent) {
+    let hwnd = HWND(INSPECTOR_HWND.load(std::sync::atomic::Ordering::Relaxed));
+    if hwnd.is_invalid() {
+        return;
+    }
+    // Never while it's closed, so that the log doesn't keep everything that's typed
+    log!(Debug, Keyboard, "{}", event);
+    if PAUSED.load(std::sync::atomic::Ordering::Relaxed) {
+        return;
+    }
+
+    let mut events = EVENTS.lock().unwrap();
+    if events.len() == MAX_EVENTS {
+        events.pop_front();
+    }
+    events.push_back(event);
+    if !REFRESH_PENDING.swap(true, std::sync::atomic::Ordering::Relaxed) {
+        let _ = post_message(Some(hwnd), WM_REFRESH, WPARAM(0), LPARAM(0));
+    }
+}
+
+/// Opens the inspector, or raises it if it's already open.
+pub fn show() -> eyre::Result<()> {
+    let hwnd = HWND(INSPECTOR_HWND.load(std::sync::atomic::Ordering::Relaxed));
+    if !hwnd.is_invalid() {
+        return set_foreground_window(hwnd);
+    }
+
+    let hinstance = HINSTANCE(get_module_handle(PCWSTR::null())?.0);
+    let class_name = HSTRING::from(CLASS_NAME);
+    let mut registered = Ok(());
+    REGISTER_CLASS.call_once(|| {
+        registered = register_class(&WNDCLASSW {
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(wndproc),
+            hInstance: hinstance,
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            ..Default::default()
+        })
+    });
+    registered?;
+
+    create_window(
+        WINDOW_EX_STYLE::default(),
+        PCWSTR(class_name.as_ptr()),
+        PCWSTR(HSTRING::from("Grist Key Inspector").as_ptr()),
+        WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+        CW_USEDEFAULT,
+        CW_USEDEFAULT,
+        960,
+        480,
+        None,
+        None,
+        Some(hinstance),
+        std::ptr::null_mut(),
+    )?;
+    Ok(())
+}
+
+fn text() -> String {
+    // Edit controls only break lines on CRLF
+    EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(KeyEvent::to_string)
+        .collect::<Vec<String>>()
+        .join("\r\n")
+}
+
+fn refresh(hwnd: HWND) -> eyre::Result<()> {
+    REFRESH_PENDING.store(false, std::sync::atomic::Ordering::Relaxed);
+
+    let pressed = KEYBOARD.read().unwrap().pressed().clone();
+    set_window_text(get_dlg_item(hwnd, PRESSED_ID)?, &format!("Pressed: {:?}", pressed))?;
+
+    let text = text();
+    let edit = get_dlg_item(hwnd, EDIT_ID)?;
+    set_window_text(edit, &text)?;
+    // Keep the latest event in view
+    let end = text.encode_utf16().count();
+    send_message(edit, EM_SETSEL, WPARAM(end), LPARAM(end as isize));
+    send_message(edit, EM_SCROLLCARET, WPARAM(0), LPARAM(0));
+    Ok(())
+}
+
+fn create_child(
+    hwnd: HWND,
+    class: &str,
+    text: &str,
+    exstyle: WINDOW_EX_STYLE,
+    style: WINDOW_STYLE,
+    id: i32,
+    font: HGDIOBJ,
+) -> eyre::Result<HWND> {
+    let child = create_window(
+        exstyle,
+        PCWSTR(HSTRING::from(class).as_ptr()),
+        PCWSTR(HSTRING::from(text).as_ptr()),
+        WS_CHILD | WS_VISIBLE | style,
+        0,
+        0,
+        0,
+        0,
+        Some(hwnd),
+        Some(HMENU(id as isize as *mut c_void)),
+        None,
+        std::ptr::null_mut(),
+    )?;
+    send_message(child, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(0));
+    Ok(child)
+}
+
+fn create_children(hwnd: HWND) -> eyre::Result<()> {
+    let none = WINDOW_EX_STYLE::default();
+    let font = dpi::font(dpi::window_dpi(hwnd));
+    for (id, label) in BUTTONS {
+        let style = WINDOW_STYLE(BS_PUSHBUTTON as u32);
+        create_child(hwnd, "BUTTON", label, none, style, id, font)?;
+    }
+    create_child(hwnd, "STATIC", "", none, WINDOW_STYLE::default(), PRESSED_ID, font)?;
+
+    // A fixed width font lines up the event columns
+    let style =
+        WS_VSCROLL | WS_HSCROLL | WINDOW_STYLE((ES_MULTILINE | ES_READONLY | ES_AUTOVSCROLL | ES_AUTOHSCROLL) as u32);
+    let fixed = get_stock_object(ANSI_FIXED_FONT);
+    let edit = create_child(hwnd, "EDIT", "", WS_EX_CLIENTEDGE, style, EDIT_ID, fixed)?;
+    // Lift the default 32K character limit
+    send_message(edit, EM_SETLIMITTEXT, WPARAM(0), LPARAM(0));
+    refresh(hwnd)
+}
+
+fn layout(hwnd: HWND, width: i32, height: i32) {
+    let (button_width, button_height, margin) = (
+        dpi::scale(hwnd, BUTTON_WIDTH),
+        dpi::scale(hwnd, BUTTON_HEIGHT),
+        dpi::scale(hwnd, MARGIN),
+    );
+    let bar_height = button_height + 2 * margin;
+    let mut x = margin;
+    for (id, _) in BUTTONS {
+        if let Ok(button) = get_dlg_item(hwnd, id) {
+            let _ = set_window_pos(button, None, x, margin, button_width, button_height, SWP_NOZORDER);
+        }
+        x += button_width + margin;
+    }
+    if let Ok(pressed) = get_dlg_item(hwnd, PRESSED_ID) {
+        let top = margin + button_height / 4;
+        let _ = set_window_pos(
+            pressed,
+            None,
+            x + margin,
+            top,
+            width - x - 2 * margin,
+            button_height,
+            SWP_NOZORDER,
+        );
+    }
+    if let Ok(edit) = get_dlg_item(hwnd, EDIT_ID) {
+        let _ = set_window_pos(edit, None, 0, bar_height, width, height - bar_height, SWP_NOZORDER);
+    }
+}
+
+fn on_command(hwnd: HWND, id: i32) -> eyre::Result<()> {
+    match id {
+        PAUSE_ID => {
+            let paused = !PAUSED.load(std::sync::atomic::Ordering::Relaxed);
+            PAUSED.store(paused, std::sync::atomic::Ordering::Relaxed);
+            set_window_text(get_dlg_item(hwnd, PAUSE_ID)?, if paused { "Resume" } else { "Pause" })
+        }
+        CLEAR_ID => {
+            EVENTS.lock().unwrap().clear();
+            refresh(hwnd)
+        }
+        COPY_ID => set_clipboard_text(hwnd, &text()),
+        _ => Ok(()),
+    }
+}
+
+extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_CREATE => {
+            PAUSED.store(false, std::sync::atomic::Ordering::Relaxed);
+            INSPECTOR_HWND.store(hwnd.0, std::sync::atomic::Ordering::Relaxed);
+            dpi::init(hwnd);
+            if let Err(error) = create_children(hwnd) {
+                log!(Error, Wndproc, "{:?}", error);
+            }
+        }
+        WM_SIZE => {
+            let (width, height) = (lparam.0 as u32 & 0xFFFF, (lparam.0 as u32 >> 16) & 0xFFFF);
+            layout(hwnd, width as i32, height as i32);
+        }
+        WM_DPICHANGED => dpi::on_dpi_changed(hwnd, wparam, lparam),
+        WM_REFRESH => {
+            if let Err(error) = refresh(hwnd) {
+                log!(Error, Wndproc, "{:?}", error);
+            }
+        }
+        WM_COMMAND => {
+            if let Err(error) = on_command(hwnd, (wparam.0 & 0xFFFF) as i32) {
+                message_box(Some(hwnd), &format!("{:?}", error), "Grist Key Inspector", MB_OK);
+            }
+        }
+        WM_DESTROY => {
+            INSPECTOR_HWND.store(std::ptr::null_mut(), std::sync::atomic::Ordering::Relaxed);
+            EVENTS.lock().unwrap().clear();
+        }
+        _ => (),
+    }
+
+    def_window_proc(hwnd, msg, wparam, lparam)
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use crate::hotkey_action::VK;
+
+/// What the cheat sheet overlay should do after a key event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HoldEvent {
+    /// A hold key went down on its own. Call `KeyboardState::poll` once the duration has passed.
+    Started(Duration),
+    Show,
+    Hide,
+}
+
+/// The keys that are down, and whether a hold key has been held on its own long enough to show the cheat sheet.
+///
+/// Callers pass in the time of each event so that this stays independent of the platform's timers.
+pub struct KeyboardState {
+    pressed: BTreeSet<VK>,
+    hold_keys: BTreeSet<VK>,
+    hold_threshold: Duration,
+    /// When a hold key went down with nothing else pressed. Pressing another key cancels the hold.
+    hold_started: Option<Instant>,
+    showing: bool,
+}
+
+impl KeyboardState {
+    pub const fn new() -> KeyboardState {
+        KeyboardState {
+            pressed: BTreeSet::new(),
+            hold_keys: BTreeSet::new(),
+            hold_threshold: Duration::from_secs(1),
+            hold_started: None,
+            showing: false,
+        }
+    }
+
+    pub fn pressed(&self) -> &BTreeSet<VK> {
+        &self.pressed
+    }
+
+    /// Holding any one of `keys` on its own for `threshold` shows the cheat sheet. No keys disables it.
+    pub fn set_hold(&mut self, keys: BTreeSet<VK>, threshold: Duration) {
+        self.hold_keys = keys;
+        self.hold_threshold = threshold;
+        self.hold_started = None;
+    }
+
+    pub fn key_down(&mut self, vk: VK, now: Instant) -> Option<HoldEvent> {
+        // Ignore auto-repeat, which would otherwise restart the hold
+        if !self.pressed.insert(vk) {
+            return None;
+        }
+
+        if self.pressed.len() == 1 && self.hold_keys.contains(&vk) {
+            self.hold_started = Some(now);
+            return Some(HoldEvent::Started(self.hold_threshold));
+        }
+        self.hold_started = None;
+        None
+    }
+
+    pub fn key_up(&mut self, vk: VK) -> Option<HoldEvent> {
+        self.pressed.remove(&vk);
+        if !self.hold_keys.contains(&vk) {
+            return None;
+        }
+        self.hold_started = None;
+        std::mem::take(&mut self.showing).then_some(HoldEvent::Hide)
+    }
+
+    /// Returns `HoldEvent::Show` once, when the hold has lasted long enough.
+    pub fn poll(&mut self, now: Instant) -> Option<HoldEvent> {
+        match self.hold_started {
+            Some(started) if !self.showing && now.duration_since(started) >= self.hold_threshold => {
+                self.showing = true;
+                Some(HoldEvent::Show)
+            }
+            _ => None,
+        }
+    }
+
+    /// Forgets every key, e.g. after rehooking, when key up events may have been missed.
+    #[cfg(windows)]
+    pub fn clear(&mut self) -> Option<HoldEvent> {
+        self.pressed.clear();
+        self.hold_started = None;
+        std::mem::take(&mut self.showing).then_some(HoldEvent::Hide)
+    }
+}
+
+/// Records a chord from the keys the hook sees, for the settings window.
+///
+/// The chord is every key held at once, and it's complete when they've all been released.
+pub struct ChordRecorder {
+    recording: bool,
+    chord: BTreeSet<VK>,
+}
+
+impl ChordRecorder {
+    pub const fn new() -> ChordRecorder {
+        ChordRecorder {
+            recording: false,
+            chord: BTreeSet::new(),
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.recording = true;
+        self.chord.clear();
+    }
+
+    pub fn cancel(&mut self) {
+        self.recording = false;
+        self.chord.clear();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Takes the keys that are down after an event, returning the chord once every key has been released.
+    pub fn update(&mut self, pressed: &BTreeSet<VK>) -> Option<BTreeSet<VK>> {
+        if !self.recording {
+            return None;
+        }
+        self.chord.extend(pressed.iter().cloned());
+        if !pressed.is_empty() || self.chord.is_empty() {
+            return None;
+        }
+        self.recording = false;
+        Some(std::mem::take(&mut self.chord))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOLD: Duration = Duration::from_millis(1000);
+
+    fn holding_windows() -> KeyboardState {
+        let mut keyboard = KeyboardState::new();
+        keyboard.set_hold(BTreeSet::from([VK::LeftWindows]), HOLD);
+        keyboard
+    }
+
+    #[test]
+    fn a_hold_key_on_its_own_shows_once_the_threshold_passes() {
+        let mut keyboard = holding_windows();
+        let start = Instant::now();
+        assert_eq!(keyboard.key_down(VK::LeftWindows, start), Some(HoldEvent::Started(HOLD)));
+        assert_eq!(keyboard.poll(start + HOLD / 2), None);
+        assert_eq!(keyboard.poll(start + HOLD), Some(HoldEvent::Show));
+        assert_eq!(keyboard.poll(start + HOLD * 2), None);
+    }
+
+    #[test]
+    fn other_keys_cancel_the_hold() {
+        let mut keyboard = holding_windows();
+        let start = Instant::now();
+        keyboard.key_down(VK::LeftWindows, start);
+        assert_eq!(keyboard.key_down(VK::Left, start + HOLD / 2), None);
+        assert_eq!(keyboard.poll(start + HOLD), None);
+        assert_eq!(keyboard.key_down(VK::A, start), None);
+        assert_eq!(keyboard.key_down(VK::RightWindows, start), None);
+    }
+
+    #[test]
+    fn auto_repeat_keeps_the_hold_going() {
+        let mut keyboard = holding_windows();
+        let start = Instant::now();
+        keyboard.key_down(VK::LeftWindows, start);
+        assert_eq!(keyboard.key_down(VK::LeftWindows, start + HOLD / 2), None);
+        assert_eq!(keyboard.poll(start + HOLD), Some(HoldEvent::Show));
+    }
+
+    #[test]
+    fn releasing_the_hold_key_hides_the_cheat_sheet() {
+        let mut keyboard = holding_windows();
+        let start = Instant::now();
+        keyboard.key_down(VK::LeftWindows, start);
+        keyboard.poll(start + HOLD);
+        assert_eq!(keyboard.key_up(VK::LeftWindows), Some(HoldEvent::Hide));
+        assert!(keyboard.pressed().is_empty());
+        assert_eq!(keyboard.poll(start + HOLD * 2), None);
+
+        // Released before it showed, there's nothing to hide
+        keyboard.key_down(VK::LeftWindows, start);
+        assert_eq!(keyboard.key_up(VK::LeftWindows), None);
+        assert_eq!(keyboard.poll(start + HOLD), None);
+    }
+
+    #[test]
+    fn records_every_key_held_until_all_are_released() {
+        let mut recorder = ChordRecorder::new();
+        recorder.start();
+        let mut pressed = BTreeSet::new();
+        for vk in [VK::LeftWindows, VK::LeftShift, VK::Left] {
+            pressed.insert(vk);
+            assert_eq!(recorder.update(&pressed), None);
+        }
+        for vk in [VK::Left, VK::LeftShift] {
+            pressed.remove(&vk);
+            assert_eq!(recorder.update(&pressed), None);
+        }
+        pressed.clear();
+        assert_eq!(
+            recorder.update(&pressed),
+            Some(BTreeSet::from([VK::LeftWindows, VK::LeftShift, VK::Left]))
+        );
+        assert!(!recorder.is_recording());
+    }
+
+    #[test]
+    fn records_nothing_unless_started() {
+        let mut recorder = ChordRecorder::new();
+        assert_eq!(recorder.update(&BTreeSet::from([VK::A])), None);
+        assert_eq!(recorder.update(&BTreeSet::new()), None);
+
+        recorder.start();
+        assert_eq!(recorder.update(&BTreeSet::from([VK::A])), None);
+        recorder.cancel();
+        assert_eq!(recorder.update(&BTreeSet::new()), None);
+        assert!(!recorder.is_recording());
+    }
+}
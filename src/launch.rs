@@ -0,0 +1,162 @@
+#[cfg(test)]
+use std::cell::RefCell;
+use std::path::Path;
+use std::process::Command;
+
+use crate::window_match::WindowMatch;
+use crate::window_system::{ShowState, WindowId, WindowSystem};
+
+/// Starts programs on behalf of `Action::Launch` and `Action::FocusOrLaunch`.
+pub trait Launcher {
+    fn launch(&self, command: &str, args: &[String], cwd: Option<&Path>) -> eyre::Result<()>;
+}
+
+/// Spawns a detached child process.
+pub struct ProcessLauncher;
+
+impl Launcher for ProcessLauncher {
+    fn launch(&self, command: &str, args: &[String], cwd: Option<&Path>) -> eyre::Result<()> {
+        let mut process = Command::new(command);
+        process.args(args);
+        if let Some(cwd) = cwd {
+            process.current_dir(cwd);
+        }
+        process.spawn().map(|_| ()).map_err(eyre::Report::from)
+    }
+}
+
+/// Records launches instead of starting processes.
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingLauncher {
+    pub launched: RefCell<Vec<String>>,
+}
+
+#[cfg(test)]
+impl Launcher for RecordingLauncher {
+    fn launch(&self, command: &str, _args: &[String], _cwd: Option<&Path>) -> eyre::Result<()> {
+        self.launched.borrow_mut().push(command.to_owned());
+        Ok(())
+    }
+}
+
+pub fn launch(launcher: &dyn Launcher, command: &str, args: &[String], cwd: Option<&str>) -> eyre::Result<()> {
+    launcher.launch(command, args, cwd.map(Path::new))
+}
+
+/// Focuses the next window matching `window`, or launches `command` if there isn't one.
+pub fn focus_or_launch(
+    system: &dyn WindowSystem,
+    launcher: &dyn Launcher,
+    window: &WindowMatch,
+    command: &str,
+) -> eyre::Result<()> {
+    let mut candidates = system
+        .windows()?
+        .into_iter()
+        .filter(|&id| system.is_visible(id))
+        .filter(|&id| system.window_info(id).is_ok_and(|info| window.matches(&info)))
+        .collect::<Vec<WindowId>>();
+
+    // EnumWindows returns windows in z-order, which changes every time one is focused. Sort by handle
+    // so that repeated presses cycle through every instance instead of flipping between the top two.
+    candidates.sort();
+
+    match next_instance(&candidates, system.foreground_window().ok()) {
+        Some(id) => focus(system, id),
+        None => launcher.launch(command, &[], None),
+    }
+}
+
+/// Returns the candidate after `current`, wrapping around, or the first candidate if `current` isn't one.
+fn next_instance<T: Copy + PartialEq>(candidates: &[T], current: Option<T>) -> Option<T> {
+    let next = current
+        .and_then(|current| candidates.iter().position(|&candidate| candidate == current))
+        .map_or(0, |i| (i + 1) % candidates.len());
+    candidates.get(next).copied()
+}
+
+fn focus(system: &dyn WindowSystem, window: WindowId) -> eyre::Result<()> {
+    if system.show_state(window) == ShowState::Minimized {
+        system.set_show_state(window, ShowState::Normal)?;
+    }
+    system.set_foreground_window(window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_desktop::FakeDesktop;
+    use crate::geometry::Rect;
+    use crate::window_match::WindowInfo;
+    use crate::window_system::Monitor;
+
+    fn desktop() -> FakeDesktop {
+        FakeDesktop::new(vec![Monitor {
+            bounds: Rect::new(0, 0, 1920, 1080),
+            work_area: Rect::new(0, 0, 1920, 1040),
+            dpi: 96,
+        }])
+    }
+
+    fn open(desktop: &FakeDesktop, exe: &str) -> WindowId {
+        let info = WindowInfo {
+            exe: exe.to_owned(),
+            ..Default::default()
+        };
+        desktop.add_window(info, Rect::new(100, 100, 900, 700))
+    }
+
+    fn focus_or_launch_notepad(desktop: &FakeDesktop, launcher: &RecordingLauncher) {
+        focus_or_launch(desktop, launcher, &WindowMatch::exe("notepad.exe"), "notepad.exe").unwrap();
+    }
+
+    #[test]
+    fn launches_when_no_window_matches() {
+        let desktop = desktop();
+        let other = open(&desktop, "explorer.exe");
+        let launcher = RecordingLauncher::default();
+        focus_or_launch_notepad(&desktop, &launcher);
+        assert_eq!(*launcher.launched.borrow(), ["notepad.exe"]);
+        assert_eq!(desktop.foreground(), Some(other));
+    }
+
+    #[test]
+    fn focuses_and_restores_the_only_match() {
+        let desktop = desktop();
+        let notepad = open(&desktop, "notepad.exe");
+        desktop.set_show_state(notepad, ShowState::Minimized).unwrap();
+        open(&desktop, "explorer.exe");
+        let launcher = RecordingLauncher::default();
+        focus_or_launch_notepad(&desktop, &launcher);
+        assert!(launcher.launched.borrow().is_empty());
+        assert_eq!(desktop.foreground(), Some(notepad));
+        assert_eq!(desktop.show_state(notepad), ShowState::Normal);
+    }
+
+    #[test]
+    fn cycles_through_every_match_from_the_focused_one() {
+        let desktop = desktop();
+        let notepads = [
+            open(&desktop, "notepad.exe"),
+            open(&desktop, "notepad.exe"),
+            open(&desktop, "notepad.exe"),
+        ];
+        open(&desktop, "explorer.exe");
+        let launcher = RecordingLauncher::default();
+        let mut focused = Vec::new();
+        for _ in 0..4 {
+            focus_or_launch_notepad(&desktop, &launcher);
+            focused.extend(desktop.foreground());
+        }
+        assert_eq!(focused, [notepads[0], notepads[1], notepads[2], notepads[0]]);
+        assert!(launcher.launched.borrow().is_empty());
+    }
+
+    #[test]
+    fn next_instance_wraps_around() {
+        assert_eq!(next_instance(&[1, 2, 3], Some(3)), Some(1));
+        assert_eq!(next_instance(&[1, 2, 3], Some(7)), Some(1));
+        assert_eq!(next_instance::<i32>(&[], None), None);
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::data_dir;
+use crate::DEBUG;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// Startup, config and IPC
+    App,
+    /// Key events and binding lookups in the keyboard hook
+    Keyboard,
+    /// Applying actions
+    Actions,
+    /// Session lock, logon and remote desktop changes
+    Session,
+    /// Window messages
+    Wndproc,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub level: Level,
+    /// Overrides `level` for individual targets.
+    pub targets: BTreeMap<Target, Level>,
+    /// The log file is rotated once it reaches this size.
+    pub max_bytes: u64,
+    /// How many rotated files to keep besides the current one.
+    pub max_files: usize,
+}
+
+impl LogConfig {
+    const fn new() -> LogConfig {
+        LogConfig {
+            level: Level::Info,
+            targets: BTreeMap::new(),
+            max_bytes: 1024 * 1024,
+            max_files: 3,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> LogConfig {
+        LogConfig::new()
+    }
+}
+
+struct Logger {
+    config: LogConfig,
+    file: Option<File>,
+    size: u64,
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    config: LogConfig::new(),
+    file: None,
+    size: 0,
+});
+
+fn log_file(dir: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join("grist.log"),
+        index => dir.join(format!("grist.{}.log", index)),
+    }
+}
+
+pub fn configure(config: LogConfig) {
+    LOGGER.lock().unwrap().config = config;
+}
+
+/// Whether a message would be written. The tray menu's Debug toggle enables everything.
+pub fn enabled(level: Level, target: Target) -> bool {
+    if DEBUG.load(std::sync::atomic::Ordering::Relaxed) {
+        return true;
+    }
+    let logger = LOGGER.lock().unwrap();
+    level <= *logger.config.targets.get(&target).unwrap_or(&logger.config.level)
+}
+
+/// Use the `log!` macro instead, which skips formatting disabled messages.
+pub fn write(level: Level, target: Target, args: std::fmt::Arguments) {
+    let line = format!("{} {:?} {:?} {}\n", timestamp(), level, target, args);
+    let mut logger = LOGGER.lock().unwrap();
+    if let Err(error) = logger.write(&data_dir(), line.as_bytes()) {
+        // There's nowhere else to report it, and a console may be attached
+        eprintln!("{:?}: {}", error, line.trim_end());
+    }
+}
+
+impl Logger {
+    fn write(&mut self, dir: &Path, line: &[u8]) -> std::io::Result<()> {
+        if self.file.is_none() {
+            std::fs::create_dir_all(dir)?;
+            let file = OpenOptions::new().create(true).append(true).open(log_file(dir, 0))?;
+            self.size = file.metadata()?.len();
+            self.file = Some(file);
+        }
+
+        if self.size > 0 && self.size + line.len() as u64 > self.config.max_bytes {
+            self.rotate(dir)?;
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(line)?;
+            self.size += line.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Shifts `grist.log` to `grist.1.log`, `grist.1.log` to `grist.2.log` and so on, dropping the oldest.
+    fn rotate(&mut self, dir: &Path) -> std::io::Result<()> {
+        self.file = None;
+        let _ = std::fs::remove_file(log_file(dir, self.config.max_files));
+        for index in (0..self.config.max_files).rev() {
+            let _ = std::fs::rename(log_file(dir, index), log_file(dir, index + 1));
+        }
+        self.file = Some(File::create(log_file(dir, 0))?);
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// UTC, e.g. `2024-01-31T12:34:56.789Z`.
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = now.as_secs();
+    let (days, time) = (seconds / 86400, seconds % 86400);
+
+    // Howard Hinnant's civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        now.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the temp directory, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let dir = std::env::temp_dir().join(format!("grist-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            TempDir(dir)
+        }
+
+        fn read(&self, index: usize) -> Option<String> {
+            std::fs::read_to_string(log_file(&self.0, index)).ok()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn logger(max_bytes: u64, max_files: usize) -> Logger {
+        Logger {
+            config: LogConfig {
+                max_bytes,
+                max_files,
+                ..LogConfig::new()
+            },
+            file: None,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn rotates_before_a_line_would_pass_the_limit() -> std::io::Result<()> {
+        let dir = TempDir::new("log-rotates");
+        let mut logger = logger(8, 3);
+        for line in ["a\n", "bb\n", "ccc\n", "d\n"] {
+            logger.write(&dir.0, line.as_bytes())?;
+        }
+        assert_eq!(dir.read(0).as_deref(), Some("ccc\nd\n"));
+        assert_eq!(dir.read(1).as_deref(), Some("a\nbb\n"));
+        assert_eq!(dir.read(2), None);
+        Ok(())
+    }
+
+    #[test]
+    fn a_line_longer_than_the_limit_gets_a_file_of_its_own() -> std::io::Result<()> {
+        let dir = TempDir::new("log-long-line");
+        let mut logger = logger(4, 3);
+        for line in ["0123456789\n", "0123456789\n"] {
+            logger.write(&dir.0, line.as_bytes())?;
+        }
+        assert_eq!(dir.read(0).as_deref(), Some("0123456789\n"));
+        assert_eq!(dir.read(1).as_deref(), Some("0123456789\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_only_the_newest_rotated_files() -> std::io::Result<()> {
+        let dir = TempDir::new("log-keeps");
+        let mut logger = logger(2, 2);
+        for line in ["1\n", "2\n", "3\n", "4\n", "5\n"] {
+            logger.write(&dir.0, line.as_bytes())?;
+        }
+        assert_eq!(dir.read(0).as_deref(), Some("5\n"));
+        assert_eq!(dir.read(1).as_deref(), Some("4\n"));
+        assert_eq!(dir.read(2).as_deref(), Some("3\n"));
+        assert_eq!(dir.read(3), None);
+        Ok(())
+    }
+
+    #[test]
+    fn appends_to_an_existing_log_counting_its_size() -> std::io::Result<()> {
+        let dir = TempDir::new("log-appends");
+        logger(8, 1).write(&dir.0, b"abcdef\n")?;
+        let mut logger = logger(8, 1);
+        logger.write(&dir.0, b"g\n")?;
+        assert_eq!(dir.read(0).as_deref(), Some("g\n"));
+        assert_eq!(dir.read(1).as_deref(), Some("abcdef\n"));
+        Ok(())
+    }
+}
@@ -1,8 +1,18 @@
+/// Writes a line to the log file if the level is enabled for the target, e.g. `log!(Warn, Keyboard, "{:?}", error)`.
+#[macro_export]
+macro_rules! log {
+    ($level:ident, $target:ident, $($arg:tt)+) => {
+        if $crate::log::enabled($crate::log::Level::$level, $crate::log::Target::$target) {
+            $crate::log::write($crate::log::Level::$level, $crate::log::Target::$target, format_args!($($arg)+));
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! PRINT_STYLE {
     ($style:expr, $winstyle:ident) => {
         if ($style) & $winstyle == $winstyle {
-            println!("  {}", stringify!($winstyle));
+            $crate::log!(Debug, Actions, "  {}", stringify!($winstyle));
         }
     };
 }
@@ -10,13 +20,13 @@ macro_rules! PRINT_STYLE {
 #[macro_export]
 macro_rules! D {
     ($e:expr) => {
-        println!("{}: '{:?}'", stringify!($e), $e);
+        $crate::log!(Debug, App, "{}: '{:?}'", stringify!($e), $e);
     };
 }
 
 #[macro_export]
 macro_rules! P {
     ($e:expr) => {
-        println!("{}: '{}'", stringify!($e), $e);
+        $crate::log!(Debug, App, "{}: '{}'", stringify!($e), $e);
     };
 }
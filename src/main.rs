@@ -3,12 +3,52 @@
 
 // Declare the application's modules
 mod cardinal;
+#[cfg(windows)]
+mod cli;
+mod config;
+#[cfg(windows)]
+mod dpi;
+mod error;
+#[cfg(test)]
+mod fake_desktop;
+mod geometry;
+#[cfg(any(windows, test))]
+mod help;
+#[cfg(windows)]
+mod help_window;
 mod hotkey_action;
+#[cfg(any(windows, test))]
+mod instance;
+#[cfg(any(windows, test))]
+mod ipc;
+#[cfg(windows)]
+mod key_inspector;
+#[cfg(any(windows, test))]
+mod keyboard;
+mod launch;
+mod log;
 mod monitor;
+#[cfg(windows)]
 mod msg;
+#[cfg(windows)]
+mod overlay;
+#[cfg(windows)]
 mod safe_win32;
+#[cfg(test)]
+mod scenario;
+#[cfg(any(windows, test))]
+mod settings;
+#[cfg(windows)]
+mod settings_window;
+#[cfg(windows)]
 mod ui;
 mod window_actions;
+mod window_match;
+mod window_system;
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+mod x11_hotkeys;
 
 // Declare the application's macros
 #[macro_use]
@@ -18,15 +58,27 @@ mod macros;
 #[macro_use]
 extern crate num_derive;
 
+#[cfg(windows)]
 use eyre::eyre;
-use std::sync::LazyLock;
 
 // Import crate members
-use crate::safe_win32::{dispatch_message, get_message, message_box, translate_message};
-use hotkey_action::HotkeyAction;
-use std::collections::BTreeSet;
+#[cfg(windows)]
+use crate::safe_win32::{attach_parent_console, dispatch_message, get_message, message_box, translate_message};
+use config::{IgnoreRule, Rescue};
+#[cfg(windows)]
+use error::ErrorNotifier;
+use hotkey_action::{Action, HotkeyAction, VK};
+#[cfg(windows)]
+use keyboard::KeyboardState;
+use std::path::Path;
 use std::sync::atomic::AtomicBool;
+#[cfg(any(windows, test))]
+use std::sync::Mutex;
 use std::sync::RwLock;
+#[cfg(windows)]
+use std::time::Duration;
+use window_match::WindowMatch;
+#[cfg(windows)]
 use windows::{
     core::BOOL,
     Win32::{
@@ -36,39 +88,113 @@ use windows::{
 };
 
 static ACTIONS: RwLock<Vec<HotkeyAction>> = RwLock::new(Vec::new());
+static IGNORE: RwLock<Vec<IgnoreRule>> = RwLock::new(Vec::new());
 static DEBUG: AtomicBool = AtomicBool::new(false);
-static PRESSED_KEYS: LazyLock<RwLock<BTreeSet<hotkey_action::VK>>> = LazyLock::new(RwLock::default);
-// https://github.com/rust-lang/rust/issues/71835
-// static PRESSED_KEYS: RwLock<BTreeSet<hotkey_action::VK>> = RwLock::new(BTreeSet::new());
-
-fn print_pressed_keys() {
-    let mut s = PRESSED_KEYS.read().unwrap().iter().fold(String::new(), |mut s, i| {
-        let _ = std::fmt::write(&mut s, format_args!("{:?} ", *i));
-        s
-    });
-    if s.is_empty() {
-        s = String::from("No keys currently pressed");
-    }
-    message_box(Some(HWND::default()), s.as_str(), "Pressed Keys", MB_OK);
-}
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static PASSTHROUGH: RwLock<Vec<WindowMatch>> = RwLock::new(Vec::new());
+static RESCUE: RwLock<Rescue> = RwLock::new(Rescue::new());
+static PROFILE: RwLock<Option<String>> = RwLock::new(None);
+#[cfg(windows)]
+static KEYBOARD: RwLock<KeyboardState> = RwLock::new(KeyboardState::new());
+#[cfg(windows)]
+static NOTIFIER: Mutex<ErrorNotifier> = Mutex::new(ErrorNotifier::new());
+/// Held by tests that set the globals above, since `cargo test` runs tests in parallel.
+#[cfg(test)]
+static TEST_GLOBALS: Mutex<()> = Mutex::new(());
 
 fn create_actions() -> Vec<HotkeyAction> {
     let mut actions = Vec::new();
 
     monitor::add_actions(&mut actions);
     window_actions::add_actions(&mut actions);
+    actions.push(HotkeyAction::new(
+        "Pause Hotkeys",
+        Action::TogglePause,
+        &[VK::LeftWindows, VK::LeftShift, VK::P],
+    ));
     actions
 }
 
-fn main() -> eyre::Result<()> {
+fn load_config() -> eyre::Result<()> {
+    let mut config = config::load(Path::new(config::CONFIG_FILE))?;
+
+    // A profile switched to at runtime outlasts reloads
+    if let Some(profile) = PROFILE.read().unwrap().clone() {
+        config.profile = Some(profile);
+    }
+
+    *ACTIONS.write().unwrap() = config.keymap();
+    *IGNORE.write().unwrap() = config.ignore;
+    *PASSTHROUGH.write().unwrap() = config.passthrough;
+    *RESCUE.write().unwrap() = config.rescue;
+    log::configure(config.log);
+
+    // Only the tray shows notifications and the cheat sheet
+    #[cfg(windows)]
     {
-        *ACTIONS.write().unwrap() = create_actions();
+        let mut muted = config.notifications.muted;
+        muted.extend(config::load_muted_errors());
+        NOTIFIER.lock().unwrap().configure(
+            config.notifications.enabled,
+            Duration::from_secs(config.notifications.interval_secs),
+            muted,
+        );
+        KEYBOARD
+            .write()
+            .unwrap()
+            .set_hold(config.cheat_sheet.keys, Duration::from_millis(config.cheat_sheet.hold_ms));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn switch_profile(name: &str) -> eyre::Result<()> {
+    let config = config::load(Path::new(config::CONFIG_FILE))?;
+    if !config.profiles.contains_key(name) {
+        return Err(eyre!("Unknown profile '{}'", name));
     }
 
-    let hwnd = ui::create()?;
+    *PROFILE.write().unwrap() = Some(name.to_owned());
+    load_config()
+}
+
+#[cfg(windows)]
+fn main() -> eyre::Result<()> {
+    // Before any window is created or measured
+    dpi::set_per_monitor_aware();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    if !args.is_empty() {
+        let _ = attach_parent_console();
+    }
+    cli::run(cli::parse(args.into_iter())?)
+}
+
+#[cfg(windows)]
+fn run_tray() -> eyre::Result<()> {
+    // A second instance would install a second hook, and every chord would fire twice
+    let Some(_instance_lock) = instance::InstanceLock::acquire()? else {
+        return Ok(());
+    };
+
+    log!(Info, App, "Starting");
+    if let Err(error) = load_config() {
+        log!(Error, App, "{:?}", error);
+        message_box(Some(HWND::default()), &format!("{:?}", error), "Grist Config", MB_OK);
+    }
+
+    let ipc_port = config::load(Path::new(config::CONFIG_FILE)).map_or(None, |config| config.ipc_port);
+    if let Some(port) = ipc_port {
+        if let Err(error) = ipc::listen(port) {
+            log!(Error, App, "{:?}", error);
+            message_box(Some(HWND::default()), &format!("{:?}", error), "Grist IPC", MB_OK);
+        }
+    }
+
+    ui::create()?;
     let mut msg = MSG::default();
     loop {
-        match get_message(&mut msg, Some(hwnd), 0, 0) {
+        // Every window, so that the help window gets its input too
+        match get_message(&mut msg, None, 0, 0) {
             BOOL(-1) => return Err(eyre!("GetMessageW() failed")),
             BOOL(0) => return Ok(()),
             _ => {
@@ -78,3 +204,17 @@ fn main() -> eyre::Result<()> {
         }
     }
 }
+
+#[cfg(target_os = "linux")]
+fn main() -> eyre::Result<()> {
+    log!(Info, App, "Starting");
+    load_config()?;
+    let system = x11::X11WindowSystem::connect()?;
+    x11_hotkeys::run(&system)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn main() {
+    eprintln!("grist only runs on Windows and X11");
+    std::process::exit(1);
+}
@@ -1,12 +1,10 @@
-use crate::cardinal::Cardinal;
+use eyre::eyre;
+
+use crate::geometry::Rect;
 use crate::hotkey_action::{Action, HotkeyAction, VK};
-use crate::safe_win32::{
-    enum_display_monitors, get_foreground_window, get_monitor_info, monitor_from_window, set_cursor_pos,
-};
 use crate::window_actions::set_window_rect;
-use windows::Win32::Foundation::RECT;
-use windows::Win32::Graphics::Gdi::MONITOR_DEFAULTTOPRIMARY;
-use windows::Win32::UI::WindowsAndMessaging::SET_WINDOW_POS_FLAGS;
+use crate::window_system::{monitor_from_rect, Monitor, ShowState, WindowId, WindowSystem};
+use crate::{log, IGNORE};
 
 enum Direction {
     Left,
@@ -36,33 +34,130 @@ pub fn add_actions(actions: &mut Vec<HotkeyAction>) {
     ]);
 }
 
-pub fn move_to_next_monitor() -> eyre::Result<()> {
-    move_to_adjacent_monitor(Direction::Right)
+pub fn move_to_next_monitor(system: &dyn WindowSystem) -> eyre::Result<()> {
+    move_to_adjacent_monitor(system, Direction::Right)
 }
 
-pub fn move_to_prev_monitor() -> eyre::Result<()> {
-    move_to_adjacent_monitor(Direction::Left)
+pub fn move_to_prev_monitor(system: &dyn WindowSystem) -> eyre::Result<()> {
+    move_to_adjacent_monitor(system, Direction::Left)
 }
 
-fn move_to_adjacent_monitor(direction: Direction) -> eyre::Result<()> {
-    let mut monitors = enum_display_monitors()?;
+fn move_to_adjacent_monitor(system: &dyn WindowSystem, direction: Direction) -> eyre::Result<()> {
+    let mut monitors = system.monitors()?;
 
-    monitors.sort_by(|lhs, rhs| match lhs.rcWork.left == rhs.rcWork.left {
-        true => lhs.rcWork.top.cmp(&rhs.rcWork.top),
-        false => lhs.rcWork.left.cmp(&rhs.rcWork.left),
+    monitors.sort_by(|lhs, rhs| match lhs.work_area.left == rhs.work_area.left {
+        true => lhs.work_area.top.cmp(&rhs.work_area.top),
+        false => lhs.work_area.left.cmp(&rhs.work_area.left),
     });
 
-    let foreground_window = get_foreground_window()?;
-    let monitor_info = get_monitor_info(monitor_from_window(foreground_window, MONITOR_DEFAULTTOPRIMARY)?)?;
+    let foreground_window = system.foreground_window()?;
+    let monitor = system.monitor_from_window(foreground_window)?;
 
     let i = direction.apply(
-        monitors.iter().position(|&m| m.rcWork == monitor_info.rcWork).unwrap(),
+        monitors.iter().position(|m| m.work_area == monitor.work_area).unwrap(),
         monitors.len(),
     );
 
-    let work_area = monitors[i].rcWork;
-    let window_pos = RECT::from_points(work_area.top_left(), work_area.center());
-    let _ = set_window_rect(foreground_window, &window_pos, SET_WINDOW_POS_FLAGS::default());
-    let _ = set_cursor_pos(window_pos.center().x, window_pos.center().y);
+    let work_area = monitors[i].work_area;
+    let window_pos = Rect::from_points(work_area.top_left(), work_area.center());
+    let _ = set_window_rect(system, foreground_window, &window_pos);
+    let _ = system.bring_to_top(foreground_window);
+    let _ = system.set_cursor_pos(window_pos.center());
+    Ok(())
+}
+
+/// Whether less than `min_visible` of the frame, from 0 to 1, is on any one work area.
+fn is_stranded(frame: &Rect, monitors: &[Monitor], min_visible: f64) -> bool {
+    monitors
+        .iter()
+        .all(|monitor| frame.overlap_ratio(&monitor.work_area) < min_visible)
+}
+
+/// The monitor whose work area is closest to the frame, the primary one breaking ties.
+fn nearest_monitor(frame: &Rect, monitors: &[Monitor]) -> Option<Monitor> {
+    monitors
+        .iter()
+        .min_by(|lhs, rhs| {
+            frame
+                .distance(&lhs.work_area)
+                .total_cmp(&frame.distance(&rhs.work_area))
+        })
+        .copied()
+}
+
+/// The windows rescuing and gathering move: visible app windows that aren't minimized, have a frame to move, and
+/// aren't ignored for `action`.
+fn movable_windows(system: &dyn WindowSystem, action: &Action) -> eyre::Result<Vec<(WindowId, Rect)>> {
+    let ignore = IGNORE.read().unwrap();
+    let ignored = |window: WindowId| {
+        system
+            .window_info(window)
+            .is_ok_and(|info| ignore.iter().any(|rule| rule.ignores(action.name(), &info)))
+    };
+    Ok(system
+        .windows()?
+        .into_iter()
+        .filter(|&window| system.is_visible(window) && system.show_state(window) != ShowState::Minimized)
+        .filter(|&window| system.is_app_window(window) && !ignored(window))
+        .filter_map(|window| system.frame_bounds(window).ok().map(|frame| (window, frame)))
+        .filter(|(_, frame)| !frame.is_empty())
+        .collect())
+}
+
+/// Moves the window's frame to `frame`, maximizing it again there if it was maximized.
+fn place(system: &dyn WindowSystem, window: WindowId, frame: &Rect) -> eyre::Result<()> {
+    let maximized = system.show_state(window) == ShowState::Maximized;
+    set_window_rect(system, window, frame)?;
+    match maximized {
+        true => system.set_show_state(window, ShowState::Maximized),
+        false => Ok(()),
+    }
+}
+
+/// Moves each window with less than `min_visible_percent` of its frame on every work area onto the nearest
+/// monitor, keeping its size if it fits there.
+///
+/// One window failing to move, e.g. because it belongs to an elevated process, doesn't stop the others.
+pub fn rescue_windows(system: &dyn WindowSystem, min_visible_percent: u32) -> eyre::Result<()> {
+    let monitors = system.monitors()?;
+    let min_visible = min_visible_percent as f64 / 100.0;
+    for (window, frame) in movable_windows(system, &Action::RescueWindows)? {
+        if !is_stranded(&frame, &monitors, min_visible) {
+            continue;
+        }
+        let Some(monitor) = nearest_monitor(&frame, &monitors) else {
+            return Err(eyre!("No monitors"));
+        };
+        let rescued = frame.clamp_within(&monitor.work_area);
+        log!(Info, Actions, "Rescuing {:?} from {:?} to {:?}", window, frame, rescued);
+        if let Err(error) = place(system, window, &rescued) {
+            log!(Warn, Actions, "Couldn't rescue {:?}: {:?}", window, error);
+        }
+    }
+    Ok(())
+}
+
+/// Moves every window to the monitor at `index`, keeping its position relative to the top left corner of its work
+/// area, and its size if it fits.
+pub fn gather_all_to_monitor(system: &dyn WindowSystem, index: usize) -> eyre::Result<()> {
+    let monitors = system.monitors()?;
+    let target = monitors
+        .get(index)
+        .ok_or_else(|| eyre!("There's no monitor {}, only {}", index, monitors.len()))?
+        .work_area;
+    for (window, frame) in movable_windows(system, &Action::GatherAllToMonitor { index })? {
+        let Some(source) = monitor_from_rect(&monitors, &frame).map(|monitor| monitor.work_area) else {
+            continue;
+        };
+        if source == target {
+            continue;
+        }
+        let gathered = frame
+            .translate(target.left - source.left, target.top - source.top)
+            .clamp_within(&target);
+        if let Err(error) = place(system, window, &gathered) {
+            log!(Warn, Actions, "Couldn't gather {:?}: {:?}", window, error);
+        }
+    }
     Ok(())
 }
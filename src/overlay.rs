@@ -0,0 +1,205 @@
+use std::ffi::c_void;
+use std::sync::atomic::AtomicPtr;
+use std::sync::{Mutex, Once};
+
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{COLORREF, HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    FONT_WEIGHT, FW_NORMAL, FW_SEMIBOLD, HFONT, MONITOR_DEFAULTTOPRIMARY, TRANSPARENT,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    HWND_TOPMOST, LWA_ALPHA, SWP_NOACTIVATE, SWP_SHOWWINDOW, SW_HIDE, WM_PAINT, WNDCLASSW, WS_EX_LAYERED,
+    WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_POPUP,
+};
+
+use crate::help::{self, CheatSheetRow};
+use crate::safe_win32::{
+    begin_paint, create_font, create_solid_brush, create_window, def_window_proc, delete_object, end_paint, get_dc,
+    get_dpi_for_monitor, get_foreground_window, get_module_handle, get_monitor_info, get_text_extent_point,
+    invalidate_rect, monitor_from_window, register_class, release_dc, select_object, set_bk_mode,
+    set_layered_window_attributes, set_text_color, set_window_pos, show_window, text_out,
+};
+use crate::window_match::WindowInfo;
+use crate::window_system::scale;
+use crate::{ACTIONS, PAUSED, PROFILE};
+
+const CLASS_NAME: &str = "GristCheatSheet";
+const FONT_FACE: &str = "Segoe UI";
+const FONT_HEIGHT: i32 = 20;
+const PADDING: i32 = 24;
+const GAP: i32 = 32;
+const ALPHA: u8 = 224;
+// COLORREFs are 0x00BBGGRR
+const BACKGROUND: COLORREF = COLORREF(0x0020_2020);
+const TEXT: COLORREF = COLORREF(0x00E0_E0E0);
+const HIGHLIGHT: COLORREF = COLORREF(0x0000_C8FF);
+
+static OVERLAY_HWND: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static REGISTER_CLASS: Once = Once::new();
+static LAYOUT: Mutex<Option<Layout>> = Mutex::new(None);
+
+/// What `WM_PAINT` draws, measured when the overlay is shown.
+struct Layout {
+    /// Of the monitor the overlay is shown on. Sizes are scaled from 100%.
+    dpi: u32,
+    title: String,
+    rows: Vec<CheatSheetRow>,
+    line_height: i32,
+    chord_width: i32,
+    column_width: i32,
+    rows_per_column: usize,
+}
+
+fn font(weight: FONT_WEIGHT, dpi: u32) -> HFONT {
+    create_font(-scale(FONT_HEIGHT, dpi), weight, FONT_FACE)
+}
+
+fn create() -> eyre::Result<HWND> {
+    let hinstance = HINSTANCE(get_module_handle(PCWSTR::null())?.0);
+    let class_name = HSTRING::from(CLASS_NAME);
+    let mut registered = Ok(());
+    REGISTER_CLASS.call_once(|| {
+        registered = register_class(&WNDCLASSW {
+            lpfnWndProc: Some(wndproc),
+            hInstance: hinstance,
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            hbrBackground: create_solid_brush(BACKGROUND),
+            ..Default::default()
+        })
+    });
+    registered?;
+
+    // Click-through and never activated, so the foreground window keeps the keyboard
+    let hwnd = create_window(
+        WS_EX_LAYERED | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE | WS_EX_TRANSPARENT,
+        PCWSTR(class_name.as_ptr()),
+        PCWSTR(class_name.as_ptr()),
+        WS_POPUP,
+        0,
+        0,
+        0,
+        0,
+        None,
+        None,
+        Some(hinstance),
+        std::ptr::null_mut(),
+    )?;
+    set_layered_window_attributes(hwnd, COLORREF(0), ALPHA, LWA_ALPHA)?;
+    OVERLAY_HWND.store(hwnd.0, std::sync::atomic::Ordering::Relaxed);
+    Ok(hwnd)
+}
+
+/// Lists the bindings that would fire for the foreground window, centered on its monitor.
+pub fn show() -> eyre::Result<()> {
+    let hwnd = match HWND(OVERLAY_HWND.load(std::sync::atomic::Ordering::Relaxed)) {
+        hwnd if hwnd.is_invalid() => create()?,
+        hwnd => hwnd,
+    };
+
+    let foreground = get_foreground_window()?;
+    let info = WindowInfo::from_hwnd(foreground).ok();
+    let rows = help::cheat_sheet(&ACTIONS.read().unwrap(), info.as_ref());
+    let mut title = String::from("Grist");
+    if let Some(profile) = PROFILE.read().unwrap().as_ref() {
+        title.push_str(&format!(" - {}", profile));
+    }
+    if PAUSED.load(std::sync::atomic::Ordering::Relaxed) {
+        title.push_str(" (paused)");
+    }
+
+    let monitor = monitor_from_window(foreground, MONITOR_DEFAULTTOPRIMARY)?;
+    let work_area = get_monitor_info(monitor)?.rcWork;
+    let dpi = get_dpi_for_monitor(monitor)?;
+    let (padding, gap) = (scale(PADDING, dpi), scale(GAP, dpi));
+
+    // Measure with the font WM_PAINT draws with
+    let hdc = get_dc(Some(hwnd));
+    let font = font(FW_NORMAL, dpi);
+    let previous = select_object(hdc, font.into());
+    let measure = |text: &str| get_text_extent_point(hdc, text).map(|size| (size.cx, size.cy));
+    let (title_width, line_height) = measure(&title)?;
+    let mut chord_width = 0;
+    let mut name_width = 0;
+    for row in &rows {
+        chord_width = chord_width.max(measure(&row.chord)?.0);
+        name_width = name_width.max(measure(&row.name)?.0);
+    }
+    select_object(hdc, previous);
+    let _ = delete_object(font.into());
+    release_dc(Some(hwnd), hdc);
+
+    // Wrap into columns when the rows don't fit the monitor
+    let available = (work_area.bottom - work_area.top) - 2 * padding - 2 * line_height;
+    let rows_per_column = ((available / line_height.max(1)).max(1) as usize).min(rows.len().max(1));
+    let columns = rows.len().div_ceil(rows_per_column).max(1) as i32;
+    let column_width = chord_width + gap + name_width;
+    let width = (columns * column_width + (columns - 1) * gap).max(title_width) + 2 * padding;
+    let height = (rows_per_column as i32 + 2) * line_height + 2 * padding;
+
+    *LAYOUT.lock().unwrap() = Some(Layout {
+        dpi,
+        title,
+        rows,
+        line_height,
+        chord_width,
+        column_width,
+        rows_per_column,
+    });
+
+    set_window_pos(
+        hwnd,
+        Some(HWND_TOPMOST),
+        work_area.left + (work_area.right - work_area.left - width) / 2,
+        work_area.top + (work_area.bottom - work_area.top - height) / 2,
+        width,
+        height,
+        SWP_NOACTIVATE | SWP_SHOWWINDOW,
+    )?;
+    invalidate_rect(hwnd);
+    Ok(())
+}
+
+pub fn hide() {
+    let hwnd = HWND(OVERLAY_HWND.load(std::sync::atomic::Ordering::Relaxed));
+    if !hwnd.is_invalid() {
+        let _ = show_window(hwnd, SW_HIDE);
+    }
+}
+
+fn paint(hwnd: HWND) {
+    let (hdc, paint) = begin_paint(hwnd);
+    if let Some(layout) = LAYOUT.lock().unwrap().as_ref() {
+        let bold = font(FW_SEMIBOLD, layout.dpi);
+        let normal = font(FW_NORMAL, layout.dpi);
+        let (padding, gap) = (scale(PADDING, layout.dpi), scale(GAP, layout.dpi));
+        set_bk_mode(hdc, TRANSPARENT);
+
+        let previous = select_object(hdc, bold.into());
+        set_text_color(hdc, TEXT);
+        text_out(hdc, padding, padding, &layout.title);
+
+        select_object(hdc, normal.into());
+        let top = padding + 2 * layout.line_height;
+        for (index, row) in layout.rows.iter().enumerate() {
+            let column = (index / layout.rows_per_column) as i32;
+            let x = padding + column * (layout.column_width + gap);
+            let y = top + (index % layout.rows_per_column) as i32 * layout.line_height;
+            set_text_color(hdc, if row.scoped { HIGHLIGHT } else { TEXT });
+            text_out(hdc, x, y, &row.chord);
+            text_out(hdc, x + layout.chord_width + gap, y, &row.name);
+        }
+
+        select_object(hdc, previous);
+        let _ = delete_object(bold.into());
+        let _ = delete_object(normal.into());
+    }
+    end_paint(hwnd, &paint);
+}
+
+extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if msg == WM_PAINT {
+        paint(hwnd);
+        return LRESULT(0);
+    }
+    def_window_proc(hwnd, msg, wparam, lparam)
+}
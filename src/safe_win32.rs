@@ -1,30 +1,55 @@
 use std::ffi::c_void;
+use std::path::PathBuf;
 
 use eyre::eyre;
-use windows::core::{BOOL, HSTRING, PCWSTR};
+use windows::core::{BOOL, HSTRING, PCWSTR, PWSTR};
 use windows::Win32::Foundation::{
-    CloseHandle, GetLastError, SetLastError, HANDLE, HINSTANCE, HMODULE, HWND, LPARAM, LRESULT, MAX_PATH, NO_ERROR,
-    POINT, RECT, WPARAM,
+    CloseHandle, GetLastError, GlobalFree, SetLastError, COLORREF, HANDLE, HINSTANCE, HMODULE, HWND, LPARAM, LRESULT,
+    MAX_PATH, NO_ERROR, POINT, RECT, SIZE, WIN32_ERROR, WPARAM,
 };
-use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS};
 use windows::Win32::Graphics::Gdi::{
-    EnumDisplayMonitors, GetMonitorInfoW, MonitorFromWindow, PtInRect, HDC, HMONITOR, MONITORINFO, MONITOR_FROM_FLAGS,
+    BeginPaint, CreateFontW, CreateSolidBrush, DeleteObject, EndPaint, EnumDisplayMonitors, GetDC, GetMonitorInfoW,
+    GetStockObject, GetTextExtentPoint32W, InvalidateRect, MapWindowPoints, MonitorFromWindow, ReleaseDC, SelectObject,
+    SetBkMode, SetTextColor, TextOutW, BACKGROUND_MODE, CLEARTYPE_QUALITY, CLIP_DEFAULT_PRECIS, DEFAULT_CHARSET,
+    FONT_WEIGHT, GET_STOCK_OBJECT_FLAGS, HBRUSH, HDC, HFONT, HGDIOBJ, HMONITOR, MONITORINFO, MONITOR_FROM_FLAGS,
+    OUT_DEFAULT_PRECIS, PAINTSTRUCT,
+};
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData, COPYDATASTRUCT,
 };
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
 use windows::Win32::System::ProcessStatus::GetModuleFileNameExW;
 use windows::Win32::System::RemoteDesktop::{WTSRegisterSessionNotification, WTSUnRegisterSessionNotification};
-use windows::Win32::System::Threading::{OpenProcess, PROCESS_ACCESS_RIGHTS};
-use windows::Win32::UI::Shell::{Shell_NotifyIconW, NOTIFYICONDATAW, NOTIFY_ICON_MESSAGE};
+use windows::Win32::System::Threading::{CreateMutexW, OpenProcess, PROCESS_ACCESS_RIGHTS};
+use windows::Win32::UI::Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK, WINEVENTPROC};
+use windows::Win32::UI::Controls::Dialogs::{GetSaveFileNameW, OFN_OVERWRITEPROMPT, OFN_PATHMUSTEXIST, OPENFILENAMEW};
+use windows::Win32::UI::HiDpi::{
+    GetDpiForMonitor, GetDpiForWindow, SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT, MDT_EFFECTIVE_DPI,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{SendInput, INPUT};
+use windows::Win32::UI::Shell::{
+    SHQueryUserNotificationState, Shell_NotifyIconW, NOTIFYICONDATAW, NOTIFY_ICON_MESSAGE,
+    QUERY_USER_NOTIFICATION_STATE,
+};
 use windows::Win32::UI::WindowsAndMessaging::{
-    CallNextHookEx, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyIcon, DestroyMenu, DispatchMessageW,
-    GetCursorPos, GetForegroundWindow, GetMessageW, GetWindowLongPtrW, GetWindowRect, GetWindowTextLengthW,
-    GetWindowTextW, GetWindowThreadProcessId, InsertMenuW, MessageBoxW, PostMessageW, RegisterClassW, SetCursorPos,
-    SetForegroundWindow, SetWindowLongPtrW, SetWindowPos, SetWindowsHookExW, ShowWindow, ShowWindowAsync,
-    TrackPopupMenu, TranslateMessage, UnhookWindowsHookEx, HHOOK, HICON, HMENU, HOOKPROC, MENU_ITEM_FLAGS,
+    CallNextHookEx, CreateMenu, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyIcon, DestroyMenu,
+    DestroyWindow, DispatchMessageW, EnumChildWindows, EnumWindows, FindWindowW, GetClassNameW, GetCursorPos,
+    GetDlgItem, GetForegroundWindow, GetLayeredWindowAttributes, GetMessageW, GetParent, GetWindow, GetWindowLongPtrW,
+    GetWindowRect, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, InsertMenuW, IsIconic,
+    IsWindowVisible, IsZoomed, KillTimer, MessageBoxW, PostMessageW, RegisterClassW, SendMessageW, SetCursorPos,
+    SetForegroundWindow, SetLayeredWindowAttributes, SetTimer, SetWindowLongPtrW, SetWindowPos, SetWindowTextW,
+    SetWindowsHookExW, ShowWindow, ShowWindowAsync, TrackPopupMenu, TranslateMessage, UnhookWindowsHookEx, GWL_EXSTYLE,
+    GWL_STYLE, GW_OWNER, HHOOK, HICON, HMENU, HOOKPROC, LAYERED_WINDOW_ATTRIBUTES_FLAGS, MENU_ITEM_FLAGS,
     MESSAGEBOX_RESULT, MESSAGEBOX_STYLE, MSG, SET_WINDOW_POS_FLAGS, SHOW_WINDOW_CMD, TRACK_POPUP_MENU_FLAGS,
-    WINDOWS_HOOK_ID, WINDOW_EX_STYLE, WINDOW_LONG_PTR_INDEX, WINDOW_STYLE, WNDCLASSW,
+    WINDOWS_HOOK_ID, WINDOW_EX_STYLE, WINDOW_LONG_PTR_INDEX, WINDOW_STYLE, WM_COPYDATA, WNDCLASSW,
 };
 
+/// From `Win32::System::Ole`, which is too large a feature to enable for one constant.
+const CF_UNICODETEXT: u32 = 13;
+
 pub trait Win32Handle
 where
     Self: std::marker::Sized,
@@ -60,6 +85,17 @@ impl Win32Handle for HMONITOR {
     }
 }
 
+/// Lets a `windows_subsystem = "windows"` process print to the console it was started from.
+pub fn attach_parent_console() -> eyre::Result<()> {
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS).map_err(eyre::Report::from) }
+}
+
+pub fn begin_paint(hwnd: HWND) -> (HDC, PAINTSTRUCT) {
+    let mut paint = PAINTSTRUCT::default();
+    let hdc = unsafe { BeginPaint(hwnd, &mut paint) };
+    (hdc, paint)
+}
+
 pub fn call_next_hook(hhk: Option<HHOOK>, ncode: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     unsafe { CallNextHookEx(hhk, ncode, wparam, lparam) }
 }
@@ -68,6 +104,42 @@ pub fn close_handle(handle: HANDLE) -> eyre::Result<()> {
     unsafe { CloseHandle(handle).map_err(eyre::Report::from) }
 }
 
+/// Creates or opens a named mutex, returning it along with `GetLastError()`, which is `ERROR_ALREADY_EXISTS` if
+/// another process created it first.
+pub fn create_mutex(name: &str) -> eyre::Result<(HANDLE, WIN32_ERROR)> {
+    unsafe {
+        CreateMutexW(None, true, &HSTRING::from(name))
+            .map(|handle| (handle, GetLastError()))
+            .map_err(eyre::Report::from)
+    }
+}
+
+/// A proportional font `height` pixels tall, e.g. `create_font(20, FW_NORMAL, "Segoe UI")`.
+pub fn create_font(height: i32, weight: FONT_WEIGHT, face: &str) -> HFONT {
+    unsafe {
+        CreateFontW(
+            height,
+            0,
+            0,
+            0,
+            weight.0 as i32,
+            0,
+            0,
+            0,
+            DEFAULT_CHARSET,
+            OUT_DEFAULT_PRECIS,
+            CLIP_DEFAULT_PRECIS,
+            CLEARTYPE_QUALITY,
+            0,
+            &HSTRING::from(face),
+        )
+    }
+}
+
+pub fn create_menu() -> eyre::Result<HMENU> {
+    unsafe { CreateMenu().map_err(eyre::Report::from) }
+}
+
 pub fn create_popup_menu() -> eyre::Result<HMENU> {
     unsafe { CreatePopupMenu().map_err(eyre::Report::from) }
 }
@@ -106,6 +178,10 @@ pub fn create_window(
     }
 }
 
+pub fn create_solid_brush(color: COLORREF) -> HBRUSH {
+    unsafe { CreateSolidBrush(color) }
+}
+
 pub fn def_window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
 }
@@ -114,10 +190,18 @@ pub fn destroy_icon(hicon: HICON) -> eyre::Result<()> {
     unsafe { DestroyIcon(hicon).map_err(eyre::Report::from) }
 }
 
+pub fn delete_object(ho: HGDIOBJ) -> eyre::Result<()> {
+    unsafe { DeleteObject(ho).ok().map_err(eyre::Report::from) }
+}
+
 pub fn destroy_menu(hmenu: HMENU) -> eyre::Result<()> {
     unsafe { DestroyMenu(hmenu).map_err(eyre::Report::from) }
 }
 
+pub fn destroy_window(hwnd: HWND) -> eyre::Result<()> {
+    unsafe { DestroyWindow(hwnd).map_err(eyre::Report::from) }
+}
+
 pub fn dispatch_message(msg: &MSG) -> LRESULT {
     unsafe { DispatchMessageW(msg) }
 }
@@ -132,11 +216,18 @@ pub fn dwm_get_window_attribute_extended_frame_bounds(hwnd: HWND) -> eyre::Resul
             std::mem::size_of::<RECT>() as u32,
         )
         .map(|_| extended_frame_bounds)
-        .map_err(|e| eyre!(e.message()))
+        .map_err(eyre::Report::from)
     }
 }
 
 pub fn enum_display_monitors() -> eyre::Result<Vec<MONITORINFO>> {
+    enum_display_monitor_handles()?
+        .into_iter()
+        .map(get_monitor_info)
+        .collect()
+}
+
+pub fn enum_display_monitor_handles() -> eyre::Result<Vec<HMONITOR>> {
     // Callback function for the Win32 EnumDisplayMonitors function
     unsafe extern "system" fn enum_display_monitors_callback(
         hmonitor: HMONITOR,
@@ -144,13 +235,9 @@ pub fn enum_display_monitors() -> eyre::Result<Vec<MONITORINFO>> {
         _rect: *mut RECT,
         monitors: LPARAM,
     ) -> BOOL {
-        if let Ok(monitor_info) = get_monitor_info(hmonitor) {
-            let monitors = &mut *(monitors.0 as *mut Vec<MONITORINFO>);
-            monitors.push(monitor_info);
-            BOOL::from(true)
-        } else {
-            BOOL::from(false)
-        }
+        let monitors = &mut *(monitors.0 as *mut Vec<HMONITOR>);
+        monitors.push(hmonitor);
+        BOOL::from(true)
     }
 
     let mut monitors = Vec::new();
@@ -159,7 +246,7 @@ pub fn enum_display_monitors() -> eyre::Result<Vec<MONITORINFO>> {
             Some(HDC::default()),
             None,
             Some(enum_display_monitors_callback),
-            LPARAM(&mut monitors as *mut Vec<MONITORINFO> as isize),
+            LPARAM(&mut monitors as *mut Vec<HMONITOR> as isize),
         )
     }
     .ok()
@@ -167,15 +254,116 @@ pub fn enum_display_monitors() -> eyre::Result<Vec<MONITORINFO>> {
     .map_err(|_| eyre!("EnumDisplayMonitors() failed"))
 }
 
+pub fn end_paint(hwnd: HWND, paint: &PAINTSTRUCT) {
+    let _ = unsafe { EndPaint(hwnd, paint) };
+}
+
+pub fn find_window(classname: &str, windowname: &str) -> eyre::Result<HWND> {
+    unsafe { FindWindowW(&HSTRING::from(classname), &HSTRING::from(windowname)).map_err(eyre::Report::from) }
+}
+
+pub fn get_class_name(hwnd: HWND) -> eyre::Result<String> {
+    let mut classname = [0u16; 256];
+    match unsafe { GetClassNameW(hwnd, &mut classname) } {
+        0 => Err(std::io::Error::last_os_error().into()),
+        len => String::from_utf16(&classname[..len as usize]).map_err(eyre::Report::from),
+    }
+}
+
+/// Whether DWM hides the window, e.g. because it's on another virtual desktop.
+pub fn dwm_is_cloaked(hwnd: HWND) -> bool {
+    let mut cloaked = 0u32;
+    unsafe {
+        DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_CLOAKED,
+            &mut cloaked as *mut u32 as *mut core::ffi::c_void,
+            std::mem::size_of::<u32>() as u32,
+        )
+    }
+    .is_ok_and(|_| cloaked != 0)
+}
+
+/// The window that owns a top level window, e.g. the main window of a dialog, if there is one.
+pub fn get_window_owner(hwnd: HWND) -> Option<HWND> {
+    unsafe { GetWindow(hwnd, GW_OWNER) }.ok()
+}
+
+/// Every descendant of the window, not just its children.
+pub fn enum_child_windows(hwnd: HWND) -> Vec<HWND> {
+    // Callback function for the Win32 EnumChildWindows function
+    unsafe extern "system" fn enum_child_windows_callback(hwnd: HWND, windows: LPARAM) -> BOOL {
+        let windows = &mut *(windows.0 as *mut Vec<HWND>);
+        windows.push(hwnd);
+        BOOL::from(true)
+    }
+
+    let mut windows = Vec::new();
+    // The return value isn't used
+    let _ = unsafe {
+        EnumChildWindows(
+            Some(hwnd),
+            Some(enum_child_windows_callback),
+            LPARAM(&mut windows as *mut Vec<HWND> as isize),
+        )
+    };
+    windows
+}
+
+pub fn enum_windows() -> eyre::Result<Vec<HWND>> {
+    // Callback function for the Win32 EnumWindows function
+    unsafe extern "system" fn enum_windows_callback(hwnd: HWND, windows: LPARAM) -> BOOL {
+        let windows = &mut *(windows.0 as *mut Vec<HWND>);
+        windows.push(hwnd);
+        BOOL::from(true)
+    }
+
+    let mut windows = Vec::new();
+    unsafe { EnumWindows(Some(enum_windows_callback), LPARAM(&mut windows as *mut Vec<HWND> as isize)) }
+        .map(|_| windows)
+        .map_err(eyre::Report::from)
+}
+
 pub fn get_cursor_pos() -> eyre::Result<POINT> {
     let mut point = Default::default();
     unsafe { GetCursorPos(&mut point).map(|_| point).map_err(eyre::Report::from) }
 }
 
+pub fn get_dc(hwnd: Option<HWND>) -> HDC {
+    unsafe { GetDC(hwnd) }
+}
+
+pub fn get_dlg_item(hdlg: HWND, niddlgitem: i32) -> eyre::Result<HWND> {
+    unsafe { GetDlgItem(Some(hdlg), niddlgitem).map_err(eyre::Report::from) }
+}
+
+/// The effective DPI, which follows the scale the user picked in Display settings.
+pub fn get_dpi_for_monitor(hmonitor: HMONITOR) -> eyre::Result<u32> {
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    unsafe { GetDpiForMonitor(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) }
+        .map(|_| dpi_x)
+        .map_err(eyre::Report::from)
+}
+
+/// The DPI of the monitor a Per-Monitor aware window is on.
+pub fn get_dpi_for_window(hwnd: HWND) -> u32 {
+    unsafe { GetDpiForWindow(hwnd) }
+}
+
 pub fn get_foreground_window() -> eyre::Result<HWND> {
     unsafe { GetForegroundWindow().ok() }
 }
 
+/// Fails for layered windows the app draws with `UpdateLayeredWindow` rather than these attributes.
+pub fn get_layered_window_attributes(hwnd: HWND) -> eyre::Result<(COLORREF, u8, LAYERED_WINDOW_ATTRIBUTES_FLAGS)> {
+    let (mut key, mut alpha, mut flags) = (COLORREF(0), 0, LAYERED_WINDOW_ATTRIBUTES_FLAGS(0));
+    unsafe {
+        GetLayeredWindowAttributes(hwnd, Some(&mut key), Some(&mut alpha), Some(&mut flags))
+            .map(|_| (key, alpha, flags))
+            .map_err(eyre::Report::from)
+    }
+}
+
 pub fn get_message(msg: &mut MSG, hwnd: Option<HWND>, wmsgfiltermin: u32, wmsgfiltermax: u32) -> BOOL {
     unsafe { GetMessageW(msg, hwnd, wmsgfiltermin, wmsgfiltermax) }
 }
@@ -205,6 +393,46 @@ pub fn get_monitor_info(hmonitor: HMONITOR) -> eyre::Result<MONITORINFO> {
     }
 }
 
+/// Shows the Save As dialog, returning `None` if the user cancels it. `filter` is a list of NUL terminated
+/// description and pattern pairs, e.g. `"Text (*.txt)\0*.txt\0"`.
+pub fn get_parent(hwnd: HWND) -> eyre::Result<HWND> {
+    unsafe { GetParent(hwnd).map_err(eyre::Report::from) }
+}
+
+pub fn get_save_file_name(hwnd: HWND, filter: &str, default_ext: &str) -> Option<PathBuf> {
+    let filter: Vec<u16> = filter.encode_utf16().chain(std::iter::once(0)).collect();
+    let default_ext = HSTRING::from(default_ext);
+    let mut file = [0u16; MAX_PATH as usize];
+    let mut ofn = OPENFILENAMEW {
+        lStructSize: std::mem::size_of::<OPENFILENAMEW>() as u32,
+        hwndOwner: hwnd,
+        lpstrFilter: PCWSTR(filter.as_ptr()),
+        lpstrFile: PWSTR(file.as_mut_ptr()),
+        nMaxFile: file.len() as u32,
+        lpstrDefExt: PCWSTR(default_ext.as_ptr()),
+        Flags: OFN_OVERWRITEPROMPT | OFN_PATHMUSTEXIST,
+        ..Default::default()
+    };
+    if !unsafe { GetSaveFileNameW(&mut ofn) }.as_bool() {
+        return None;
+    }
+    let len = file.iter().position(|&c| c == 0).unwrap_or(file.len());
+    Some(PathBuf::from(String::from_utf16_lossy(&file[..len])))
+}
+
+pub fn get_stock_object(i: GET_STOCK_OBJECT_FLAGS) -> HGDIOBJ {
+    unsafe { GetStockObject(i) }
+}
+
+pub fn get_text_extent_point(hdc: HDC, text: &str) -> eyre::Result<SIZE> {
+    let text: Vec<u16> = text.encode_utf16().collect();
+    let mut size = SIZE::default();
+    unsafe { GetTextExtentPoint32W(hdc, &text, &mut size) }
+        .ok()
+        .map(|_| size)
+        .map_err(eyre::Report::from)
+}
+
 pub fn get_window_long_ptr(hwnd: HWND, nindex: WINDOW_LONG_PTR_INDEX) -> eyre::Result<isize> {
     match unsafe { GetWindowLongPtrW(hwnd, nindex) } {
         0 => Err(std::io::Error::last_os_error().into()),
@@ -217,6 +445,18 @@ pub fn get_window_rect(hwnd: HWND) -> eyre::Result<RECT> {
     unsafe { GetWindowRect(hwnd, &mut rect).map(|_| rect).map_err(eyre::Report::from) }
 }
 
+/// The style and extended style. Unlike `get_window_long_ptr`, a style of zero isn't an error.
+pub fn get_window_styles(hwnd: HWND) -> eyre::Result<(WINDOW_STYLE, WINDOW_EX_STYLE)> {
+    let get = |nindex: WINDOW_LONG_PTR_INDEX| unsafe {
+        SetLastError(NO_ERROR);
+        match GetWindowLongPtrW(hwnd, nindex) {
+            0 => GetLastError().ok().map(|_| 0).map_err(eyre::Report::from),
+            longptr => Ok(longptr),
+        }
+    };
+    Ok((WINDOW_STYLE(get(GWL_STYLE)? as u32), WINDOW_EX_STYLE(get(GWL_EXSTYLE)? as u32)))
+}
+
 pub fn get_window_text_length(hwnd: HWND) -> eyre::Result<i32> {
     unsafe {
         SetLastError(NO_ERROR);
@@ -259,6 +499,31 @@ pub fn insert_menu(
     unsafe { InsertMenuW(hmenu, uposition, uflags, uidnewitem, &HSTRING::from(lpnewitem)).map_err(eyre::Report::from) }
 }
 
+pub fn invalidate_rect(hwnd: HWND) {
+    let _ = unsafe { InvalidateRect(Some(hwnd), None, true) };
+}
+
+pub fn is_iconic(hwnd: HWND) -> bool {
+    unsafe { IsIconic(hwnd).as_bool() }
+}
+
+pub fn is_window_visible(hwnd: HWND) -> bool {
+    unsafe { IsWindowVisible(hwnd).as_bool() }
+}
+
+pub fn is_zoomed(hwnd: HWND) -> bool {
+    unsafe { IsZoomed(hwnd).as_bool() }
+}
+
+pub fn kill_timer(hwnd: HWND, uidevent: usize) -> eyre::Result<()> {
+    unsafe { KillTimer(Some(hwnd), uidevent).map_err(eyre::Report::from) }
+}
+
+/// Converts points from one window's client coordinates to another's. `None` is the screen.
+pub fn map_window_points(from: Option<HWND>, to: Option<HWND>, points: &mut [POINT]) {
+    unsafe { MapWindowPoints(from, to, points) };
+}
+
 pub fn message_box(hwnd: Option<HWND>, text: &str, caption: &str, utype: MESSAGEBOX_STYLE) -> MESSAGEBOX_RESULT {
     unsafe { MessageBoxW(hwnd, &HSTRING::from(text), &HSTRING::from(caption), utype) }
 }
@@ -275,14 +540,14 @@ pub fn open_process(
     unsafe { OpenProcess(dwdesiredaccess, binherithandle, dwprocessid).map_err(eyre::Report::from) }
 }
 
-pub fn point_in_rect(rect: &RECT, point: &POINT) -> bool {
-    unsafe { PtInRect(rect, *point).as_bool() }
-}
-
 pub fn post_message(hwnd: Option<HWND>, msg: u32, wparam: WPARAM, lparam: LPARAM) -> eyre::Result<()> {
     unsafe { PostMessageW(hwnd, msg, wparam, lparam).map_err(eyre::Report::from) }
 }
 
+pub fn release_dc(hwnd: Option<HWND>, hdc: HDC) {
+    let _ = unsafe { ReleaseDC(hwnd, hdc) };
+}
+
 pub fn register_class(wndclass: &WNDCLASSW) -> eyre::Result<()> {
     unsafe {
         match RegisterClassW(wndclass) {
@@ -292,6 +557,64 @@ pub fn register_class(wndclass: &WNDCLASSW) -> eyre::Result<()> {
     }
 }
 
+pub fn select_object(hdc: HDC, h: HGDIOBJ) -> HGDIOBJ {
+    unsafe { SelectObject(hdc, h) }
+}
+
+pub fn send_input(inputs: &[INPUT]) -> eyre::Result<()> {
+    match unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) } as usize {
+        sent if sent == inputs.len() => Ok(()),
+        _ => Err(std::io::Error::last_os_error().into()),
+    }
+}
+
+/// Sends `data` to another process' window with `WM_COPYDATA`, failing if the window doesn't handle it.
+pub fn send_copy_data(hwnd: HWND, dwdata: usize, data: &[u8]) -> eyre::Result<()> {
+    let copy_data = COPYDATASTRUCT {
+        dwData: dwdata,
+        cbData: data.len() as u32,
+        lpData: data.as_ptr() as *mut c_void,
+    };
+    match unsafe { SendMessageW(hwnd, WM_COPYDATA, None, Some(LPARAM(&copy_data as *const _ as isize))) } {
+        LRESULT(0) => Err(eyre!("WM_COPYDATA was not handled")),
+        _ => Ok(()),
+    }
+}
+
+pub fn send_message(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe { SendMessageW(hwnd, msg, Some(wparam), Some(lparam)) }
+}
+
+pub fn set_bk_mode(hdc: HDC, mode: BACKGROUND_MODE) {
+    let _ = unsafe { SetBkMode(hdc, mode) };
+}
+
+/// Replaces the clipboard's contents with `text`.
+pub fn set_clipboard_text(hwnd: HWND, text: &str) -> eyre::Result<()> {
+    let text: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        let hmem = GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of_val(text.as_slice()))?;
+        let ptr = GlobalLock(hmem) as *mut u16;
+        if ptr.is_null() {
+            let _ = GlobalFree(Some(hmem));
+            return Err(std::io::Error::last_os_error().into());
+        }
+        std::ptr::copy_nonoverlapping(text.as_ptr(), ptr, text.len());
+        let _ = GlobalUnlock(hmem);
+
+        let result = OpenClipboard(Some(hwnd)).and_then(|_| {
+            let result = EmptyClipboard().and_then(|_| SetClipboardData(CF_UNICODETEXT, Some(HANDLE(hmem.0))));
+            let _ = CloseClipboard();
+            result
+        });
+        // The clipboard owns the memory only once SetClipboardData succeeds
+        if result.is_err() {
+            let _ = GlobalFree(Some(hmem));
+        }
+        result.map(|_| ()).map_err(eyre::Report::from)
+    }
+}
+
 pub fn set_cursor_pos(x: i32, y: i32) -> eyre::Result<()> {
     unsafe { SetCursorPos(x, y).map_err(eyre::Report::from) }
 }
@@ -300,6 +623,31 @@ pub fn set_foreground_window(hwnd: HWND) -> eyre::Result<()> {
     unsafe { SetForegroundWindow(hwnd).ok().map_err(eyre::Report::from) }
 }
 
+/// Makes a `WS_EX_LAYERED` window translucent.
+pub fn set_layered_window_attributes(
+    hwnd: HWND,
+    key: COLORREF,
+    alpha: u8,
+    flags: LAYERED_WINDOW_ATTRIBUTES_FLAGS,
+) -> eyre::Result<()> {
+    unsafe { SetLayeredWindowAttributes(hwnd, key, alpha, flags).map_err(eyre::Report::from) }
+}
+
+pub fn set_process_dpi_awareness_context(value: DPI_AWARENESS_CONTEXT) -> eyre::Result<()> {
+    unsafe { SetProcessDpiAwarenessContext(value).map_err(eyre::Report::from) }
+}
+
+pub fn set_text_color(hdc: HDC, color: COLORREF) {
+    let _ = unsafe { SetTextColor(hdc, color) };
+}
+
+pub fn set_timer(hwnd: HWND, nidevent: usize, uelapse: u32) -> eyre::Result<()> {
+    match unsafe { SetTimer(Some(hwnd), nidevent, uelapse, None) } {
+        0 => Err(std::io::Error::last_os_error().into()),
+        _ => Ok(()),
+    }
+}
+
 pub fn set_window_long_ptr(hwnd: HWND, nindex: WINDOW_LONG_PTR_INDEX, dwnewlong: isize) -> eyre::Result<isize> {
     unsafe {
         SetLastError(NO_ERROR);
@@ -314,6 +662,10 @@ pub fn set_window_long_ptr(hwnd: HWND, nindex: WINDOW_LONG_PTR_INDEX, dwnewlong:
     }
 }
 
+pub fn set_window_text(hwnd: HWND, text: &str) -> eyre::Result<()> {
+    unsafe { SetWindowTextW(hwnd, &HSTRING::from(text)).map_err(eyre::Report::from) }
+}
+
 pub fn set_window_pos(
     hwnd: HWND,
     hwndinsertafter: Option<HWND>,
@@ -326,6 +678,18 @@ pub fn set_window_pos(
     unsafe { SetWindowPos(hwnd, hwndinsertafter, x, y, cx, cy, uflags).map_err(eyre::Report::from) }
 }
 
+pub fn set_win_event_hook(
+    eventmin: u32,
+    eventmax: u32,
+    pfnwineventproc: WINEVENTPROC,
+    dwflags: u32,
+) -> eyre::Result<HWINEVENTHOOK> {
+    match unsafe { SetWinEventHook(eventmin, eventmax, None, pfnwineventproc, 0, 0, dwflags) } {
+        hook if hook.is_invalid() => Err(eyre!("SetWinEventHook() failed")),
+        hook => Ok(hook),
+    }
+}
+
 pub fn set_windows_hook(
     idhook: WINDOWS_HOOK_ID,
     lpfn: HOOKPROC,
@@ -335,6 +699,10 @@ pub fn set_windows_hook(
     unsafe { SetWindowsHookExW(idhook, lpfn, hinstance, dwthreadid).map_err(eyre::Report::from) }
 }
 
+pub fn sh_query_user_notification_state() -> eyre::Result<QUERY_USER_NOTIFICATION_STATE> {
+    unsafe { SHQueryUserNotificationState().map_err(eyre::Report::from) }
+}
+
 pub fn shell_notify_icon(dwmessage: NOTIFY_ICON_MESSAGE, lpdata: &mut NOTIFYICONDATAW) -> eyre::Result<()> {
     unsafe { Shell_NotifyIconW(dwmessage, lpdata).ok().map_err(eyre::Report::from) }
 }
@@ -348,6 +716,11 @@ pub fn show_window_async(hwnd: HWND, ncmdshow: SHOW_WINDOW_CMD) -> eyre::Result<
     unsafe { ShowWindowAsync(hwnd, ncmdshow).ok().map_err(eyre::Report::from) }
 }
 
+pub fn text_out(hdc: HDC, x: i32, y: i32, text: &str) {
+    let text: Vec<u16> = text.encode_utf16().collect();
+    let _ = unsafe { TextOutW(hdc, x, y, &text) };
+}
+
 pub fn track_popup_menu(
     hmenu: HMENU,
     uflags: TRACK_POPUP_MENU_FLAGS,
@@ -367,6 +740,10 @@ pub fn translate_message(msg: &MSG) -> BOOL {
     unsafe { TranslateMessage(msg) }
 }
 
+pub fn unhook_win_event(hwineventhook: HWINEVENTHOOK) -> eyre::Result<()> {
+    unsafe { UnhookWinEvent(hwineventhook).ok().map_err(eyre::Report::from) }
+}
+
 pub fn unhook_windows_hook_ex(hhk: HHOOK) -> eyre::Result<()> {
     unsafe { UnhookWindowsHookEx(hhk).map_err(eyre::Report::from) }
 }
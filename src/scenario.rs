@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+use eyre::{eyre, WrapErr};
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::fake_desktop::{FakeDesktop, WindowKind};
+use crate::geometry::{Margins, Point, Rect, Size};
+use crate::hotkey_action::{self, Action, VK};
+use crate::keyboard::KeyboardState;
+use crate::launch::RecordingLauncher;
+use crate::window_actions;
+use crate::window_match::WindowInfo;
+use crate::window_system::{Layering, Monitor, ShowState, WindowSystem};
+use crate::{IGNORE, PAUSED, RESCUE, TEST_GLOBALS};
+
+/// Where `cargo test` looks for scenario files, relative to the crate root.
+const SCENARIO_DIR: &str = "tests/scenarios";
+
+/// A desktop, a config, the keys the user presses and what the desktop should look like afterwards.
+///
+/// Scenarios are YAML files in `tests/scenarios`, so that regressions can be described without writing Rust.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    #[serde(default)]
+    description: String,
+    /// The first monitor is the primary one.
+    monitors: Vec<Monitor>,
+    /// Opened in order, so the last one is on top.
+    #[serde(default)]
+    windows: Vec<WindowSpec>,
+    /// The window that has focus at the start. Defaults to the last one opened.
+    focus: Option<String>,
+    #[serde(default)]
+    cursor: Point,
+    /// The same as `grist.yaml`. Missing keys take their usual defaults, built-in bindings included.
+    #[serde(default)]
+    config: Config,
+    #[serde(default)]
+    keys: Vec<KeyStep>,
+    expect: Expect,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WindowSpec {
+    /// What the rest of the scenario calls the window.
+    name: String,
+    #[serde(default)]
+    exe: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    title: String,
+    /// The visible frame.
+    frame: Rect,
+    /// The invisible resize borders around the frame.
+    #[serde(default)]
+    border: Margins,
+    #[serde(default)]
+    state: ShowState,
+    #[serde(default)]
+    topmost: bool,
+    /// Layered by the app itself.
+    #[serde(default)]
+    layered: bool,
+    /// Run as administrator, so that its exe, class and title can't be read.
+    #[serde(default)]
+    elevated: bool,
+    /// Whether it's an ordinary app window or part of the shell, a tool window and so on.
+    #[serde(default)]
+    kind: WindowKind,
+    /// Hidden by the app, as tray apps do with their main window.
+    #[serde(default)]
+    hidden: bool,
+    /// The app's size limits, for the window rect including the border.
+    min_size: Option<Size>,
+    max_size: Option<Size>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyStep {
+    /// Milliseconds since the start of the scenario.
+    #[serde(default)]
+    at: u64,
+    #[serde(flatten)]
+    event: KeyEvent,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum KeyEvent {
+    Down(VK),
+    Up(VK),
+    /// Each key down in order, then up in reverse order.
+    Press(Vec<VK>),
+}
+
+impl KeyEvent {
+    /// Each key with whether it goes down.
+    fn keys(&self) -> Vec<(VK, bool)> {
+        match self {
+            KeyEvent::Down(vk) => vec![(*vk, true)],
+            KeyEvent::Up(vk) => vec![(*vk, false)],
+            KeyEvent::Press(vks) => vks
+                .iter()
+                .map(|vk| (*vk, true))
+                .chain(vks.iter().rev().map(|vk| (*vk, false)))
+                .collect(),
+        }
+    }
+}
+
+/// Only what's listed is checked.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expect {
+    #[serde(default)]
+    windows: BTreeMap<String, WindowExpect>,
+    focus: Option<String>,
+    cursor: Option<Point>,
+    /// The commands launched, in order.
+    launched: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WindowExpect {
+    frame: Option<Rect>,
+    state: Option<ShowState>,
+    topmost: Option<bool>,
+    borderless: Option<bool>,
+    layered: Option<bool>,
+    opacity: Option<u32>,
+    click_through: Option<bool>,
+}
+
+fn load(path: &Path) -> eyre::Result<Scenario> {
+    let text = std::fs::read_to_string(path)?;
+    serde_yaml::from_str(&text).map_err(eyre::Report::from)
+}
+
+/// Plays the scenario's keys and returns how the result differs from what it expects.
+fn run(scenario: &Scenario) -> eyre::Result<Vec<String>> {
+    let desktop = FakeDesktop::new(scenario.monitors.clone());
+    let mut windows = BTreeMap::new();
+    for spec in &scenario.windows {
+        let info = WindowInfo {
+            exe: spec.exe.clone(),
+            class: spec.class.clone(),
+            title: spec.title.clone(),
+        };
+        let id = desktop.add_window(info, spec.frame);
+        desktop.set_border(id, spec.border)?;
+        desktop.set_size_limits(id, spec.min_size, spec.max_size)?;
+        desktop.set_elevated(id, spec.elevated)?;
+        desktop.set_kind(id, spec.kind)?;
+        desktop.set_visible(id, !spec.hidden)?;
+        if spec.topmost {
+            desktop.set_topmost(id, true)?;
+        }
+        if spec.layered {
+            desktop.set_layering(id, &Layering(vec![1]))?;
+        }
+        if spec.state != ShowState::Normal {
+            desktop.set_show_state(id, spec.state)?;
+        }
+        if windows.insert(spec.name.clone(), id).is_some() {
+            return Err(eyre!("There are two windows named '{}'", spec.name));
+        }
+    }
+    let window = |name: &String| {
+        windows
+            .get(name)
+            .copied()
+            .ok_or_else(|| eyre!("No window named '{}'", name))
+    };
+    if let Some(focus) = &scenario.focus {
+        desktop.set_foreground_window(window(focus)?)?;
+    }
+    desktop.move_cursor(scenario.cursor);
+
+    let launcher = RecordingLauncher::default();
+    let actions = scenario.config.keymap();
+    *IGNORE.write().unwrap() = scenario.config.ignore.clone();
+    *RESCUE.write().unwrap() = scenario.config.rescue;
+    PAUSED.store(false, Ordering::Relaxed);
+    window_actions::forget_windows();
+
+    // As `low_level_keyboard_proc` does, less the Windows only parts
+    let mut keyboard = KeyboardState::new();
+    let start = Instant::now();
+    for step in &scenario.keys {
+        for (vk, down) in step.event.keys() {
+            match down {
+                true => keyboard.key_down(vk, start + Duration::from_millis(step.at)),
+                false => keyboard.key_up(vk),
+            };
+
+            let foreground = desktop
+                .foreground_window()
+                .and_then(|window| desktop.window_info(window))
+                .ok();
+            let suspended = PAUSED.load(Ordering::Relaxed)
+                || foreground
+                    .as_ref()
+                    .is_some_and(|info| scenario.config.passthrough.iter().any(|window| window.matches(info)));
+            let binding = hotkey_action::find_binding(&actions, keyboard.pressed(), foreground.as_ref())
+                .filter(|action| action.action == Action::TogglePause || !suspended);
+            if let Some(action) = binding {
+                action
+                    .apply_with(&desktop, &launcher)
+                    .wrap_err_with(|| format!("'{}' failed at {}ms", action.name, step.at))?;
+            }
+        }
+    }
+
+    let mut mismatches = Vec::new();
+    for (name, expected) in &scenario.expect.windows {
+        let actual = desktop.window(window(name)?)?;
+        if let Some(frame) = expected.frame.filter(|&frame| frame != actual.frame_bounds()) {
+            mismatches.push(format!(
+                "'{}' frame: expected {:?}, got {:?}",
+                name,
+                frame,
+                actual.frame_bounds()
+            ));
+        }
+        if let Some(state) = expected.state.filter(|&state| state != actual.show_state) {
+            mismatches.push(format!("'{}' state: expected {:?}, got {:?}", name, state, actual.show_state));
+        }
+        if let Some(topmost) = expected.topmost.filter(|&topmost| topmost != actual.topmost) {
+            mismatches.push(format!("'{}' topmost: expected {}, got {}", name, topmost, actual.topmost));
+        }
+        if let Some(borderless) = expected
+            .borderless
+            .filter(|&borderless| borderless != actual.borderless)
+        {
+            mismatches.push(format!(
+                "'{}' borderless: expected {}, got {}",
+                name, borderless, actual.borderless
+            ));
+        }
+        if let Some(layered) = expected.layered.filter(|&layered| layered != actual.layered) {
+            mismatches.push(format!("'{}' layered: expected {}, got {}", name, layered, actual.layered));
+        }
+        let translucency = actual.translucency;
+        if let Some(opacity) = expected.opacity.filter(|&opacity| opacity != translucency.opacity) {
+            mismatches.push(format!(
+                "'{}' opacity: expected {}, got {}",
+                name, opacity, translucency.opacity
+            ));
+        }
+        if let Some(click_through) = expected
+            .click_through
+            .filter(|&click_through| click_through != translucency.click_through)
+        {
+            mismatches.push(format!(
+                "'{}' click_through: expected {}, got {}",
+                name, click_through, translucency.click_through
+            ));
+        }
+    }
+    if let Some(focus) = &scenario.expect.focus {
+        let actual = desktop
+            .foreground()
+            .and_then(|id| windows.iter().find(|(_, &window)| window == id).map(|(name, _)| name));
+        if actual != Some(focus) {
+            mismatches.push(format!("focus: expected '{}', got {:?}", focus, actual));
+        }
+    }
+    if let Some(cursor) = scenario.expect.cursor.filter(|&cursor| cursor != desktop.cursor()) {
+        mismatches.push(format!("cursor: expected {:?}, got {:?}", cursor, desktop.cursor()));
+    }
+    if let Some(launched) = scenario
+        .expect
+        .launched
+        .as_ref()
+        .filter(|&launched| *launched != *launcher.launched.borrow())
+    {
+        mismatches.push(format!(
+            "launched: expected {:?}, got {:?}",
+            launched,
+            launcher.launched.borrow()
+        ));
+    }
+    Ok(mismatches)
+}
+
+fn scenario_files() -> eyre::Result<Vec<PathBuf>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(SCENARIO_DIR);
+    let mut paths = std::fs::read_dir(&dir)
+        .wrap_err_with(|| format!("Couldn't list {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, std::io::Error>>()?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "yaml"));
+    paths.sort();
+    Ok(paths)
+}
+
+/// Runs every scenario, one after another since they share the binding globals, and reports all the failures
+/// together.
+#[test]
+fn scenarios() -> eyre::Result<()> {
+    let _globals = TEST_GLOBALS.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut failures = Vec::new();
+    for path in scenario_files()? {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let scenario = match load(&path) {
+            Ok(scenario) => scenario,
+            Err(error) => {
+                failures.push(format!("{}:\n    {:?}", name, error));
+                continue;
+            }
+        };
+        match run(&scenario) {
+            Ok(mismatches) if mismatches.is_empty() => (),
+            Ok(mismatches) => {
+                failures.push(format!("{}: {}\n    {}", name, scenario.description, mismatches.join("\n    ")))
+            }
+            Err(error) => failures.push(format!("{}: {}\n    {:?}", name, scenario.description, error)),
+        }
+    }
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(eyre!("{} scenario(s) failed\n{}", failures.len(), failures.join("\n"))),
+    }
+}
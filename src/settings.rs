@@ -0,0 +1,177 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use eyre::{eyre, WrapErr};
+use num_traits::Bounded;
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Value};
+
+use crate::hotkey_action::{Action, ActionInfo, ACTION_INFO};
+
+/// The `ACTION_INFO` entry for an action name.
+pub fn action_info(name: &str) -> Option<&'static ActionInfo> {
+    ACTION_INFO.iter().find(|info| info.name == name)
+}
+
+/// The text of each of the action's parameter fields, in `ActionInfo::params` order.
+///
+/// Numbers and strings are shown as they are, lists of strings one per line, and anything else as YAML.
+pub fn param_texts(action: &Action) -> Vec<String> {
+    let Some(info) = action_info(action.name()) else {
+        return Vec::new();
+    };
+    // Unit variants serialize to their name, and the others to their parameters tagged with the name
+    let params = match serde_yaml::to_value(action) {
+        Ok(Value::Tagged(tagged)) => Some(tagged.value),
+        _ => None,
+    };
+
+    info.params
+        .iter()
+        .map(|(name, ty)| {
+            let value = match (&params, *name) {
+                (Some(params), "") => Some(params),
+                (Some(params), name) => params.get(name),
+                (None, _) => None,
+            };
+            value.map_or(String::new(), |value| param_text(ty, value))
+        })
+        .collect()
+}
+
+fn param_text(ty: &str, value: &Value) -> String {
+    match (ty, value) {
+        (_, Value::Null) => String::new(),
+        (_, Value::Number(number)) => number.to_string(),
+        (_, Value::String(text)) => text.clone(),
+        ("[String]", Value::Sequence(items)) => {
+            items.iter().filter_map(Value::as_str).collect::<Vec<&str>>().join("\n")
+        }
+        (_, value) => serde_yaml::to_string(value).unwrap_or_default().trim_end().to_owned(),
+    }
+}
+
+/// Builds an action from the text of its parameter fields, naming the field that doesn't parse.
+pub fn parse_action(info: &ActionInfo, texts: &[String]) -> eyre::Result<Action> {
+    let value = match info.params {
+        [] => Value::String(info.name.to_owned()),
+        [("", ty)] => tagged(info.name, parse_param("", ty, texts.first().map_or("", String::as_str))?),
+        params => {
+            let mut mapping = Mapping::new();
+            for (i, (name, ty)) in params.iter().enumerate() {
+                // Left out, optional parameters take their defaults
+                match parse_param(name, ty, texts.get(i).map_or("", String::as_str))? {
+                    Value::Null => None,
+                    value => mapping.insert(Value::String((*name).to_owned()), value),
+                };
+            }
+            tagged(info.name, Value::Mapping(mapping))
+        }
+    };
+    serde_yaml::from_value(value).wrap_err_with(|| format!("Invalid {} parameters", info.name))
+}
+
+/// A variant with parameters, as `serde_yaml` reads it.
+fn tagged(name: &str, value: Value) -> Value {
+    Value::Tagged(Box::new(TaggedValue { tag: Tag::new(name), value }))
+}
+
+/// Whether a parameter of type `ty` is edited on one line, rather than as a list or YAML.
+#[cfg(windows)]
+pub fn is_single_line(ty: &str) -> bool {
+    matches!(
+        ty.trim_end_matches('?'),
+        "i32" | "u32" | "u64" | "usize" | "String" | "Units" | "Corner"
+    )
+}
+
+fn parse_param(name: &str, ty: &str, text: &str) -> eyre::Result<Value> {
+    let label = if name.is_empty() { "The parameter" } else { name };
+    // Left empty, optional parameters take their defaults, e.g. physical pixels for `Units`
+    if ty.ends_with('?') && text.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+    match ty.trim_end_matches('?') {
+        "i32" => parse_number::<i32>(label, text),
+        "u32" => parse_number::<u32>(label, text),
+        "u64" => parse_number::<u64>(label, text),
+        "usize" => parse_number::<usize>(label, text),
+        "String" if text.trim().is_empty() => Err(eyre!("'{}' is required", label)),
+        "String" => Ok(Value::String(text.trim().to_owned())),
+        "[String]" => Ok(Value::Sequence(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| Value::String(line.to_owned()))
+                .collect(),
+        )),
+        // WindowMatch and [Action] are edited as YAML
+        ty => serde_yaml::from_str(text).wrap_err_with(|| format!("'{}' must be YAML describing a {}", label, ty)),
+    }
+}
+
+fn parse_number<T>(label: &str, text: &str) -> eyre::Result<Value>
+where
+    T: Bounded + Display + FromStr + Into<Value>,
+{
+    text.trim().parse::<T>().map(Into::into).map_err(|_| {
+        eyre!(
+            "'{}' must be a whole number from {} to {}",
+            label,
+            T::min_value(),
+            T::max_value()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey_action::Units;
+    use crate::window_match::WindowMatch;
+
+    fn round_trip(action: Action) {
+        let info = action_info(action.name()).unwrap();
+        assert_eq!(parse_action(info, &param_texts(&action)).unwrap(), action);
+    }
+
+    #[test]
+    fn actions_round_trip_through_their_fields() {
+        round_trip(Action::Maximize);
+        round_trip(Action::OnMonitor {
+            x: -8,
+            y: 0,
+            w: 16,
+            h: 8,
+            units: Units::Logical,
+        });
+        round_trip(Action::FocusOrLaunch {
+            window: WindowMatch::exe("notepad.exe"),
+            command: "notepad.exe".to_owned(),
+        });
+        round_trip(Action::Launch {
+            command: "code".to_owned(),
+            args: vec!["--new-window".to_owned(), "notes".to_owned()],
+            cwd: Some("C:\\Users".to_owned()),
+        });
+        round_trip(Action::Sequence(vec![Action::Maximize, Action::Delay { ms: 100 }]));
+    }
+
+    #[test]
+    fn fields_are_shown_as_typed() {
+        let action = Action::Launch {
+            command: "code".to_owned(),
+            args: vec!["--new-window".to_owned(), "notes".to_owned()],
+            cwd: None,
+        };
+        assert_eq!(param_texts(&action), ["code", "--new-window\nnotes", ""]);
+    }
+
+    #[test]
+    fn bad_fields_are_named() {
+        let info = action_info("OnDesktop").unwrap();
+        let texts = ["0", "0", "wide", "600"].map(str::to_owned);
+        let error = parse_action(info, &texts).unwrap_err();
+        assert_eq!(error.to_string(), "'w' must be a whole number from -2147483648 to 2147483647");
+    }
+}
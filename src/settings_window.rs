@@ -0,0 +1,615 @@
+use std::collections::BTreeSet;
+use std::ffi::c_void;
+use std::path::Path;
+use std::sync::atomic::AtomicPtr;
+use std::sync::{Mutex, Once};
+
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Graphics::Gdi::{COLOR_BTNFACE, HBRUSH};
+use windows::Win32::UI::Controls::EM_SETREADONLY;
+use windows::Win32::UI::WindowsAndMessaging::{
+    BN_CLICKED, BS_PUSHBUTTON, CBN_SELCHANGE, CBS_DROPDOWNLIST, CB_ADDSTRING, CB_GETCURSEL, CB_SETCURSEL,
+    CW_USEDEFAULT, ES_AUTOHSCROLL, ES_AUTOVSCROLL, ES_MULTILINE, ES_WANTRETURN, HMENU, IDYES, LBN_SELCHANGE,
+    LBS_NOINTEGRALHEIGHT, LBS_NOTIFY, LB_ADDSTRING, LB_GETCURSEL, LB_RESETCONTENT, LB_SETCURSEL, MB_ICONWARNING, MB_OK,
+    MB_YESNO, WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP, WM_COMMAND, WM_CREATE, WM_DESTROY, WM_DPICHANGED, WM_SETFONT,
+    WNDCLASSW, WS_BORDER, WS_CAPTION, WS_CHILD, WS_EX_CLIENTEDGE, WS_MINIMIZEBOX, WS_SYSMENU, WS_TABSTOP, WS_VISIBLE,
+    WS_VSCROLL,
+};
+
+use crate::config::{self, Config};
+use crate::dpi;
+use crate::hotkey_action::{Action, ActionInfo, HotkeyAction, ACTION_INFO, VK};
+use crate::keyboard::ChordRecorder;
+use crate::safe_win32::{
+    create_window, def_window_proc, destroy_window, get_dlg_item, get_module_handle, get_window_text, message_box,
+    post_message, register_class, send_message, set_foreground_window, set_window_text,
+};
+use crate::settings::{self, action_info};
+use crate::{create_actions, load_config, log};
+
+const CLASS_NAME: &str = "GristSettings";
+const TITLE: &str = "Grist Settings";
+const LIST_ID: i32 = 0x01;
+const NEW_ID: i32 = 0x02;
+const DELETE_ID: i32 = 0x03;
+const NAME_ID: i32 = 0x04;
+const CHORD_ID: i32 = 0x05;
+const RECORD_ID: i32 = 0x06;
+const ACTION_ID: i32 = 0x07;
+const WARNING_ID: i32 = 0x08;
+const APPLY_ID: i32 = 0x09;
+const SAVE_ID: i32 = 0x0A;
+/// Parameter fields and their labels are numbered from here, in `ActionInfo::params` order.
+const PARAM_FIRST: i32 = 0x100;
+const PARAM_LABEL_FIRST: i32 = 0x200;
+/// The most parameters any action has.
+const MAX_PARAMS: i32 = 5;
+
+const MARGIN: i32 = 8;
+const ROW_HEIGHT: i32 = 24;
+const ROW_SPACING: i32 = 32;
+const LIST_WIDTH: i32 = 280;
+const LABEL_WIDTH: i32 = 96;
+const FIELD_WIDTH: i32 = 340;
+const BUTTON_WIDTH: i32 = 92;
+const FORM_X: i32 = MARGIN + LIST_WIDTH + 2 * MARGIN;
+const FIELD_X: i32 = FORM_X + LABEL_WIDTH;
+const PARAMS_Y: i32 = MARGIN + 3 * ROW_SPACING;
+const BOTTOM_Y: i32 = 432;
+const WM_CHORD_RECORDED: u32 = WM_APP + 1;
+
+static SETTINGS_HWND: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static REGISTER_CLASS: Once = Once::new();
+static STATE: Mutex<Option<Settings>> = Mutex::new(None);
+static RECORDER: Mutex<ChordRecorder> = Mutex::new(ChordRecorder::new());
+static RECORDED: Mutex<Option<BTreeSet<VK>>> = Mutex::new(None);
+
+/// The config being edited, and the binding shown in the form.
+///
+/// The lock is never held across a message box, whose modal loop would dispatch more messages to this window.
+struct Settings {
+    config: Config,
+    /// Listed before `config.actions` while `config.default_actions` is set. Editing one copies them all into
+    /// `config.actions`.
+    builtins: Vec<HotkeyAction>,
+    selected: Option<usize>,
+    /// The chord in the form, which becomes the binding's trigger when it's applied.
+    trigger: BTreeSet<VK>,
+    /// The action type in the form.
+    info: &'static ActionInfo,
+}
+
+impl Settings {
+    fn entries(&self) -> impl Iterator<Item = &HotkeyAction> {
+        self.builtins.iter().chain(self.config.actions.iter())
+    }
+
+    fn entry(&self, index: usize) -> Option<&HotkeyAction> {
+        self.entries().nth(index)
+    }
+
+    /// Built-in bindings come from code, so the only way to change one is to write them all to the config file.
+    fn take_builtins(&mut self) {
+        let mut actions = std::mem::take(&mut self.builtins);
+        actions.append(&mut self.config.actions);
+        self.config.actions = actions;
+        self.config.default_actions = false;
+    }
+
+    /// The binding, including the active profile's, that shares `binding`'s chord and windows.
+    fn conflict(&self, binding: &HotkeyAction) -> Option<&HotkeyAction> {
+        let profile = self
+            .config
+            .profile
+            .as_ref()
+            .and_then(|profile| self.config.profiles.get(profile))
+            .into_iter()
+            .flatten();
+        self.entries()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != self.selected)
+            .map(|(_, action)| action)
+            .chain(profile)
+            .find(|action| action.conflicts_with(binding))
+    }
+}
+
+fn hwnd() -> HWND {
+    HWND(SETTINGS_HWND.load(std::sync::atomic::Ordering::Relaxed))
+}
+
+/// Opens the settings window on the config file, or raises it if it's already open.
+pub fn show() -> eyre::Result<()> {
+    if !hwnd().is_invalid() {
+        return set_foreground_window(hwnd());
+    }
+
+    let config = config::load(Path::new(config::CONFIG_FILE))?;
+    let builtins = match config.default_actions {
+        true => create_actions(),
+        false => Vec::new(),
+    };
+    *STATE.lock().unwrap() = Some(Settings {
+        config,
+        builtins,
+        selected: None,
+        trigger: BTreeSet::new(),
+        info: &ACTION_INFO[0],
+    });
+
+    let hinstance = HINSTANCE(get_module_handle(PCWSTR::null())?.0);
+    let class_name = HSTRING::from(CLASS_NAME);
+    let mut registered = Ok(());
+    REGISTER_CLASS.call_once(|| {
+        registered = register_class(&WNDCLASSW {
+            lpfnWndProc: Some(wndproc),
+            hInstance: hinstance,
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize as *mut c_void),
+            ..Default::default()
+        })
+    });
+    registered?;
+
+    create_window(
+        WINDOW_EX_STYLE::default(),
+        PCWSTR(class_name.as_ptr()),
+        PCWSTR(HSTRING::from(TITLE).as_ptr()),
+        WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
+        CW_USEDEFAULT,
+        CW_USEDEFAULT,
+        FIELD_X + FIELD_WIDTH + 2 * MARGIN + 16,
+        BOTTOM_Y + ROW_HEIGHT + 2 * MARGIN + 40,
+        None,
+        None,
+        Some(hinstance),
+        std::ptr::null_mut(),
+    )?;
+    Ok(())
+}
+
+/// Feeds the keys that are down after each event to the chord being recorded. Returns whether one is being
+/// recorded, in which case the hook swallows the event.
+pub fn record_chord(pressed: &BTreeSet<VK>) -> bool {
+    let mut recorder = RECORDER.lock().unwrap();
+    if !recorder.is_recording() {
+        return false;
+    }
+    if let Some(chord) = recorder.update(pressed) {
+        *RECORDED.lock().unwrap() = Some(chord);
+        let _ = post_message(Some(hwnd()), WM_CHORD_RECORDED, WPARAM(0), LPARAM(0));
+    }
+    true
+}
+
+fn create_child(
+    hwnd: HWND,
+    class: &str,
+    text: &str,
+    exstyle: WINDOW_EX_STYLE,
+    style: WINDOW_STYLE,
+    id: i32,
+    (x, y, width, height): (i32, i32, i32, i32),
+) -> eyre::Result<HWND> {
+    // The layout is in pixels at 100% scale
+    let child = create_window(
+        exstyle,
+        PCWSTR(HSTRING::from(class).as_ptr()),
+        PCWSTR(HSTRING::from(text).as_ptr()),
+        WS_CHILD | WS_VISIBLE | style,
+        dpi::scale(hwnd, x),
+        dpi::scale(hwnd, y),
+        dpi::scale(hwnd, width),
+        dpi::scale(hwnd, height),
+        Some(hwnd),
+        Some(HMENU(id as isize as *mut c_void)),
+        None,
+        std::ptr::null_mut(),
+    )?;
+    let font = dpi::font(dpi::window_dpi(hwnd));
+    send_message(child, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(0));
+    Ok(child)
+}
+
+fn label(hwnd: HWND, text: &str, id: i32, y: i32) -> eyre::Result<HWND> {
+    let rect = (FORM_X, y + 4, LABEL_WIDTH - MARGIN, ROW_HEIGHT);
+    create_child(
+        hwnd,
+        "STATIC",
+        text,
+        WINDOW_EX_STYLE::default(),
+        WINDOW_STYLE::default(),
+        id,
+        rect,
+    )
+}
+
+fn button(hwnd: HWND, text: &str, id: i32, x: i32, y: i32) -> eyre::Result<HWND> {
+    let style = WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32);
+    let rect = (x, y, BUTTON_WIDTH, ROW_HEIGHT);
+    create_child(hwnd, "BUTTON", text, WINDOW_EX_STYLE::default(), style, id, rect)
+}
+
+fn edit(hwnd: HWND, id: i32, y: i32, width: i32, multiline: bool) -> eyre::Result<HWND> {
+    let (style, height) = match multiline {
+        true => (ES_MULTILINE | ES_AUTOVSCROLL | ES_WANTRETURN, 3 * ROW_HEIGHT),
+        false => (ES_AUTOHSCROLL, ROW_HEIGHT),
+    };
+    let style = WS_TABSTOP | WINDOW_STYLE(style as u32) | if multiline { WS_VSCROLL } else { WINDOW_STYLE(0) };
+    create_child(hwnd, "EDIT", "", WS_EX_CLIENTEDGE, style, id, (FIELD_X, y, width, height))
+}
+
+fn create_children(hwnd: HWND) -> eyre::Result<()> {
+    let none = WINDOW_EX_STYLE::default();
+    let list_style = WS_TABSTOP | WS_VSCROLL | WS_BORDER | WINDOW_STYLE((LBS_NOTIFY | LBS_NOINTEGRALHEIGHT) as u32);
+    let list_rect = (MARGIN, MARGIN, LIST_WIDTH, BOTTOM_Y - 2 * MARGIN);
+    create_child(hwnd, "LISTBOX", "", none, list_style, LIST_ID, list_rect)?;
+    button(hwnd, "New", NEW_ID, MARGIN, BOTTOM_Y)?;
+    button(hwnd, "Delete", DELETE_ID, MARGIN + BUTTON_WIDTH + MARGIN, BOTTOM_Y)?;
+
+    label(hwnd, "Name", 0, MARGIN)?;
+    edit(hwnd, NAME_ID, MARGIN, FIELD_WIDTH, false)?;
+
+    label(hwnd, "Chord", 0, MARGIN + ROW_SPACING)?;
+    let chord_width = FIELD_WIDTH - BUTTON_WIDTH - MARGIN;
+    let chord = edit(hwnd, CHORD_ID, MARGIN + ROW_SPACING, chord_width, false)?;
+    send_message(chord, EM_SETREADONLY, WPARAM(1), LPARAM(0));
+    button(hwnd, "Record", RECORD_ID, FIELD_X + chord_width + MARGIN, MARGIN + ROW_SPACING)?;
+
+    label(hwnd, "Action", 0, MARGIN + 2 * ROW_SPACING)?;
+    let combo_style = WS_TABSTOP | WS_VSCROLL | WINDOW_STYLE(CBS_DROPDOWNLIST as u32);
+    let combo_rect = (FIELD_X, MARGIN + 2 * ROW_SPACING, FIELD_WIDTH, 300);
+    let combo = create_child(hwnd, "COMBOBOX", "", none, combo_style, ACTION_ID, combo_rect)?;
+    for info in ACTION_INFO {
+        let name = HSTRING::from(info.name);
+        send_message(combo, CB_ADDSTRING, WPARAM(0), LPARAM(name.as_ptr() as isize));
+    }
+
+    let warning_rect = (FORM_X, BOTTOM_Y - 2 * ROW_HEIGHT, LABEL_WIDTH + FIELD_WIDTH, 2 * ROW_HEIGHT);
+    create_child(hwnd, "STATIC", "", none, WINDOW_STYLE::default(), WARNING_ID, warning_rect)?;
+    button(
+        hwnd,
+        "Apply",
+        APPLY_ID,
+        FIELD_X + FIELD_WIDTH - 2 * BUTTON_WIDTH - MARGIN,
+        BOTTOM_Y,
+    )?;
+    button(hwnd, "Save", SAVE_ID, FIELD_X + FIELD_WIDTH - BUTTON_WIDTH, BOTTOM_Y)?;
+
+    fill_list(hwnd, 0)
+}
+
+/// Lists every binding, and selects the one at `index`.
+fn fill_list(hwnd: HWND, index: usize) -> eyre::Result<()> {
+    let list = get_dlg_item(hwnd, LIST_ID)?;
+    send_message(list, LB_RESETCONTENT, WPARAM(0), LPARAM(0));
+    let count = {
+        let state = STATE.lock().unwrap();
+        let Some(state) = state.as_ref() else {
+            return Ok(());
+        };
+        for (i, binding) in state.entries().enumerate() {
+            let builtin = if i < state.builtins.len() { " (built-in)" } else { "" };
+            let text = HSTRING::from(format!("{}  {}{}", binding.chord(), binding.name, builtin));
+            send_message(list, LB_ADDSTRING, WPARAM(0), LPARAM(text.as_ptr() as isize));
+        }
+        state.entries().count()
+    };
+    if count > 0 {
+        let index = index.min(count - 1);
+        send_message(list, LB_SETCURSEL, WPARAM(index), LPARAM(0));
+        select(hwnd, index)?;
+    }
+    Ok(())
+}
+
+/// Shows the binding at `index` in the form.
+fn select(hwnd: HWND, index: usize) -> eyre::Result<()> {
+    let (binding, info) = {
+        let mut state = STATE.lock().unwrap();
+        let Some(state) = state.as_mut() else {
+            return Ok(());
+        };
+        let Some(binding) = state.entry(index).cloned() else {
+            return Ok(());
+        };
+        state.selected = Some(index);
+        state.trigger = binding.trigger.clone();
+        state.info = action_info(binding.action.name()).unwrap_or(&ACTION_INFO[0]);
+        (binding, state.info)
+    };
+
+    set_window_text(get_dlg_item(hwnd, NAME_ID)?, &binding.name)?;
+    set_window_text(get_dlg_item(hwnd, CHORD_ID)?, &binding.chord())?;
+    let position = ACTION_INFO
+        .iter()
+        .position(|other| other.name == info.name)
+        .unwrap_or(0);
+    send_message(get_dlg_item(hwnd, ACTION_ID)?, CB_SETCURSEL, WPARAM(position), LPARAM(0));
+    create_params(hwnd, info, &settings::param_texts(&binding.action))?;
+    update_warning(hwnd)
+}
+
+/// Replaces the parameter fields with ones for `info`'s parameters, filled with `texts`.
+fn create_params(hwnd: HWND, info: &ActionInfo, texts: &[String]) -> eyre::Result<()> {
+    for i in 0..MAX_PARAMS {
+        for id in [PARAM_FIRST + i, PARAM_LABEL_FIRST + i] {
+            if let Ok(child) = get_dlg_item(hwnd, id) {
+                let _ = destroy_window(child);
+            }
+        }
+    }
+
+    let mut y = PARAMS_Y;
+    for (i, (name, ty)) in info.params.iter().enumerate() {
+        let text = match *name {
+            "" => format!("{}:", ty),
+            name => format!("{} ({}):", name, ty),
+        };
+        label(hwnd, &text, PARAM_LABEL_FIRST + i as i32, y)?;
+        // Lists and YAML get a few lines
+        let multiline = !settings::is_single_line(ty);
+        let field = edit(hwnd, PARAM_FIRST + i as i32, y, FIELD_WIDTH, multiline)?;
+        // Edit controls only break lines on CRLF
+        let text = texts.get(i).map_or(String::new(), |text| text.replace('\n', "\r\n"));
+        set_window_text(field, &text)?;
+        y += if multiline {
+            3 * ROW_HEIGHT + MARGIN
+        } else {
+            ROW_SPACING
+        };
+    }
+    Ok(())
+}
+
+fn field_text(hwnd: HWND, id: i32) -> eyre::Result<String> {
+    let text = get_window_text(get_dlg_item(hwnd, id)?)?;
+    Ok(text.trim_end_matches('\0').replace("\r\n", "\n"))
+}
+
+/// Builds the binding in the form, keeping the selected binding's `when` clause.
+fn form_binding(hwnd: HWND) -> eyre::Result<HotkeyAction> {
+    let (info, trigger, when) = {
+        let state = STATE.lock().unwrap();
+        let Some(state) = state.as_ref() else {
+            return Err(eyre::eyre!("The settings window is closed"));
+        };
+        let when = state
+            .selected
+            .and_then(|index| state.entry(index))
+            .and_then(|binding| binding.when.clone());
+        (state.info, state.trigger.clone(), when)
+    };
+
+    let name = field_text(hwnd, NAME_ID)?.trim().to_owned();
+    if name.is_empty() {
+        return Err(eyre::eyre!("The binding needs a name"));
+    }
+    let texts = (0..info.params.len() as i32)
+        .map(|i| field_text(hwnd, PARAM_FIRST + i))
+        .collect::<eyre::Result<Vec<String>>>()?;
+    Ok(HotkeyAction {
+        name,
+        action: settings::parse_action(info, &texts)?,
+        trigger,
+        when,
+    })
+}
+
+/// Warns when the chord in the form is missing or already taken by another binding.
+fn update_warning(hwnd: HWND) -> eyre::Result<()> {
+    let warning = {
+        let state = STATE.lock().unwrap();
+        let Some(state) = state.as_ref() else {
+            return Ok(());
+        };
+        let selected = state.selected.and_then(|index| state.entry(index));
+        let binding = HotkeyAction {
+            trigger: state.trigger.clone(),
+            when: selected.and_then(|binding| binding.when.clone()),
+            ..HotkeyAction::new("", Action::ClearTop, &[])
+        };
+        match state.conflict(&binding) {
+            _ if binding.trigger.is_empty() => "Press Record, then the chord, to choose the keys.".to_owned(),
+            Some(other) => format!(
+                "Warning: {} is already bound to '{}'. Only one of them will fire.",
+                binding.chord(),
+                other.name
+            ),
+            None => String::new(),
+        }
+    };
+    set_window_text(get_dlg_item(hwnd, WARNING_ID)?, &warning)
+}
+
+/// The index into `config.actions` of the binding at `index`, first asking to copy the built-in bindings into the
+/// config if it's one of them. `None` if the user declines.
+fn editable(hwnd: HWND, index: usize) -> Option<usize> {
+    let builtins = STATE.lock().unwrap().as_ref().map_or(0, |state| state.builtins.len());
+    if index < builtins {
+        let text = "Built-in bindings can only be changed once they're all copied into the config file, which turns \
+                    off default_actions. Copy them?";
+        if message_box(Some(hwnd), text, TITLE, MB_YESNO | MB_ICONWARNING) != IDYES {
+            return None;
+        }
+        if let Some(state) = STATE.lock().unwrap().as_mut() {
+            state.take_builtins();
+        }
+        // Built-ins are listed first, so the index is unchanged
+        return Some(index);
+    }
+    Some(index - builtins)
+}
+
+/// Replaces the selected binding with the one in the form. Returns false if the form is invalid or the user
+/// declined to copy the built-in bindings.
+fn apply(hwnd: HWND) -> eyre::Result<bool> {
+    let Some(selected) = STATE.lock().unwrap().as_ref().and_then(|state| state.selected) else {
+        return Ok(true);
+    };
+    let binding = match form_binding(hwnd) {
+        Ok(binding) => binding,
+        Err(error) => {
+            message_box(Some(hwnd), &format!("{:#}", error), TITLE, MB_OK | MB_ICONWARNING);
+            return Ok(false);
+        }
+    };
+    let Some(index) = editable(hwnd, selected) else {
+        return Ok(false);
+    };
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.config.actions[index] = binding;
+    }
+    fill_list(hwnd, selected)?;
+    Ok(true)
+}
+
+fn delete(hwnd: HWND) -> eyre::Result<()> {
+    let Some(selected) = STATE.lock().unwrap().as_ref().and_then(|state| state.selected) else {
+        return Ok(());
+    };
+    let Some(index) = editable(hwnd, selected) else {
+        return Ok(());
+    };
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.config.actions.remove(index);
+        state.selected = None;
+    }
+    fill_list(hwnd, selected)
+}
+
+fn new_binding(hwnd: HWND) -> eyre::Result<()> {
+    let index = {
+        let mut state = STATE.lock().unwrap();
+        let Some(state) = state.as_mut() else {
+            return Ok(());
+        };
+        state
+            .config
+            .actions
+            .push(HotkeyAction::new("New binding", Action::Maximize, &[]));
+        state.entries().count() - 1
+    };
+    fill_list(hwnd, index)
+}
+
+/// Applies the form, then writes the bindings to the config file and reloads it.
+fn save(hwnd: HWND) -> eyre::Result<()> {
+    if !apply(hwnd)? {
+        return Ok(());
+    }
+    let Some(config) = STATE.lock().unwrap().as_ref().map(|state| state.config.clone()) else {
+        return Ok(());
+    };
+
+    let problems = config::validate(&config);
+    if !problems.is_empty() {
+        let text = format!("{}\n\nSave anyway?", problems.join("\n"));
+        if message_box(Some(hwnd), &text, TITLE, MB_YESNO | MB_ICONWARNING) != IDYES {
+            return Ok(());
+        }
+    }
+
+    config::save_actions(Path::new(config::CONFIG_FILE), &config)?;
+    load_config()
+}
+
+fn toggle_recording(hwnd: HWND) -> eyre::Result<()> {
+    let recording = {
+        let mut recorder = RECORDER.lock().unwrap();
+        match recorder.is_recording() {
+            true => recorder.cancel(),
+            false => recorder.start(),
+        }
+        recorder.is_recording()
+    };
+    set_window_text(
+        get_dlg_item(hwnd, RECORD_ID)?,
+        if recording { "Press keys..." } else { "Record" },
+    )
+}
+
+/// Puts the recorded chord in the form. Escape on its own cancels recording instead.
+fn on_chord_recorded(hwnd: HWND) -> eyre::Result<()> {
+    set_window_text(get_dlg_item(hwnd, RECORD_ID)?, "Record")?;
+    let Some(chord) = RECORDED.lock().unwrap().take() else {
+        return Ok(());
+    };
+    if chord == BTreeSet::from([VK::Escape]) {
+        return Ok(());
+    }
+
+    let text = HotkeyAction {
+        trigger: chord.clone(),
+        ..HotkeyAction::new("", Action::ClearTop, &[])
+    }
+    .chord();
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.trigger = chord;
+    }
+    set_window_text(get_dlg_item(hwnd, CHORD_ID)?, &text)?;
+    update_warning(hwnd)
+}
+
+fn on_action_changed(hwnd: HWND) -> eyre::Result<()> {
+    let index = send_message(get_dlg_item(hwnd, ACTION_ID)?, CB_GETCURSEL, WPARAM(0), LPARAM(0)).0;
+    let Some(info) = usize::try_from(index).ok().and_then(|index| ACTION_INFO.get(index)) else {
+        return Ok(());
+    };
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.info = info;
+    }
+    create_params(hwnd, info, &[])
+}
+
+fn on_command(hwnd: HWND, id: i32, code: u32) -> eyre::Result<()> {
+    match (id, code) {
+        (LIST_ID, LBN_SELCHANGE) => {
+            let index = send_message(get_dlg_item(hwnd, LIST_ID)?, LB_GETCURSEL, WPARAM(0), LPARAM(0)).0;
+            match usize::try_from(index) {
+                Ok(index) => select(hwnd, index),
+                Err(_) => Ok(()),
+            }
+        }
+        (ACTION_ID, CBN_SELCHANGE) => on_action_changed(hwnd),
+        (NEW_ID, BN_CLICKED) => new_binding(hwnd),
+        (DELETE_ID, BN_CLICKED) => delete(hwnd),
+        (RECORD_ID, BN_CLICKED) => toggle_recording(hwnd),
+        (APPLY_ID, BN_CLICKED) => apply(hwnd).map(|_| ()),
+        (SAVE_ID, BN_CLICKED) => save(hwnd),
+        _ => Ok(()),
+    }
+}
+
+extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_CREATE => {
+            SETTINGS_HWND.store(hwnd.0, std::sync::atomic::Ordering::Relaxed);
+            dpi::init(hwnd);
+            if let Err(error) = create_children(hwnd) {
+                log!(Error, Wndproc, "{:?}", error);
+            }
+        }
+        WM_COMMAND => {
+            let (id, code) = ((wparam.0 & 0xFFFF) as i32, ((wparam.0 >> 16) & 0xFFFF) as u32);
+            if let Err(error) = on_command(hwnd, id, code) {
+                message_box(Some(hwnd), &format!("{:?}", error), TITLE, MB_OK);
+            }
+        }
+        WM_DPICHANGED => dpi::on_dpi_changed(hwnd, wparam, lparam),
+        WM_CHORD_RECORDED => {
+            if let Err(error) = on_chord_recorded(hwnd) {
+                log!(Error, Wndproc, "{:?}", error);
+            }
+        }
+        WM_DESTROY => {
+            RECORDER.lock().unwrap().cancel();
+            *STATE.lock().unwrap() = None;
+            SETTINGS_HWND.store(std::ptr::null_mut(), std::sync::atomic::Ordering::Relaxed);
+        }
+        _ => (),
+    }
+
+    def_window_proc(hwnd, msg, wparam, lparam)
+}
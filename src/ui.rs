@@ -1,39 +1,92 @@
+use crate::config::save_muted_errors;
+use crate::error::{ActionError, ErrorKind};
+use crate::hotkey_action::{Action, HotkeyAction, VK};
+use crate::instance::Request;
+use crate::ipc::{Event, MonitorState, WindowState, EVENTS};
+use crate::key_inspector::KeyEvent;
+use crate::keyboard::HoldEvent;
 use crate::safe_win32::{
-    call_next_hook, create_popup_menu, create_window, def_window_proc, destroy_icon, destroy_menu, get_module_handle,
-    get_window_long_ptr, insert_menu, message_box, post_message, register_class, set_foreground_window,
-    set_window_long_ptr, set_windows_hook, shell_notify_icon, track_popup_menu, unhook_windows_hook_ex,
-    wts_register_session_notification, wts_unregister_session_notification,
+    call_next_hook, create_popup_menu, create_window, def_window_proc, destroy_icon, destroy_menu,
+    enum_display_monitors, get_class_name, get_foreground_window, get_module_handle, get_window_long_ptr, insert_menu,
+    kill_timer, message_box, post_message, register_class, send_input, set_foreground_window, set_timer,
+    set_win_event_hook, set_window_long_ptr, set_windows_hook, sh_query_user_notification_state, shell_notify_icon,
+    track_popup_menu, unhook_win_event, unhook_windows_hook_ex, wts_register_session_notification,
+    wts_unregister_session_notification,
+};
+use crate::window_match::WindowInfo;
+use crate::window_system::Win32WindowSystem;
+use crate::{
+    help_window, hotkey_action, key_inspector, load_config, log, monitor, msg, overlay, settings_window,
+    switch_profile, ACTIONS, DEBUG, KEYBOARD, NOTIFIER, PASSTHROUGH, PAUSED, RESCUE,
 };
-use crate::{hotkey_action, msg, print_pressed_keys, ACTIONS, DEBUG, PRESSED_KEYS};
 use num::FromPrimitive;
+use std::ffi::c_void;
+use std::sync::atomic::AtomicPtr;
+use std::sync::Mutex;
+use std::time::Instant;
 use windows::core::{HSTRING, PCWSTR};
 use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
 use windows::Win32::Graphics::Gdi::HBRUSH;
+use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+use windows::Win32::UI::Accessibility::HWINEVENTHOOK;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP, VIRTUAL_KEY,
+};
 use windows::Win32::UI::Shell::{
-    NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NIM_SETVERSION, NOTIFYICONDATAW, NOTIFYICONDATAW_0,
-    NOTIFYICON_VERSION_4,
+    NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_ERROR, NIM_ADD, NIM_DELETE, NIM_MODIFY, NIM_SETVERSION,
+    NIN_BALLOONUSERCLICK, NOTIFYICONDATAW, NOTIFYICONDATAW_0, NOTIFYICON_VERSION_4, QUNS_RUNNING_D3D_FULL_SCREEN,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
-    LoadImageW, CS_HREDRAW, CS_OWNDC, CS_VREDRAW, CW_USEDEFAULT, HCURSOR, HHOOK, HICON, HMENU, IMAGE_ICON,
-    KBDLLHOOKSTRUCT, LR_DEFAULTSIZE, LR_LOADFROMFILE, MB_OK, MF_BYPOSITION, MF_CHECKED, MF_STRING, MF_UNCHECKED,
-    TPM_BOTTOMALIGN, TPM_LEFTBUTTON, TPM_RIGHTALIGN, WH_KEYBOARD_LL, WINDOW_EX_STYLE, WINDOW_LONG_PTR_INDEX, WM_APP,
-    WM_COMMAND, WM_CREATE, WM_DESTROY, WM_ENTERIDLE, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDBLCLK, WM_MOUSEMOVE, WM_NULL,
-    WM_QUIT, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_WTSSESSION_CHANGE, WNDCLASSW, WS_OVERLAPPEDWINDOW,
-    WTS_CONSOLE_CONNECT, WTS_CONSOLE_DISCONNECT, WTS_REMOTE_CONNECT, WTS_REMOTE_DISCONNECT, WTS_SESSION_CREATE,
-    WTS_SESSION_LOCK, WTS_SESSION_LOGOFF, WTS_SESSION_LOGON, WTS_SESSION_REMOTE_CONTROL, WTS_SESSION_TERMINATE,
-    WTS_SESSION_UNLOCK,
+    LoadImageW, CS_HREDRAW, CS_OWNDC, CS_VREDRAW, CW_USEDEFAULT, EVENT_SYSTEM_FOREGROUND, HCURSOR, HHOOK, HICON, HMENU,
+    IDYES, IMAGE_ICON, KBDLLHOOKSTRUCT, LR_DEFAULTSIZE, LR_LOADFROMFILE, MB_ICONQUESTION, MB_OK, MB_YESNO,
+    MF_BYPOSITION, MF_CHECKED, MF_GRAYED, MF_POPUP, MF_STRING, MF_UNCHECKED, OBJID_WINDOW, TPM_BOTTOMALIGN,
+    TPM_LEFTBUTTON, TPM_RIGHTALIGN, WH_KEYBOARD_LL, WINDOW_EX_STYLE, WINDOW_LONG_PTR_INDEX, WINEVENT_OUTOFCONTEXT,
+    WM_APP, WM_COMMAND, WM_COPYDATA, WM_CREATE, WM_DESTROY, WM_DISPLAYCHANGE, WM_ENTERIDLE, WM_KEYDOWN, WM_KEYUP,
+    WM_LBUTTONDBLCLK, WM_MOUSEMOVE, WM_NULL, WM_QUIT, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_TIMER,
+    WM_WTSSESSION_CHANGE, WNDCLASSW, WS_OVERLAPPEDWINDOW, WTS_CONSOLE_CONNECT, WTS_CONSOLE_DISCONNECT,
+    WTS_REMOTE_CONNECT, WTS_REMOTE_DISCONNECT, WTS_SESSION_CREATE, WTS_SESSION_LOCK, WTS_SESSION_LOGOFF,
+    WTS_SESSION_LOGON, WTS_SESSION_REMOTE_CONTROL, WTS_SESSION_TERMINATE, WTS_SESSION_UNLOCK,
 };
 
 const NOTIFY_FOR_THIS_SESSION: u32 = 0x00000000;
 
+static GRIST_HWND: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
+/// The error `WM_SHOW_ERROR` shows. Actions can fail off the UI thread, which owns the notification icon.
+static PENDING_ERROR: Mutex<Option<(ErrorKind, String)>> = Mutex::new(None);
+
+/// The last window the user activated, other than Grist and the taskbar, so that the tray menu can act on it.
+static LAST_FOREGROUND: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Clicking the tray icon activates one of these, which isn't the window the user wants to act on.
+const TASKBAR_CLASSES: [&str; 4] = [
+    "Shell_TrayWnd",
+    "Shell_SecondaryTrayWnd",
+    "NotifyIconOverflowWindow",
+    "TopLevelWindowForOverflowXamlIsland",
+];
+
 // Notification icon messages
 const WM_CLICK_NOTIFY_ICON: u32 = WM_APP + 1;
 const MENU_EXIT: usize = 0x00;
 const MENU_RELOAD: usize = 0x01;
-const MENU_PRINT_KEYS: usize = 0x02;
+const MENU_KEY_INSPECTOR: usize = 0x02;
 const MENU_HELP: usize = 0x03;
 const MENU_DEBUG: usize = 0x04;
-const MENU_ACTIONS: usize = 0x05;
+const MENU_PAUSE: usize = 0x06;
+const MENU_SETTINGS: usize = 0x07;
+/// Items in the actions submenu are numbered from here, in `GristApp::menu_actions` order.
+const MENU_ACTION_FIRST: usize = 0x100;
+const WM_PAUSE_CHANGED: u32 = WM_APP + 2;
+const WM_SHOW_ERROR: u32 = WM_APP + 3;
+const TIMER_CHEAT_SHEET: usize = 0x01;
+const TIMER_RESCUE: usize = 0x02;
+/// How long after a display change to rescue windows, so that Windows has finished moving them off removed monitors
+/// itself.
+const RESCUE_DELAY_MS: u32 = 2000;
+/// An unassigned virtual key, tapped before releasing the Windows key so that the Start menu doesn't open.
+const VK_UNASSIGNED: u16 = 0xE8;
+const TOOLTIP: &str = "Grist Window Manager";
 const GRIST_INDEX: WINDOW_LONG_PTR_INDEX = WINDOW_LONG_PTR_INDEX(0);
 
 fn grist_app_from_hwnd(hwnd: &mut HWND) -> &mut GristApp {
@@ -57,14 +110,25 @@ fn load_icon(name: &str) -> eyre::Result<HICON> {
     }
 }
 
-fn create_notification_icon(hwnd: HWND) -> eyre::Result<NOTIFYICONDATAW> {
+/// Copies `text` into a fixed size `NOTIFYICONDATAW` field, truncating it to leave room for the NUL.
+fn copy_wide(field: &mut [u16], text: &str) {
+    let mut wide: Vec<u16> = text.encode_utf16().take(field.len() - 1).collect();
+    wide.resize(field.len(), 0);
+    field.copy_from_slice(wide.as_slice());
+}
+
+fn set_tooltip(nid: &mut NOTIFYICONDATAW, text: &str) {
+    copy_wide(&mut nid.szTip, text);
+}
+
+fn create_notification_icon(hwnd: HWND, icon: HICON) -> eyre::Result<NOTIFYICONDATAW> {
     let mut nid = NOTIFYICONDATAW {
         cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
         hWnd: hwnd,
         uID: 0x0,
         uFlags: NIF_MESSAGE | NIF_ICON | NIF_TIP,
         uCallbackMessage: WM_CLICK_NOTIFY_ICON,
-        hIcon: load_icon("grist.ico")?,
+        hIcon: icon,
         szTip: [0; 128],
         dwState: Default::default(),
         dwStateMask: Default::default(),
@@ -78,9 +142,7 @@ fn create_notification_icon(hwnd: HWND) -> eyre::Result<NOTIFYICONDATAW> {
         hBalloonIcon: Default::default(),
     };
 
-    let mut tooltip: Vec<u16> = "Grist Window Manager".encode_utf16().collect();
-    tooltip.resize(nid.szTip.len(), 0);
-    nid.szTip.copy_from_slice(tooltip.as_slice());
+    set_tooltip(&mut nid, TOOLTIP);
 
     shell_notify_icon(NIM_ADD, &mut nid)?;
     shell_notify_icon(NIM_SETVERSION, &mut nid)?;
@@ -114,8 +176,19 @@ fn GET_Y_LPARAM(dword: u32) -> i32 {
 
 fn on_notification_icon(hwnd: &mut HWND, wparam: WPARAM, lparam: LPARAM) -> eyre::Result<()> {
     match LOWORD(lparam.0 as u32) as u32 {
+        NIN_BALLOONUSERCLICK => {
+            if let Some(kind) = grist_app_from_hwnd(hwnd).balloon_kind.take() {
+                let text = format!("Stop showing notifications about {}?", kind.description());
+                if message_box(Some(*hwnd), &text, "Grist", MB_YESNO | MB_ICONQUESTION) == IDYES {
+                    let mut notifier = NOTIFIER.lock().unwrap();
+                    notifier.mute(kind);
+                    save_muted_errors(notifier.muted())?;
+                }
+            }
+            Ok(())
+        }
         WM_LBUTTONDBLCLK => {
-            print_pressed_keys();
+            on_wm_command(WPARAM(MENU_KEY_INSPECTOR), hwnd);
             grist_app_from_hwnd(hwnd).rehook_keyboard();
             Ok(())
         }
@@ -124,18 +197,32 @@ fn on_notification_icon(hwnd: &mut HWND, wparam: WPARAM, lparam: LPARAM) -> eyre
             let y = GET_Y_LPARAM(wparam.0 as u32);
             let hmenu = create_popup_menu()?;
             let uflags = MF_BYPOSITION | MF_STRING;
-            let debug_checked = if crate::DEBUG.load(core::sync::atomic::Ordering::Relaxed) {
+            let debug_checked = if DEBUG.load(core::sync::atomic::Ordering::Relaxed) {
                 MF_CHECKED
             } else {
                 MF_UNCHECKED
             };
 
+            let paused_checked = if PAUSED.load(core::sync::atomic::Ordering::Relaxed) {
+                MF_CHECKED
+            } else {
+                MF_UNCHECKED
+            };
+
+            let groups = group_by_name(&ACTIONS.read().unwrap());
+            let actions_menu = create_actions_menu(&groups)?;
+            let grist_app = grist_app_from_hwnd(hwnd);
+            grist_app.menu_target = HWND(LAST_FOREGROUND.load(std::sync::atomic::Ordering::Relaxed));
+            grist_app.menu_actions = groups;
+
             let items = [
-                (MENU_PRINT_KEYS, "Pressed Keys", uflags),
+                (MENU_KEY_INSPECTOR, "Key Inspector", uflags),
                 (MENU_RELOAD, "Reload", uflags),
+                (MENU_SETTINGS, "Settings...", uflags),
                 (MENU_HELP, "Help", uflags),
+                (MENU_PAUSE, "Pause Hotkeys", uflags | paused_checked),
                 (MENU_DEBUG, "Debug", uflags | debug_checked),
-                (MENU_ACTIONS, "Actions", uflags | debug_checked),
+                (actions_menu.0 as usize, "Actions", uflags | MF_POPUP),
                 (MENU_EXIT, "Exit", uflags),
             ];
             for (uposition, (uidnewitem, lpnewitem, uflags)) in items.iter().enumerate() {
@@ -159,6 +246,62 @@ fn on_notification_icon(hwnd: &mut HWND, wparam: WPARAM, lparam: LPARAM) -> eyre
     }
 }
 
+/// Groups bindings that share a name, e.g. the same action bound to two chords, in the order they're listed.
+fn group_by_name(actions: &[HotkeyAction]) -> Vec<Vec<HotkeyAction>> {
+    let mut groups: Vec<Vec<HotkeyAction>> = Vec::new();
+    for action in actions {
+        match groups.iter_mut().find(|group| group[0].name == action.name) {
+            Some(group) => group.push(action.clone()),
+            None => groups.push(vec![action.clone()]),
+        }
+    }
+    groups
+}
+
+/// One item per action, with its chords as the accelerator label.
+fn create_actions_menu(groups: &[Vec<HotkeyAction>]) -> eyre::Result<HMENU> {
+    let hmenu = create_popup_menu()?;
+    if groups.is_empty() {
+        let _ = insert_menu(hmenu, 0, MF_BYPOSITION | MF_STRING | MF_GRAYED, MENU_ACTION_FIRST, "No actions");
+    }
+    for (index, group) in groups.iter().enumerate() {
+        let chords = group
+            .iter()
+            .map(HotkeyAction::chord)
+            .collect::<Vec<String>>()
+            .join(", ");
+        let label = format!("{}\t{}", group[0].name, chords);
+        let _ = insert_menu(
+            hmenu,
+            index as u32,
+            MF_BYPOSITION | MF_STRING,
+            MENU_ACTION_FIRST + index,
+            &label,
+        );
+    }
+    Ok(hmenu)
+}
+
+/// Applies the binding from the actions submenu that best fits the window the menu was opened over.
+fn on_menu_action(hwnd: &mut HWND, index: usize) {
+    let grist_app = grist_app_from_hwnd(hwnd);
+    let target = grist_app.menu_target;
+    let Some(group) = grist_app.menu_actions.get(index) else {
+        return;
+    };
+
+    if !target.is_invalid() {
+        let _ = set_foreground_window(target);
+    }
+    let foreground = WindowInfo::from_hwnd(target).ok();
+    match hotkey_action::most_specific(group.iter(), foreground.as_ref()) {
+        Some(action) => apply_binding(action),
+        None => {
+            log!(Debug, Actions, "No binding for '{}' applies to {:?}", group[0].name, foreground);
+        }
+    }
+}
+
 fn on_wm_command(wparam: WPARAM, hwnd: &mut HWND) {
     match wparam {
         WPARAM(MENU_EXIT) => {
@@ -168,90 +311,81 @@ fn on_wm_command(wparam: WPARAM, hwnd: &mut HWND) {
             let _ = post_message(Some(*hwnd), WM_QUIT, WPARAM(0), LPARAM(0));
         }
         WPARAM(MENU_RELOAD) => {
+            if let Err(error) = load_config() {
+                message_box(Some(*hwnd), &format!("{:?}", error), "Grist Config", MB_OK);
+            }
             grist_app_from_hwnd(hwnd).rehook_keyboard();
         }
-        WPARAM(MENU_PRINT_KEYS) => {
-            print_pressed_keys();
+        WPARAM(MENU_KEY_INSPECTOR) => {
+            if let Err(error) = key_inspector::show() {
+                message_box(Some(*hwnd), &format!("{:?}", error), "Grist Key Inspector", MB_OK);
+            }
+        }
+        WPARAM(MENU_PAUSE) => {
+            set_paused(!PAUSED.load(core::sync::atomic::Ordering::Relaxed));
         }
         WPARAM(MENU_DEBUG) => {
-            let debug = !crate::DEBUG.load(core::sync::atomic::Ordering::Relaxed);
-            println!("Setting debug to {}", debug);
-            crate::DEBUG.store(debug, core::sync::atomic::Ordering::Relaxed);
-        }
-        WPARAM(MENU_ACTIONS) => {
-            let actions = ACTIONS
-                .read()
-                .unwrap()
-                .iter()
-                .map(|action| format!("{:?}", action))
-                .collect::<Vec<String>>()
-                .join("\n");
-            message_box(Some(*hwnd), actions.as_str(), "Grist Help", MB_OK);
+            let debug = !DEBUG.load(core::sync::atomic::Ordering::Relaxed);
+            log!(Info, App, "Setting debug to {}", debug);
+            DEBUG.store(debug, core::sync::atomic::Ordering::Relaxed);
+        }
+        WPARAM(MENU_SETTINGS) => {
+            if let Err(error) = settings_window::show() {
+                message_box(Some(*hwnd), &format!("{:?}", error), "Grist Settings", MB_OK);
+            }
         }
         WPARAM(MENU_HELP) => {
-            let text = r"Actions:
-    ClearTop,
-    Maximize,
-    Minimize,
-    MonitorBottom,
-    MonitorBottomLeft,
-    MonitorBottomRight,
-    MonitorLeft,
-    MonitorRight,
-    MonitorTop,
-    MonitorTopLeft,
-    MonitorTopRight,
-    MoveNextMonitor,
-    MovePrevMonitor,
-    OnDesktop { x: i32, y: i32, w: i32, h: i32 },
-    OnMonitor { x: i32, y: i32, w: i32, h: i32 },
-            ";
-            message_box(Some(*hwnd), text, "Grist Help", MB_OK);
+            if let Err(error) = help_window::show() {
+                message_box(Some(*hwnd), &format!("{:?}", error), "Grist Help", MB_OK);
+            }
         }
+        WPARAM(id) if id >= MENU_ACTION_FIRST => on_menu_action(hwnd, id - MENU_ACTION_FIRST),
         _ => (),
     }
 }
 
+fn on_request(hwnd: &mut HWND, request: Request) -> eyre::Result<()> {
+    match request {
+        Request::Reload => on_wm_command(WPARAM(MENU_RELOAD), hwnd),
+        // As in `apply_binding`, so that a delay doesn't stall the tray and the keyboard hook, which share this thread
+        Request::Run(action) if action.has_delay() => apply_delayed(action.name().to_owned(), move || action.apply()),
+        Request::Run(action) => return Ok(action.apply()?),
+        Request::Pause => set_paused(true),
+        Request::Resume => set_paused(false),
+        Request::SwitchProfile(name) => return switch_profile(&name),
+        Request::Quit => on_wm_command(WPARAM(MENU_EXIT), hwnd),
+    }
+    Ok(())
+}
+
 fn on_wtssession_change(hwnd: &mut HWND, _msg: u32, wparam: WPARAM, _lparam: LPARAM) {
-    let print_wts = |wts| println!("          WM_WTSSESSION_CHANGE {}", wts);
+    let log_wts = |wts| log!(Info, Session, "WM_WTSSESSION_CHANGE {}", wts);
 
     match wparam.0 as u32 {
-        WTS_CONSOLE_CONNECT => print_wts("WTS_CONSOLE_CONNECT"),
-        WTS_CONSOLE_DISCONNECT => print_wts("WTS_CONSOLE_DISCONNECT"),
-        WTS_REMOTE_CONNECT => print_wts("WTS_REMOTE_CONNECT"),
-        WTS_REMOTE_DISCONNECT => print_wts("WTS_REMOTE_DISCONNECT"),
+        WTS_CONSOLE_CONNECT => log_wts("WTS_CONSOLE_CONNECT"),
+        WTS_CONSOLE_DISCONNECT => log_wts("WTS_CONSOLE_DISCONNECT"),
+        WTS_REMOTE_CONNECT => log_wts("WTS_REMOTE_CONNECT"),
+        WTS_REMOTE_DISCONNECT => log_wts("WTS_REMOTE_DISCONNECT"),
         WTS_SESSION_LOGON => {
+            log_wts("WTS_SESSION_LOGON");
             grist_app_from_hwnd(hwnd).hook_keyboard();
-            // print_wts("WTS_SESSION_LOGON")
         }
         WTS_SESSION_LOGOFF => {
+            log_wts("WTS_SESSION_LOGOFF");
             grist_app_from_hwnd(hwnd).unhook_keyboard();
-            // print_wts("WTS_SESSION_LOGOFF")
         }
         WTS_SESSION_LOCK => {
+            log_wts("WTS_SESSION_LOCK");
             grist_app_from_hwnd(hwnd).unhook_keyboard();
-            // print_wts("WTS_SESSION_LOCK")
         }
         WTS_SESSION_UNLOCK => {
+            log_wts("WTS_SESSION_UNLOCK");
             grist_app_from_hwnd(hwnd).hook_keyboard();
-            // print_wts("WTS_SESSION_UNLOCK")
-        }
-        WTS_SESSION_REMOTE_CONTROL => {
-            // print_wts("WTS_SESSION_REMOTE_CONTROL")
-            ()
-        }
-        WTS_SESSION_CREATE => {
-            // print_wts("WTS_SESSION_CREATE")
-            ()
-        }
-        WTS_SESSION_TERMINATE => {
-            //print_wts("WTS_SESSION_TERMINATE")
-            ()
-        }
-        _ => {
-            // print_wts("WTS Unknown wParam")
-            ()
         }
+        WTS_SESSION_REMOTE_CONTROL => log_wts("WTS_SESSION_REMOTE_CONTROL"),
+        WTS_SESSION_CREATE => log_wts("WTS_SESSION_CREATE"),
+        WTS_SESSION_TERMINATE => log_wts("WTS_SESSION_TERMINATE"),
+        _ => log_wts("WTS Unknown wParam"),
     }
 }
 
@@ -260,18 +394,18 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM)
     match msg {
         WM_CREATE => {
             if let Err(error) = wts_register_session_notification(hwnd, NOTIFY_FOR_THIS_SESSION) {
-                println!("{:?}", error)
+                log!(Error, Session, "{:?}", error)
             }
 
-            let nid = match create_notification_icon(hwnd) {
-                Ok(nid) => nid,
+            let mut grist_app = match GristApp::new(hwnd) {
+                Ok(grist_app) => Box::new(grist_app),
                 Err(error) => {
-                    println!("{:?}", error);
+                    log!(Error, Wndproc, "{:?}", error);
                     return def_window_proc(hwnd, msg, wparam, lparam);
                 }
             };
-            let mut grist_app = Box::new(GristApp { nid, hook: HHOOK::default() });
             grist_app.hook_keyboard();
+            GRIST_HWND.store(hwnd.0, std::sync::atomic::Ordering::Relaxed);
             let _ = set_window_long_ptr(hwnd, GRIST_INDEX, Box::into_raw(grist_app) as isize);
         }
         WM_DESTROY => {
@@ -280,15 +414,67 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM)
                 drop(unsafe { Box::from_raw(ptr as *mut GristApp) });
             }
         }
+        WM_TIMER if wparam.0 == TIMER_CHEAT_SHEET => {
+            let _ = kill_timer(hwnd, TIMER_CHEAT_SHEET);
+            let event = KEYBOARD.write().unwrap().poll(Instant::now());
+            if event == Some(HoldEvent::Show) {
+                if let Err(error) = overlay::show() {
+                    log!(Error, Wndproc, "{:?}", error);
+                }
+            }
+        }
+        WM_TIMER if wparam.0 == TIMER_RESCUE => {
+            let _ = kill_timer(hwnd, TIMER_RESCUE);
+            let min_visible_percent = RESCUE.read().unwrap().min_visible_percent;
+            if let Err(error) = monitor::rescue_windows(&Win32WindowSystem, min_visible_percent) {
+                log!(Error, Wndproc, "{:?}", error);
+            }
+        }
+        WM_SHOW_ERROR => {
+            if let Some((kind, text)) = PENDING_ERROR.lock().unwrap().take() {
+                let _ = grist_app_from_hwnd(&mut hwnd).show_error(kind, &text);
+            }
+        }
+        WM_PAUSE_CHANGED => {
+            let _ = grist_app_from_hwnd(&mut hwnd).update_notification_icon();
+        }
+        WM_COPYDATA => {
+            if let Some(request) = unsafe { Request::from_copy_data(lparam.0 as *const COPYDATASTRUCT) } {
+                return match request.and_then(|request| on_request(&mut hwnd, request)) {
+                    Ok(()) => LRESULT(1),
+                    Err(error) => {
+                        log!(Error, Wndproc, "{:?}", error);
+                        LRESULT(0)
+                    }
+                };
+            }
+        }
+        WM_DISPLAYCHANGE => {
+            // Restarting the timer rescues once after a burst of changes, e.g. docking a laptop
+            if RESCUE.read().unwrap().on_monitor_change {
+                let _ = set_timer(hwnd, TIMER_RESCUE, RESCUE_DELAY_MS);
+            }
+            if EVENTS.has_subscribers() {
+                if let Ok(monitors) = enum_display_monitors() {
+                    EVENTS.publish(Event::MonitorsChanged {
+                        monitors: monitors.into_iter().map(MonitorState::from).collect(),
+                    });
+                }
+            }
+        }
         WM_CLICK_NOTIFY_ICON => on_notification_icon(&mut hwnd, wparam, lparam).unwrap_or(()),
         WM_COMMAND => on_wm_command(wparam, &mut hwnd),
         WM_WTSSESSION_CHANGE => on_wtssession_change(&mut hwnd, msg, wparam, lparam),
         _ => {
-            if DEBUG.load(std::sync::atomic::Ordering::Relaxed)
-                && msg != WM_ENTERIDLE
-                && lparam != LPARAM(WM_MOUSEMOVE as isize)
-            {
-                println!("{:>30} w: 0x{:X} l: 0x{:X}", msg::msg_to_string(msg), wparam.0, lparam.0);
+            if msg != WM_ENTERIDLE && lparam != LPARAM(WM_MOUSEMOVE as isize) {
+                log!(
+                    Trace,
+                    Wndproc,
+                    "{:>30} w: 0x{:X} l: 0x{:X}",
+                    msg::msg_to_string(msg),
+                    wparam.0,
+                    lparam.0
+                );
             }
         }
     };
@@ -298,45 +484,182 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM)
 
 unsafe extern "system" fn low_level_keyboard_proc(n_code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     if n_code < 0 {
-        println!("low_level_keyboard_proc(): ncode < 0");
+        log!(Warn, Keyboard, "low_level_keyboard_proc(): ncode < 0");
         return call_next_hook(Some(HHOOK::default()), n_code, wparam, lparam);
     }
 
     let msg = wparam.0 as u32;
-    let vk_code = (*(lparam.0 as *const KBDLLHOOKSTRUCT)).vkCode;
+    let hook = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
 
-    if let Some(vk_code) = hotkey_action::VK::from_u32(vk_code) {
-        match msg {
-            WM_KEYDOWN | WM_SYSKEYDOWN => PRESSED_KEYS.write().unwrap().insert(vk_code),
-            WM_KEYUP | WM_SYSKEYUP => PRESSED_KEYS.write().unwrap().remove(&vk_code),
-            _ => true,
-        }
-    } else {
+    let Some(vk_code) = hotkey_action::VK::from_u32(hook.vkCode) else {
         // How did we get an invalid VK_CODE?
+        inspect(hook, msg, None, false);
         return call_next_hook(Some(HHOOK::default()), n_code, wparam, lparam);
     };
+    let hold = match msg {
+        WM_KEYDOWN | WM_SYSKEYDOWN => KEYBOARD.write().unwrap().key_down(vk_code, Instant::now()),
+        WM_KEYUP | WM_SYSKEYUP => KEYBOARD.write().unwrap().key_up(vk_code),
+        _ => None,
+    };
 
-    // Print the current keys if debug is enabled
-    if (msg == WM_KEYDOWN || msg == WM_SYSKEYDOWN) && DEBUG.load(std::sync::atomic::Ordering::Relaxed) {
-        print_pressed_keys();
+    // While the settings window records a chord, keys go to it instead of the bindings
+    if settings_window::record_chord(&KEYBOARD.read().unwrap().pressed().clone()) {
+        inspect(hook, msg, None, true);
+        return LRESULT(1);
+    }
+
+    match hold {
+        Some(HoldEvent::Started(delay)) => {
+            let hwnd = HWND(GRIST_HWND.load(std::sync::atomic::Ordering::Relaxed));
+            let _ = set_timer(hwnd, TIMER_CHEAT_SHEET, delay.as_millis() as u32);
+        }
+        Some(HoldEvent::Hide) => {
+            overlay::hide();
+            if matches!(vk_code, VK::LeftWindows | VK::RightWindows) && release_without_start_menu(vk_code).is_ok() {
+                inspect(hook, msg, None, true);
+                return LRESULT(1);
+            }
+        }
+        _ => (),
     }
 
     // Trigger the matching actions
-    if let Some(action) = ACTIONS
-        .read()
-        .unwrap()
+    let pressed_keys = KEYBOARD.read().unwrap().pressed().clone();
+    let actions = ACTIONS.read().unwrap();
+
+    // Only look up the foreground window when an app-scoped binding could fire
+    let foreground = match actions
         .iter()
-        .find(|hotkey_action| hotkey_action.trigger == *PRESSED_KEYS.read().unwrap())
+        .any(|action| action.trigger == pressed_keys && action.when.is_some())
     {
-        if let Err(error) = action.action.apply() {
-            println!("{:?}", error);
-        }
+        true => get_foreground_window().and_then(WindowInfo::from_hwnd).ok(),
+        false => None,
+    };
+
+    let matched = hotkey_action::find_binding(&actions, &pressed_keys, foreground.as_ref());
+    let binding = matched.filter(|action| action.action == Action::TogglePause || !hotkeys_suspended());
+    inspect(hook, msg, matched, binding.is_some());
+
+    if let Some(action) = binding {
+        apply_binding(action);
         LRESULT(1)
     } else {
         call_next_hook(Some(HHOOK::default()), n_code, wparam, lparam)
     }
 }
 
+/// Passes a key event to the key inspector and the log, with the binding it matched and whether it was swallowed.
+fn inspect(hook: &KBDLLHOOKSTRUCT, msg: u32, binding: Option<&HotkeyAction>, swallowed: bool) {
+    if !key_inspector::is_open() {
+        return;
+    }
+    key_inspector::record(KeyEvent {
+        vk_code: hook.vkCode,
+        scan_code: hook.scanCode,
+        flags: hook.flags,
+        time: hook.time,
+        down: msg == WM_KEYDOWN || msg == WM_SYSKEYDOWN,
+        pressed: KEYBOARD.read().unwrap().pressed().clone(),
+        binding: binding.map(|action| action.name.clone()),
+        swallowed,
+    });
+}
+
+fn apply_binding(action: &HotkeyAction) {
+    // Windows stalls keyboard input while the hook runs, so delayed actions get their own thread
+    if action.action.has_delay() {
+        let action = action.clone();
+        apply_delayed(action.name.clone(), move || action.apply());
+    } else if let Err(error) = action.apply() {
+        log!(Error, Actions, "'{}' failed: {:?}", action.name, error);
+        notify_error(&error);
+    }
+}
+
+/// Applies an action that sleeps on its own thread, logging and notifying of failures since nothing waits for it.
+fn apply_delayed(name: String, apply: impl FnOnce() -> Result<(), ActionError> + Send + 'static) {
+    std::thread::spawn(move || {
+        if let Err(error) = apply() {
+            log!(Error, Actions, "'{}' failed: {:?}", name, error);
+            notify_error(&error);
+        }
+    });
+}
+
+/// Shows a tray notification for a failed action, unless one of the same kind was shown recently or the user muted
+/// them.
+pub fn notify_error(error: &ActionError) {
+    if !NOTIFIER.lock().unwrap().should_show(error.kind(), Instant::now()) {
+        return;
+    }
+    *PENDING_ERROR.lock().unwrap() = Some((error.kind(), error.to_string()));
+    let hwnd = HWND(GRIST_HWND.load(std::sync::atomic::Ordering::Relaxed));
+    let _ = post_message(Some(hwnd), WM_SHOW_ERROR, WPARAM(0), LPARAM(0));
+}
+
+/// Swallowing a Windows key release and replaying it after another key keeps the Start menu closed.
+fn release_without_start_menu(vk_code: VK) -> eyre::Result<()> {
+    let key = |vk: u16, flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(vk),
+                dwFlags: flags,
+                ..Default::default()
+            },
+        },
+    };
+    send_input(&[
+        key(VK_UNASSIGNED, KEYBD_EVENT_FLAGS(0)),
+        key(VK_UNASSIGNED, KEYEVENTF_KEYUP),
+        key(vk_code as u16, KEYEVENTF_KEYUP),
+    ])
+}
+
+/// Whether bindings should pass keys through, either because the user paused them or because the foreground
+/// window needs Windows key chords for itself.
+fn hotkeys_suspended() -> bool {
+    PAUSED.load(std::sync::atomic::Ordering::Relaxed)
+        || sh_query_user_notification_state().is_ok_and(|state| state == QUNS_RUNNING_D3D_FULL_SCREEN)
+        || get_foreground_window()
+            .and_then(WindowInfo::from_hwnd)
+            .is_ok_and(|info| PASSTHROUGH.read().unwrap().iter().any(|window| window.matches(&info)))
+}
+
+/// Pauses or resumes every binding other than `Action::TogglePause`.
+pub fn set_paused(paused: bool) {
+    PAUSED.store(paused, std::sync::atomic::Ordering::Relaxed);
+    let hwnd = HWND(GRIST_HWND.load(std::sync::atomic::Ordering::Relaxed));
+    let _ = post_message(Some(hwnd), WM_PAUSE_CHANGED, WPARAM(0), LPARAM(0));
+}
+
+unsafe extern "system" fn win_event_proc(
+    _hwineventhook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    idobject: i32,
+    _idchild: i32,
+    _ideventthread: u32,
+    _dwmseventtime: u32,
+) {
+    if event != EVENT_SYSTEM_FOREGROUND || idobject != OBJID_WINDOW.0 {
+        return;
+    }
+
+    let is_grist = hwnd.0 == GRIST_HWND.load(std::sync::atomic::Ordering::Relaxed);
+    let is_taskbar = get_class_name(hwnd).is_ok_and(|class| TASKBAR_CLASSES.contains(&class.as_str()));
+    if !is_grist && !is_taskbar {
+        LAST_FOREGROUND.store(hwnd.0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    if !EVENTS.has_subscribers() {
+        return;
+    }
+    if let Ok(window) = WindowState::from_hwnd(hwnd) {
+        EVENTS.publish(Event::FocusChanged { window });
+    }
+}
+
 pub fn create() -> eyre::Result<HWND> {
     let mut name: Vec<u16> = "Grist".encode_utf16().collect();
 
@@ -377,30 +700,82 @@ pub fn create() -> eyre::Result<HWND> {
 struct GristApp {
     nid: NOTIFYICONDATAW,
     hook: HHOOK,
+    foreground_hook: HWINEVENTHOOK,
+    icon: HICON,
+    paused_icon: HICON,
+    /// The window the tray menu was opened over.
+    menu_target: HWND,
+    /// The bindings behind each item of the actions submenu, captured when the menu opened.
+    menu_actions: Vec<Vec<HotkeyAction>>,
+    /// The kind of error the last notification was about, until the user clicks it.
+    balloon_kind: Option<ErrorKind>,
 }
 
 impl GristApp {
+    fn new(hwnd: HWND) -> eyre::Result<GristApp> {
+        let icon = load_icon("grist.ico")?;
+        let paused_icon = load_icon("grist_paused.ico")?;
+        Ok(GristApp {
+            nid: create_notification_icon(hwnd, icon)?,
+            hook: HHOOK::default(),
+            foreground_hook: set_win_event_hook(
+                EVENT_SYSTEM_FOREGROUND,
+                EVENT_SYSTEM_FOREGROUND,
+                Some(win_event_proc),
+                WINEVENT_OUTOFCONTEXT,
+            )?,
+            icon,
+            paused_icon,
+            menu_target: HWND::default(),
+            menu_actions: Vec::new(),
+            balloon_kind: None,
+        })
+    }
+
+    pub fn update_notification_icon(&mut self) -> eyre::Result<()> {
+        if PAUSED.load(std::sync::atomic::Ordering::Relaxed) {
+            self.nid.hIcon = self.paused_icon;
+            set_tooltip(&mut self.nid, &format!("{} (paused)", TOOLTIP));
+        } else {
+            self.nid.hIcon = self.icon;
+            set_tooltip(&mut self.nid, TOOLTIP);
+        }
+        shell_notify_icon(NIM_MODIFY, &mut self.nid)
+    }
+
+    pub fn show_error(&mut self, kind: ErrorKind, text: &str) -> eyre::Result<()> {
+        copy_wide(&mut self.nid.szInfoTitle, "Grist");
+        copy_wide(&mut self.nid.szInfo, &format!("{}\nClick to stop showing these.", text));
+        self.nid.dwInfoFlags = NIIF_ERROR;
+        self.nid.uFlags |= NIF_INFO;
+        let result = shell_notify_icon(NIM_MODIFY, &mut self.nid);
+        // Otherwise every later NIM_MODIFY shows the balloon again
+        self.nid.uFlags &= !NIF_INFO;
+        self.balloon_kind = Some(kind);
+        result
+    }
+
     pub fn unhook_keyboard(&mut self) {
         if self.hook.is_invalid() {
-            println!("Keyboard wasn't hooked!");
+            log!(Warn, Keyboard, "Keyboard wasn't hooked!");
             return;
         }
 
         let _ = unhook_windows_hook_ex(self.hook);
         self.hook = HHOOK::default();
-        // println!("Unhooked keyboard events");
+        log!(Debug, Keyboard, "Unhooked keyboard events");
     }
 
     pub fn hook_keyboard(&mut self) {
         if !self.hook.is_invalid() {
-            println!("Keyboard was already hooked!");
+            log!(Warn, Keyboard, "Keyboard was already hooked!");
             return;
         }
 
         let hmodule = match get_module_handle(PCWSTR::null()) {
             Ok(hmodule) => hmodule,
             Err(_) => {
-                println!("Failed to get module handle");
+                log!(Error, Keyboard, "Failed to get module handle");
                 return;
             }
         };
@@ -408,17 +783,19 @@ impl GristApp {
 
         if let Ok(hook) = set_windows_hook(WH_KEYBOARD_LL, Some(low_level_keyboard_proc), Some(hinstance), 0) {
             self.hook = hook;
-            // println!("Hooked keyboard events");
+            log!(Debug, Keyboard, "Hooked keyboard events");
             return;
         }
 
-        println!("Failed to hook keyboard events");
+        log!(Error, Keyboard, "Failed to hook keyboard events");
         self.hook = HHOOK::default();
     }
 
     pub fn rehook_keyboard(&mut self) {
         self.unhook_keyboard();
-        PRESSED_KEYS.write().unwrap().clear();
+        if KEYBOARD.write().unwrap().clear().is_some() {
+            overlay::hide();
+        }
         self.hook_keyboard();
     }
 }
@@ -426,10 +803,14 @@ impl GristApp {
 impl Drop for GristApp {
     fn drop(&mut self) {
         let _ = shell_notify_icon(NIM_DELETE, &mut self.nid);
-        if !self.nid.hIcon.is_invalid() {
-            let _ = destroy_icon(self.nid.hIcon);
-            self.nid.hIcon = HICON::default();
+        for icon in [&mut self.icon, &mut self.paused_icon] {
+            if !icon.is_invalid() {
+                let _ = destroy_icon(*icon);
+                *icon = HICON::default();
+            }
         }
+        self.nid.hIcon = HICON::default();
+        let _ = unhook_win_event(self.foreground_hook);
         self.unhook_keyboard();
     }
 }
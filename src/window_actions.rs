@@ -1,14 +1,18 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
 use crate::cardinal::Cardinal;
-use crate::hotkey_action::{Action, HotkeyAction, VK};
+use crate::geometry::{Margins, Rect, Size};
+use crate::hotkey_action::{Action, Corner, HotkeyAction, Units, VK};
+use crate::log;
+#[cfg(windows)]
 use crate::safe_win32::*;
+use crate::window_system::{Decorations, Layering, ShowState, Translucency, WindowId, WindowSystem};
+#[cfg(windows)]
 use crate::PRINT_STYLE;
-use eyre::eyre;
-use windows::Win32::Foundation::{HWND, RECT};
-use windows::Win32::Graphics::Gdi::MONITOR_DEFAULTTOPRIMARY;
-use windows::Win32::System::Threading::{PROCESS_QUERY_INFORMATION, PROCESS_VM_READ};
+#[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::{
-    GWL_EXSTYLE, GWL_STYLE, HWND_NOTOPMOST, SET_WINDOW_POS_FLAGS, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SW_MAXIMIZE,
-    SW_MINIMIZE, SW_RESTORE, WINDOW_EX_STYLE, WINDOW_STYLE, WS_BORDER, WS_CAPTION, WS_CHILD, WS_CHILDWINDOW,
+    GWL_EXSTYLE, GWL_STYLE, WINDOW_EX_STYLE, WINDOW_STYLE, WS_BORDER, WS_CAPTION, WS_CHILD, WS_CHILDWINDOW,
     WS_CLIPCHILDREN, WS_CLIPSIBLINGS, WS_DISABLED, WS_DLGFRAME, WS_EX_ACCEPTFILES, WS_EX_APPWINDOW, WS_EX_CLIENTEDGE,
     WS_EX_COMPOSITED, WS_EX_CONTEXTHELP, WS_EX_CONTROLPARENT, WS_EX_DLGMODALFRAME, WS_EX_LAYERED, WS_EX_LAYOUTRTL,
     WS_EX_LEFT, WS_EX_LEFTSCROLLBAR, WS_EX_LTRREADING, WS_EX_MDICHILD, WS_EX_NOACTIVATE, WS_EX_NOINHERITLAYOUT,
@@ -18,25 +22,53 @@ use windows::Win32::UI::WindowsAndMessaging::{
     WS_SIZEBOX, WS_SYSMENU, WS_TABSTOP, WS_THICKFRAME, WS_TILED, WS_VISIBLE, WS_VSCROLL,
 };
 
-type WorkAreaToWindowPosFn = dyn Fn(&RECT) -> RECT;
+type WorkAreaToWindowPosFn = dyn Fn(&Rect) -> Rect;
 
-fn get_window_executable(hwnd: HWND) -> eyre::Result<String> {
-    let tpid = get_window_thread_process_id(hwnd);
-    let process_handle = open_process(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, tpid.process_id)?;
-    let filename = get_module_file_name(Some(process_handle));
-    let _ = close_handle(process_handle);
-    filename
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Toggle {
+    Maximize,
+    BorderlessFullscreen,
 }
 
-pub fn get_foreground_window_not_zoom() -> eyre::Result<HWND> {
-    let hwnd = get_foreground_window()?;
-    let filename = get_window_executable(hwnd)?;
-    if filename.contains("Zoom.exe") {
-        return Err(eyre!("Don't position Zoom.exe"));
-    }
-    Ok(hwnd)
+/// How a window was before a toggle changed it, so that toggling it back restores it exactly.
+#[derive(Clone, Debug)]
+struct Untoggled {
+    frame: Rect,
+    show_state: ShowState,
+    /// What borderless fullscreen took off.
+    decorations: Option<Decorations>,
+}
+
+/// Kept per window and toggle, so that toggling one window doesn't disturb another.
+static UNTOGGLED: Mutex<BTreeMap<(WindowId, Toggle), Untoggled>> = Mutex::new(BTreeMap::new());
+
+/// A window made see-through, with how it was layered before.
+#[derive(Clone, Debug)]
+struct SeeThrough {
+    original: Layering,
+    translucency: Translucency,
 }
 
+/// Windows that aren't listed are opaque, as far as grist knows.
+static SEE_THROUGH: Mutex<BTreeMap<WindowId, SeeThrough>> = Mutex::new(BTreeMap::new());
+
+/// The least opacity the opacity actions set, so that a window can't vanish altogether.
+const MIN_OPACITY: u32 = 10;
+
+/// The window in picture-in-picture, with how it was before.
+#[derive(Clone, Debug)]
+struct PictureInPicture {
+    window: WindowId,
+    corner: Corner,
+    frame: Rect,
+    maximized: bool,
+    topmost: bool,
+    translucency: Translucency,
+}
+
+/// Only one window is in picture-in-picture at a time.
+static PICTURE_IN_PICTURE: Mutex<Option<PictureInPicture>> = Mutex::new(None);
+
 pub fn add_actions(actions: &mut Vec<HotkeyAction>) {
     actions.extend_from_slice(&[
         HotkeyAction::new("Top Left", Action::MonitorTopLeft, &[VK::LeftWindows, VK::Numpad7]),
@@ -62,101 +94,352 @@ pub fn add_actions(actions: &mut Vec<HotkeyAction>) {
     ]);
 }
 
-pub fn set_window_rect(hwnd: HWND, position: &RECT, flags: SET_WINDOW_POS_FLAGS) -> eyre::Result<()> {
-    show_window(hwnd, SW_RESTORE)?;
+/// Restores the window and moves it so that its visible frame, not counting the invisible resize borders, fills
+/// `position`.
+///
+/// A window moved onto a monitor with a different DPI rescales itself when Windows tells it about the change,
+/// undoing the size, and its borders scale with it. So it's moved a second time, once it's on the new monitor.
+///
+/// A window that can't be made the size of `position`, because of its minimum or maximum size, is left at its
+/// top left corner. It's moved again to keep to the edges of the work area `position` is flush with instead.
+pub fn set_window_rect(system: &dyn WindowSystem, window: WindowId, position: &Rect) -> eyre::Result<()> {
+    system.set_show_state(window, ShowState::Normal)?;
+
+    let dpi = system.monitor_from_window(window)?.dpi;
+    move_frame(system, window, position)?;
+    if system.monitor_from_window(window)?.dpi != dpi {
+        move_frame(system, window, position)?;
+    }
 
-    let margin = calculate_margin(hwnd)?;
-    set_window_pos(
-        hwnd,
-        Some(HWND::default()),
-        position.left + margin.left,
-        position.top + margin.top,
-        position.width() + margin.right - margin.left,
-        position.height() + margin.bottom - margin.top,
-        flags,
-    )
+    let size = system.frame_bounds(window)?.size();
+    if size == position.size() {
+        return Ok(());
+    }
+    let work_area = system.monitor_from_window(window)?.work_area;
+    move_frame(system, window, &position.reanchor(size, &work_area))
 }
 
-fn calculate_margin(hwnd: HWND) -> eyre::Result<RECT> {
-    let window_rect = get_window_rect(hwnd)?;
-    let extended_frame_bounds = dwm_get_window_attribute_extended_frame_bounds(hwnd)?;
+/// Moves the window so that its visible frame fills `frame`.
+fn move_frame(system: &dyn WindowSystem, window: WindowId, frame: &Rect) -> eyre::Result<()> {
+    let border = invisible_border(system, window)?;
+    system.set_window_rect(window, frame.outset(border))
+}
 
-    Ok(RECT {
-        left: window_rect.left - extended_frame_bounds.left,
-        right: window_rect.right - extended_frame_bounds.right,
-        top: window_rect.top - extended_frame_bounds.top,
-        bottom: window_rect.bottom - extended_frame_bounds.bottom,
-    })
+fn invisible_border(system: &dyn WindowSystem, window: WindowId) -> eyre::Result<Margins> {
+    let window_rect = system.window_rect(window)?;
+    let frame_bounds = system.frame_bounds(window)?;
+
+    Ok(Margins::new(
+        frame_bounds.left - window_rect.left,
+        frame_bounds.top - window_rect.top,
+        window_rect.right - frame_bounds.right,
+        window_rect.bottom - frame_bounds.bottom,
+    ))
 }
 
-pub fn set_window_pos_action(workarea_to_window_pos: &WorkAreaToWindowPosFn) -> eyre::Result<()> {
-    let foreground_window = get_foreground_window()?;
-    let monitor_info = get_monitor_info(monitor_from_window(foreground_window, MONITOR_DEFAULTTOPRIMARY)?)?;
-    let new_window_pos = workarea_to_window_pos(&monitor_info.rcWork);
-    set_window_rect(foreground_window, &new_window_pos, SWP_NOZORDER)?;
+pub fn set_window_pos_action(
+    system: &dyn WindowSystem,
+    workarea_to_window_pos: &WorkAreaToWindowPosFn,
+) -> eyre::Result<()> {
+    let foreground_window = system.foreground_window()?;
+    let monitor = system.monitor_from_window(foreground_window)?;
+    let new_window_pos = workarea_to_window_pos(&monitor.work_area);
+    set_window_rect(system, foreground_window, &new_window_pos)?;
 
-    if point_in_rect(&new_window_pos, &get_cursor_pos()?) {
+    if new_window_pos.contains_point(system.cursor_pos()?) {
         Ok(())
     } else {
-        set_cursor_pos(new_window_pos.center().x, new_window_pos.center().y).map(|_| ())
+        system.set_cursor_pos(new_window_pos.center())
     }
 }
 
-pub fn top_left() -> eyre::Result<()> {
-    set_window_pos_action(&|r| RECT::from_points(r.top_left(), r.center()))
+/// Offsets and grows the work area of the window's monitor, scaling the amounts for its DPI if they're logical.
+pub fn on_monitor(system: &dyn WindowSystem, x: i32, y: i32, w: i32, h: i32, units: Units) -> eyre::Result<()> {
+    let monitor = system.monitor_from_window(system.foreground_window()?)?;
+    let scale = |pixels: i32| match units {
+        Units::Pixels => pixels,
+        Units::Logical => monitor.scale(pixels),
+    };
+    let (x, y, w, h) = (scale(x), scale(y), scale(w), scale(h));
+    set_window_pos_action(system, &move |r| Rect {
+        left: r.left + x,
+        top: r.top + y,
+        right: r.right + x + w,
+        bottom: r.bottom + y + h,
+    })
 }
 
-pub fn top_right() -> eyre::Result<()> {
-    set_window_pos_action(&|r| RECT::from_points(r.top_right(), r.center()))
+pub fn top_left(system: &dyn WindowSystem) -> eyre::Result<()> {
+    set_window_pos_action(system, &|r| Rect::from_points(r.top_left(), r.center()))
 }
 
-pub fn bottom_left() -> eyre::Result<()> {
-    set_window_pos_action(&|r| RECT::from_points(r.bottom_left(), r.center()))
+pub fn top_right(system: &dyn WindowSystem) -> eyre::Result<()> {
+    set_window_pos_action(system, &|r| Rect::from_points(r.top_right(), r.center()))
 }
 
-pub fn bottom_right() -> eyre::Result<()> {
-    set_window_pos_action(&|r| RECT::from_points(r.bottom_right(), r.center()))
+pub fn bottom_left(system: &dyn WindowSystem) -> eyre::Result<()> {
+    set_window_pos_action(system, &|r| Rect::from_points(r.bottom_left(), r.center()))
 }
 
-pub fn left() -> eyre::Result<()> {
-    set_window_pos_action(&|r| RECT::from_points(r.top_left(), r.south()))
+pub fn bottom_right(system: &dyn WindowSystem) -> eyre::Result<()> {
+    set_window_pos_action(system, &|r| Rect::from_points(r.bottom_right(), r.center()))
 }
 
-pub fn right() -> eyre::Result<()> {
-    set_window_pos_action(&|r| RECT::from_points(r.north(), r.bottom_right()))
+pub fn left(system: &dyn WindowSystem) -> eyre::Result<()> {
+    set_window_pos_action(system, &|r| Rect::from_points(r.top_left(), r.south()))
 }
 
-pub fn top() -> eyre::Result<()> {
-    set_window_pos_action(&|r| RECT::from_points(r.top_left(), r.east()))
+pub fn right(system: &dyn WindowSystem) -> eyre::Result<()> {
+    set_window_pos_action(system, &|r| Rect::from_points(r.north(), r.bottom_right()))
 }
 
-pub fn bottom() -> eyre::Result<()> {
-    set_window_pos_action(&|r| RECT::from_points(r.west(), r.bottom_right()))
+pub fn top(system: &dyn WindowSystem) -> eyre::Result<()> {
+    set_window_pos_action(system, &|r| Rect::from_points(r.top_left(), r.east()))
 }
 
-pub fn maximize() -> eyre::Result<()> {
-    get_foreground_window()
-        .and_then(|hwnd| show_window(hwnd, SW_MAXIMIZE))
-        .map(|_| ())
+pub fn bottom(system: &dyn WindowSystem) -> eyre::Result<()> {
+    set_window_pos_action(system, &|r| Rect::from_points(r.west(), r.bottom_right()))
 }
 
-pub fn minimize() -> eyre::Result<()> {
-    get_foreground_window_not_zoom()
-        .and_then(|hwnd| show_window(hwnd, SW_MINIMIZE))
-        .map(|_| ())
+pub fn maximize(system: &dyn WindowSystem) -> eyre::Result<()> {
+    system
+        .foreground_window()
+        .and_then(|window| system.set_show_state(window, ShowState::Maximized))
 }
 
-pub fn clear_topmost() -> eyre::Result<()> {
-    get_foreground_window()
-        .and_then(|hwnd| set_window_pos(hwnd, Some(HWND_NOTOPMOST), 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE))
-        .map(|_| ())
+pub fn minimize(system: &dyn WindowSystem) -> eyre::Result<()> {
+    system
+        .foreground_window()
+        .and_then(|window| system.set_show_state(window, ShowState::Minimized))
+}
+
+pub fn clear_topmost(system: &dyn WindowSystem) -> eyre::Result<()> {
+    system
+        .foreground_window()
+        .and_then(|window| system.set_topmost(window, false))
+}
+
+pub fn toggle_topmost(system: &dyn WindowSystem) -> eyre::Result<()> {
+    let window = system.foreground_window()?;
+    system.set_topmost(window, !system.is_topmost(window))
+}
+
+/// Maximizes the window, or puts a maximized one back where it was before `ToggleMaximize` maximized it.
+pub fn toggle_maximize(system: &dyn WindowSystem) -> eyre::Result<()> {
+    let window = system.foreground_window()?;
+    let mut untoggled = UNTOGGLED.lock().unwrap();
+    match system.show_state(window) {
+        // Maximized some other way, so Windows knows best where it goes
+        ShowState::Maximized => match untoggled.remove(&(window, Toggle::Maximize)) {
+            Some(previous) => set_window_rect(system, window, &previous.frame),
+            None => system.set_show_state(window, ShowState::Normal),
+        },
+        // Parked off screen, so there's no frame worth keeping
+        ShowState::Minimized => system.set_show_state(window, ShowState::Maximized),
+        ShowState::Normal => {
+            let previous = Untoggled {
+                frame: system.frame_bounds(window)?,
+                show_state: ShowState::Normal,
+                decorations: None,
+            };
+            untoggled.insert((window, Toggle::Maximize), previous);
+            system.set_show_state(window, ShowState::Maximized)
+        }
+    }
+}
+
+/// Takes the caption and frame off the window and covers its whole monitor, taskbar included, or puts a borderless
+/// window back as it was.
+///
+/// A window that was since moved or resized, or a new window that reuses the handle, isn't fullscreen any more, so
+/// it's made fullscreen again rather than restored.
+pub fn toggle_borderless_fullscreen(system: &dyn WindowSystem) -> eyre::Result<()> {
+    let window = system.foreground_window()?;
+    let bounds = system.monitor_from_window(window)?.bounds;
+    let key = (window, Toggle::BorderlessFullscreen);
+    let mut untoggled = UNTOGGLED.lock().unwrap();
+
+    let fullscreen = system.window_rect(window).is_ok_and(|rect| rect == bounds);
+    if let Some(previous) = untoggled.remove(&key) {
+        if !fullscreen {
+            // Moved or resized since, but still without its caption and frame, which only `previous` remembers
+            untoggled.insert(key, previous);
+            system.set_window_rect(window, bounds)?;
+            return system.bring_to_top(window);
+        }
+        if let Some(decorations) = &previous.decorations {
+            system.set_decorations(window, decorations)?;
+        }
+        set_window_rect(system, window, &previous.frame)?;
+        if previous.show_state == ShowState::Maximized {
+            system.set_show_state(window, ShowState::Maximized)?;
+        }
+        return Ok(());
+    }
+
+    let show_state = system.show_state(window);
+    if show_state != ShowState::Normal {
+        system.set_show_state(window, ShowState::Normal)?;
+    }
+    let frame = system.frame_bounds(window)?;
+    let decorations = system.remove_decorations(window)?;
+    untoggled.insert(
+        key,
+        Untoggled {
+            frame,
+            show_state,
+            decorations: Some(decorations),
+        },
+    );
+    system.set_window_rect(window, bounds)?;
+    system.bring_to_top(window)
+}
+
+/// Changes how see-through the foreground window is, putting back its original layering once it's opaque and
+/// clickable again.
+fn change_translucency(
+    system: &dyn WindowSystem,
+    window: WindowId,
+    change: impl FnOnce(Translucency) -> Translucency,
+) -> eyre::Result<()> {
+    let mut see_through = SEE_THROUGH.lock().unwrap();
+    let current = see_through
+        .get(&window)
+        .map_or(Translucency::OPAQUE, |see_through| see_through.translucency);
+    let mut translucency = change(current);
+    translucency.opacity = translucency.opacity.clamp(MIN_OPACITY, 100);
+
+    if translucency == Translucency::OPAQUE {
+        return match see_through.remove(&window) {
+            Some(previous) => system.set_layering(window, &previous.original),
+            None => Ok(()),
+        };
+    }
+    let original = match see_through.get(&window) {
+        Some(previous) => previous.original.clone(),
+        None => system.layering(window)?,
+    };
+    system.set_translucency(window, translucency)?;
+    see_through.insert(window, SeeThrough { original, translucency });
+    Ok(())
+}
+
+pub fn set_opacity(system: &dyn WindowSystem, percent: u32) -> eyre::Result<()> {
+    change_translucency(system, system.foreground_window()?, |translucency| Translucency {
+        opacity: percent,
+        ..translucency
+    })
+}
+
+pub fn adjust_opacity(system: &dyn WindowSystem, delta: i32) -> eyre::Result<()> {
+    change_translucency(system, system.foreground_window()?, |translucency| Translucency {
+        opacity: translucency.opacity.saturating_add_signed(delta),
+        ..translucency
+    })
+}
+
+/// Lets clicks through the window to the ones below, or stops it. A click-through window can't be clicked to focus
+/// it again, so it's worth making it translucent and topmost as well.
+pub fn toggle_click_through(system: &dyn WindowSystem) -> eyre::Result<()> {
+    change_translucency(system, system.foreground_window()?, |translucency| Translucency {
+        click_through: !translucency.click_through,
+        ..translucency
+    })
+}
+
+/// Shrinks the foreground window to `size` percent of its work area across, keeping its aspect ratio, in `corner`,
+/// keeps it on top and makes it `opacity` percent opaque, if that's given.
+///
+/// When a window is already in picture-in-picture, whichever window has focus, it's put back instead: its rect,
+/// opacity and whether it was topmost. It had focus when it went into picture-in-picture, so it goes back to the top
+/// of the windows that aren't topmost.
+pub fn picture_in_picture(
+    system: &dyn WindowSystem,
+    corner: Corner,
+    size: u32,
+    opacity: Option<u32>,
+) -> eyre::Result<()> {
+    let mut picture_in_picture = PICTURE_IN_PICTURE.lock().unwrap();
+    // Unless it's been closed since
+    if let Some(previous) = picture_in_picture
+        .take()
+        .filter(|previous| system.window_rect(previous.window).is_ok())
+    {
+        set_window_rect(system, previous.window, &previous.frame)?;
+        if previous.maximized {
+            system.set_show_state(previous.window, ShowState::Maximized)?;
+        }
+        // Layering last touches the extended style, so topmost is settled after it
+        change_translucency(system, previous.window, |_| previous.translucency)?;
+        return system.set_topmost(previous.window, previous.topmost);
+    }
+
+    let window = system.foreground_window()?;
+    let show_state = system.show_state(window);
+    // Restored first, so that the frame remembered and sized from is the normal one rather than the work area
+    if show_state != ShowState::Normal {
+        system.set_show_state(window, ShowState::Normal)?;
+    }
+    let frame = system.frame_bounds(window)?;
+    let work_area = system.monitor_from_window(window)?.work_area;
+    let width = work_area.width() * size.clamp(1, 100) as i32 / 100;
+    let height = (width as i64 * frame.height() as i64 / frame.width().max(1) as i64) as i32;
+    let pip = work_area.align(Size::new(width, height.min(work_area.height())), corner.anchors());
+
+    let previous = PictureInPicture {
+        window,
+        corner,
+        frame,
+        maximized: show_state == ShowState::Maximized,
+        topmost: system.is_topmost(window),
+        translucency: SEE_THROUGH
+            .lock()
+            .unwrap()
+            .get(&window)
+            .map_or(Translucency::OPAQUE, |see_through| see_through.translucency),
+    };
+    set_window_rect(system, window, &pip)?;
+    if let Some(opacity) = opacity {
+        change_translucency(system, window, |translucency| Translucency { opacity, ..translucency })?;
+    }
+    system.set_topmost(window, true)?;
+    *picture_in_picture = Some(previous);
+    Ok(())
+}
+
+/// Moves the picture-in-picture window to the next corner clockwise, keeping its size.
+pub fn cycle_picture_in_picture(system: &dyn WindowSystem) -> eyre::Result<()> {
+    let mut picture_in_picture = PICTURE_IN_PICTURE.lock().unwrap();
+    let Some(current) = picture_in_picture.as_mut() else {
+        log!(Debug, Actions, "No window is in picture-in-picture");
+        return Ok(());
+    };
+    current.corner = current.corner.next();
+    let size = system.frame_bounds(current.window)?.size();
+    let work_area = system.monitor_from_window(current.window)?.work_area;
+    set_window_rect(system, current.window, &work_area.align(size, current.corner.anchors()))
+}
+
+/// Forgets what toggles, see-through windows and picture-in-picture remember about each window, for scenarios that
+/// reuse window ids.
+#[cfg(test)]
+pub fn forget_windows() {
+    UNTOGGLED.lock().unwrap().clear();
+    SEE_THROUGH.lock().unwrap().clear();
+    *PICTURE_IN_PICTURE.lock().unwrap() = None;
 }
 
+#[cfg(windows)]
 #[allow(dead_code)]
 pub fn print_window_flags() -> eyre::Result<()> {
     get_foreground_window()
         .and_then(|hwnd| {
-            println!("Styles for '{}'", get_window_text(hwnd).unwrap_or_else(|_| "".to_owned()));
+            log!(
+                Debug,
+                Actions,
+                "Styles for '{}'",
+                get_window_text(hwnd).unwrap_or_else(|_| "".to_owned())
+            );
             let styles = WINDOW_STYLE(get_window_long_ptr(hwnd, GWL_STYLE)? as u32);
             PRINT_STYLE!(styles, WS_BORDER);
             PRINT_STYLE!(styles, WS_CAPTION);
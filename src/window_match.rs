@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use windows::Win32::Foundation::HWND;
+#[cfg(windows)]
+use windows::Win32::System::Threading::{PROCESS_QUERY_INFORMATION, PROCESS_VM_READ};
+
+#[cfg(windows)]
+use crate::safe_win32::{
+    close_handle, get_class_name, get_module_file_name, get_window_text, get_window_thread_process_id, open_process,
+};
+
+/// Identifying strings of a top level window, used to match it against a `WindowMatch`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WindowInfo {
+    pub exe: String,
+    pub class: String,
+    pub title: String,
+}
+
+#[cfg(windows)]
+impl WindowInfo {
+    pub fn from_hwnd(hwnd: HWND) -> eyre::Result<WindowInfo> {
+        Ok(WindowInfo {
+            exe: get_window_executable(hwnd)?.trim_end_matches('\0').to_owned(),
+            class: get_class_name(hwnd)?,
+            title: get_window_text(hwnd)
+                .unwrap_or_default()
+                .trim_end_matches('\0')
+                .to_owned(),
+        })
+    }
+}
+
+#[cfg(windows)]
+fn get_window_executable(hwnd: HWND) -> eyre::Result<String> {
+    let tpid = get_window_thread_process_id(hwnd);
+    let process_handle = open_process(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, tpid.process_id)?;
+    let filename = get_module_file_name(Some(process_handle));
+    let _ = close_handle(process_handle);
+    filename
+}
+
+/// Matches windows by executable, class and/or title.
+///
+/// Every field that is set must be contained in the corresponding `WindowInfo` field, ignoring case.
+/// A `WindowMatch` with no fields set matches nothing.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WindowMatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl WindowMatch {
+    pub fn exe(exe: &str) -> WindowMatch {
+        WindowMatch {
+            exe: Some(exe.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// The number of fields set, so that e.g. an exe and title match beats an exe-only match.
+    pub fn specificity(&self) -> usize {
+        [&self.exe, &self.class, &self.title]
+            .iter()
+            .filter(|pattern| pattern.is_some())
+            .count()
+    }
+
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        let fields = [
+            (&self.exe, &info.exe),
+            (&self.class, &info.class),
+            (&self.title, &info.title),
+        ];
+
+        fields.iter().any(|(pattern, _)| pattern.is_some())
+            && fields.iter().all(|(pattern, value)| match pattern {
+                Some(pattern) => contains_ignore_case(value, pattern),
+                None => true,
+            })
+    }
+}
+
+impl std::fmt::Display for WindowMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = [("exe", &self.exe), ("class", &self.class), ("title", &self.title)]
+            .iter()
+            .filter_map(|(name, pattern)| pattern.as_ref().map(|pattern| format!("{}: {}", name, pattern)))
+            .collect::<Vec<String>>();
+        write!(f, "{}", fields.join(", "))
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zoom() -> WindowInfo {
+        WindowInfo {
+            exe: r"C:\Program Files\Zoom\bin\Zoom.exe".to_owned(),
+            class: "ZPContentViewWndClass".to_owned(),
+            title: "Zoom Meeting".to_owned(),
+        }
+    }
+
+    #[test]
+    fn fields_match_substrings_ignoring_case() {
+        assert!(WindowMatch::exe("zoom.EXE").matches(&zoom()));
+        assert!(WindowMatch::exe(r"bin\zoom").matches(&zoom()));
+        assert!(!WindowMatch::exe("Teams.exe").matches(&zoom()));
+    }
+
+    #[test]
+    fn every_field_set_must_match() {
+        let window = WindowMatch {
+            exe: Some("Zoom.exe".to_owned()),
+            title: Some("meeting".to_owned()),
+            ..Default::default()
+        };
+        assert!(window.matches(&zoom()));
+        let window = WindowMatch {
+            title: Some("Settings".to_owned()),
+            ..window
+        };
+        assert!(!window.matches(&zoom()));
+    }
+
+    #[test]
+    fn an_empty_match_matches_nothing() {
+        assert!(!WindowMatch::default().matches(&zoom()));
+        assert!(!WindowMatch::default().matches(&WindowInfo::default()));
+    }
+}
@@ -0,0 +1,385 @@
+#[cfg(windows)]
+use std::ffi::c_void;
+
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use windows::Win32::Foundation::{COLORREF, HWND};
+#[cfg(windows)]
+use windows::Win32::Graphics::Gdi::{HMONITOR, MONITORINFO, MONITOR_DEFAULTTOPRIMARY};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{
+    GWL_EXSTYLE, GWL_STYLE, HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST, LAYERED_WINDOW_ATTRIBUTES_FLAGS, LWA_ALPHA,
+    MONITORINFOF_PRIMARY, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SW_MAXIMIZE,
+    SW_MINIMIZE, SW_RESTORE, WS_CAPTION, WS_EX_CLIENTEDGE, WS_EX_DLGMODALFRAME, WS_EX_LAYERED, WS_EX_STATICEDGE,
+    WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_EX_WINDOWEDGE, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_SYSMENU,
+    WS_THICKFRAME,
+};
+
+use crate::geometry::{Point, Rect};
+#[cfg(windows)]
+use crate::safe_win32::{
+    dwm_get_window_attribute_extended_frame_bounds, dwm_is_cloaked, enum_display_monitor_handles, enum_windows,
+    get_class_name, get_cursor_pos, get_dpi_for_monitor, get_foreground_window, get_layered_window_attributes,
+    get_monitor_info, get_window_owner, get_window_rect, get_window_styles, is_iconic, is_window_visible, is_zoomed,
+    monitor_from_window, set_cursor_pos, set_foreground_window, set_layered_window_attributes, set_window_long_ptr,
+    set_window_pos, show_window,
+};
+use crate::window_match::WindowInfo;
+
+/// A top level window. On Win32 it's the `HWND`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct WindowId(pub usize);
+
+#[cfg(windows)]
+impl From<HWND> for WindowId {
+    fn from(hwnd: HWND) -> WindowId {
+        WindowId(hwnd.0 as usize)
+    }
+}
+
+#[cfg(windows)]
+impl From<WindowId> for HWND {
+    fn from(window: WindowId) -> HWND {
+        HWND(window.0 as *mut c_void)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShowState {
+    #[default]
+    Normal,
+    Minimized,
+    Maximized,
+}
+
+/// A window's caption and frame as the backend describes them, so that what `remove_decorations` takes off can be
+/// put back exactly. On Win32 it's the style and extended style, and on X11 the `_MOTIF_WM_HINTS`, if any.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Decorations(pub Vec<u32>);
+
+/// How see-through a window is.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Translucency {
+    /// From 0 to 100.
+    pub opacity: u32,
+    /// Clicks go through to the windows below.
+    pub click_through: bool,
+}
+
+impl Translucency {
+    pub const OPAQUE: Translucency = Translucency {
+        opacity: 100,
+        click_through: false,
+    };
+}
+
+/// How a window was layered before it was made see-through, so that it can be put back exactly once it's opaque
+/// again. On Win32 it's the `WS_EX_LAYERED` and `WS_EX_TRANSPARENT` styles and the layered attributes the app set
+/// itself, if any, and on X11 the `_NET_WM_WINDOW_OPACITY`, if any.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Layering(pub Vec<u32>);
+
+/// The desktop behind every window and the taskbars, which `EnumWindows` lists alongside app windows.
+#[cfg(windows)]
+const SHELL_CLASSES: [&str; 4] = ["Progman", "WorkerW", "Shell_TrayWnd", "Shell_SecondaryTrayWnd"];
+
+/// The DPI of a monitor at 100% scale.
+pub const DEFAULT_DPI: u32 = 96;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Monitor {
+    pub bounds: Rect,
+    /// The bounds less the taskbar and docked toolbars.
+    pub work_area: Rect,
+    #[serde(default = "default_dpi")]
+    pub dpi: u32,
+}
+
+fn default_dpi() -> u32 {
+    DEFAULT_DPI
+}
+
+/// Converts logical pixels, as at 100% scale, to physical pixels at `dpi`, rounding to the nearest.
+pub fn scale(logical: i32, dpi: u32) -> i32 {
+    let (logical, dpi, default) = (logical as i64, dpi as i64, DEFAULT_DPI as i64);
+    ((logical * dpi + logical.signum() * default / 2) / default) as i32
+}
+
+impl Monitor {
+    /// Converts logical pixels to physical pixels on this monitor.
+    pub fn scale(&self, logical: i32) -> i32 {
+        scale(logical, self.dpi)
+    }
+}
+
+#[cfg(windows)]
+impl From<MONITORINFO> for Monitor {
+    fn from(info: MONITORINFO) -> Monitor {
+        Monitor {
+            bounds: info.rcMonitor.into(),
+            work_area: info.rcWork.into(),
+            dpi: DEFAULT_DPI,
+        }
+    }
+}
+
+/// The monitor `rect` mostly overlaps, or the first, primary, monitor if it overlaps none of them.
+pub fn monitor_from_rect(monitors: &[Monitor], rect: &Rect) -> Option<Monitor> {
+    monitors
+        .iter()
+        .filter(|monitor| monitor.bounds.intersection(rect).is_some())
+        .max_by_key(|monitor| monitor.bounds.intersection(rect).map_or(0, |overlap| overlap.area()))
+        .or(monitors.first())
+        .copied()
+}
+
+/// Everything actions need from the desktop, so that they can run against `FakeDesktop` as well as Windows.
+pub trait WindowSystem {
+    fn foreground_window(&self) -> eyre::Result<WindowId>;
+    fn set_foreground_window(&self, window: WindowId) -> eyre::Result<()>;
+
+    /// The primary monitor first, as `GatherAllToMonitor` indexes and `monitor_from_rect` falls back on.
+    fn monitors(&self) -> eyre::Result<Vec<Monitor>>;
+    /// The monitor the window is mostly on, or the primary monitor if it's on none of them.
+    fn monitor_from_window(&self, window: WindowId) -> eyre::Result<Monitor>;
+
+    /// Includes the invisible resize borders Windows 10 and later draw around most windows.
+    fn window_rect(&self, window: WindowId) -> eyre::Result<Rect>;
+    /// The visible frame, without the invisible borders.
+    fn frame_bounds(&self, window: WindowId) -> eyre::Result<Rect>;
+    /// Moves and sizes the window without changing its z-order.
+    fn set_window_rect(&self, window: WindowId, rect: Rect) -> eyre::Result<()>;
+
+    fn show_state(&self, window: WindowId) -> ShowState;
+    fn set_show_state(&self, window: WindowId, state: ShowState) -> eyre::Result<()>;
+
+    fn bring_to_top(&self, window: WindowId) -> eyre::Result<()>;
+    fn is_topmost(&self, window: WindowId) -> bool;
+    fn set_topmost(&self, window: WindowId, topmost: bool) -> eyre::Result<()>;
+
+    /// Takes off the caption and frame, returning what they were.
+    fn remove_decorations(&self, window: WindowId) -> eyre::Result<Decorations>;
+    fn set_decorations(&self, window: WindowId, decorations: &Decorations) -> eyre::Result<()>;
+
+    fn layering(&self, window: WindowId) -> eyre::Result<Layering>;
+    /// Puts back the layering and makes the window opaque and clickable again.
+    fn set_layering(&self, window: WindowId, layering: &Layering) -> eyre::Result<()>;
+    fn set_translucency(&self, window: WindowId, translucency: Translucency) -> eyre::Result<()>;
+
+    fn cursor_pos(&self) -> eyre::Result<Point>;
+    fn set_cursor_pos(&self, point: Point) -> eyre::Result<()>;
+
+    /// Every top level window, in z-order from the top.
+    fn windows(&self) -> eyre::Result<Vec<WindowId>>;
+    fn is_visible(&self, window: WindowId) -> bool;
+    /// An ordinary app window, rather than the desktop, a taskbar or panel, a tool window, a window owned by
+    /// another such as a dialog, or one on another virtual desktop.
+    fn is_app_window(&self, window: WindowId) -> bool;
+    fn window_info(&self, window: WindowId) -> eyre::Result<WindowInfo>;
+}
+
+/// The real desktop, through `safe_win32`.
+///
+/// Rects are in physical pixels, as long as the process is Per-Monitor-V2 DPI aware. Otherwise Windows scales them
+/// for whichever monitor it thinks grist is on.
+#[cfg(windows)]
+pub struct Win32WindowSystem;
+
+#[cfg(windows)]
+fn monitor(hmonitor: HMONITOR) -> eyre::Result<Monitor> {
+    Ok(Monitor {
+        dpi: get_dpi_for_monitor(hmonitor)?,
+        ..Monitor::from(get_monitor_info(hmonitor)?)
+    })
+}
+
+/// The extended styles that making a window see-through changes, and that `Layering` remembers.
+#[cfg(windows)]
+const LAYERING_STYLES: u32 = WS_EX_LAYERED.0 | WS_EX_TRANSPARENT.0;
+
+/// Sets the styles, and has Windows redraw the frame and recompute the client area for them.
+#[cfg(windows)]
+fn set_styles(hwnd: HWND, style: u32, ex_style: u32) -> eyre::Result<()> {
+    set_window_long_ptr(hwnd, GWL_STYLE, style as isize)?;
+    set_window_long_ptr(hwnd, GWL_EXSTYLE, ex_style as isize)?;
+    set_window_pos(
+        hwnd,
+        None,
+        0,
+        0,
+        0,
+        0,
+        SWP_FRAMECHANGED | SWP_NOACTIVATE | SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER,
+    )
+}
+
+#[cfg(windows)]
+impl WindowSystem for Win32WindowSystem {
+    fn foreground_window(&self) -> eyre::Result<WindowId> {
+        get_foreground_window().map(WindowId::from)
+    }
+
+    fn set_foreground_window(&self, window: WindowId) -> eyre::Result<()> {
+        set_foreground_window(window.into())
+    }
+
+    fn monitors(&self) -> eyre::Result<Vec<Monitor>> {
+        let mut monitors = enum_display_monitor_handles()?
+            .into_iter()
+            .map(|hmonitor| {
+                let primary = get_monitor_info(hmonitor)?.dwFlags & MONITORINFOF_PRIMARY != 0;
+                Ok((primary, monitor(hmonitor)?))
+            })
+            .collect::<eyre::Result<Vec<(bool, Monitor)>>>()?;
+        // The primary monitor goes first, which EnumDisplayMonitors doesn't promise
+        monitors.sort_by_key(|(primary, _)| !primary);
+        Ok(monitors.into_iter().map(|(_, monitor)| monitor).collect())
+    }
+
+    fn monitor_from_window(&self, window: WindowId) -> eyre::Result<Monitor> {
+        monitor(monitor_from_window(window.into(), MONITOR_DEFAULTTOPRIMARY)?)
+    }
+
+    fn window_rect(&self, window: WindowId) -> eyre::Result<Rect> {
+        get_window_rect(window.into()).map(Rect::from)
+    }
+
+    fn frame_bounds(&self, window: WindowId) -> eyre::Result<Rect> {
+        dwm_get_window_attribute_extended_frame_bounds(window.into()).map(Rect::from)
+    }
+
+    fn set_window_rect(&self, window: WindowId, rect: Rect) -> eyre::Result<()> {
+        set_window_pos(
+            window.into(),
+            Some(HWND::default()),
+            rect.left,
+            rect.top,
+            rect.width(),
+            rect.height(),
+            SWP_NOZORDER,
+        )
+    }
+
+    fn show_state(&self, window: WindowId) -> ShowState {
+        match (is_iconic(window.into()), is_zoomed(window.into())) {
+            (true, _) => ShowState::Minimized,
+            (_, true) => ShowState::Maximized,
+            _ => ShowState::Normal,
+        }
+    }
+
+    fn set_show_state(&self, window: WindowId, state: ShowState) -> eyre::Result<()> {
+        let command = match state {
+            ShowState::Normal => SW_RESTORE,
+            ShowState::Minimized => SW_MINIMIZE,
+            ShowState::Maximized => SW_MAXIMIZE,
+        };
+        show_window(window.into(), command)
+    }
+
+    fn bring_to_top(&self, window: WindowId) -> eyre::Result<()> {
+        set_window_pos(window.into(), Some(HWND_TOP), 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE)
+    }
+
+    fn is_topmost(&self, window: WindowId) -> bool {
+        get_window_styles(window.into()).is_ok_and(|(_, ex_style)| ex_style.contains(WS_EX_TOPMOST))
+    }
+
+    fn set_topmost(&self, window: WindowId, topmost: bool) -> eyre::Result<()> {
+        let insert_after = if topmost { HWND_TOPMOST } else { HWND_NOTOPMOST };
+        set_window_pos(window.into(), Some(insert_after), 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE)
+    }
+
+    fn remove_decorations(&self, window: WindowId) -> eyre::Result<Decorations> {
+        let (style, ex_style) = get_window_styles(window.into())?;
+        set_styles(
+            window.into(),
+            (style & !(WS_CAPTION | WS_THICKFRAME | WS_SYSMENU | WS_MINIMIZEBOX | WS_MAXIMIZEBOX)).0,
+            (ex_style & !(WS_EX_DLGMODALFRAME | WS_EX_CLIENTEDGE | WS_EX_STATICEDGE | WS_EX_WINDOWEDGE)).0,
+        )?;
+        Ok(Decorations(vec![style.0, ex_style.0]))
+    }
+
+    fn set_decorations(&self, window: WindowId, decorations: &Decorations) -> eyre::Result<()> {
+        match decorations.0[..] {
+            [style, ex_style] => set_styles(window.into(), style, ex_style),
+            _ => Err(eyre::eyre!("Not Win32 styles: {:?}", decorations)),
+        }
+    }
+
+    fn layering(&self, window: WindowId) -> eyre::Result<Layering> {
+        let (_, ex_style) = get_window_styles(window.into())?;
+        let layering_styles = ex_style.0 & LAYERING_STYLES;
+        if !ex_style.contains(WS_EX_LAYERED) {
+            return Ok(Layering(vec![layering_styles]));
+        }
+        // Windows drawn with UpdateLayeredWindow have no attributes, and keep drawing themselves
+        Ok(match get_layered_window_attributes(window.into()) {
+            Ok((key, alpha, flags)) => Layering(vec![layering_styles, key.0, alpha as u32, flags.0]),
+            Err(_) => Layering(vec![layering_styles]),
+        })
+    }
+
+    fn set_layering(&self, window: WindowId, layering: &Layering) -> eyre::Result<()> {
+        // The other styles may have changed meanwhile, e.g. WS_EX_TOPMOST from ToggleTopmost, so they're kept
+        let set_layering_styles = |layering_styles: u32| -> eyre::Result<()> {
+            let (_, ex_style) = get_window_styles(window.into())?;
+            let ex_style = (ex_style.0 & !LAYERING_STYLES) | (layering_styles & LAYERING_STYLES);
+            set_window_long_ptr(window.into(), GWL_EXSTYLE, ex_style as isize).map(|_| ())
+        };
+        match layering.0[..] {
+            [layering_styles] => set_layering_styles(layering_styles)?,
+            [layering_styles, key, alpha, flags] => {
+                set_layering_styles(layering_styles)?;
+                set_layered_window_attributes(
+                    window.into(),
+                    COLORREF(key),
+                    alpha as u8,
+                    LAYERED_WINDOW_ATTRIBUTES_FLAGS(flags),
+                )?;
+            }
+            _ => return Err(eyre::eyre!("Not a Win32 layering: {:?}", layering)),
+        }
+        Ok(())
+    }
+
+    fn set_translucency(&self, window: WindowId, translucency: Translucency) -> eyre::Result<()> {
+        let (_, ex_style) = get_window_styles(window.into())?;
+        // WS_EX_TRANSPARENT only lets clicks through layered windows
+        let ex_style = match translucency.click_through {
+            true => ex_style | WS_EX_LAYERED | WS_EX_TRANSPARENT,
+            false => (ex_style | WS_EX_LAYERED) & !WS_EX_TRANSPARENT,
+        };
+        set_window_long_ptr(window.into(), GWL_EXSTYLE, ex_style.0 as isize)?;
+        let alpha = (translucency.opacity.min(100) * 255 + 50) / 100;
+        set_layered_window_attributes(window.into(), COLORREF(0), alpha as u8, LWA_ALPHA)
+    }
+
+    fn cursor_pos(&self) -> eyre::Result<Point> {
+        get_cursor_pos().map(Point::from)
+    }
+
+    fn set_cursor_pos(&self, point: Point) -> eyre::Result<()> {
+        set_cursor_pos(point.x, point.y)
+    }
+
+    fn windows(&self) -> eyre::Result<Vec<WindowId>> {
+        Ok(enum_windows()?.into_iter().map(WindowId::from).collect())
+    }
+
+    fn is_visible(&self, window: WindowId) -> bool {
+        is_window_visible(window.into())
+    }
+
+    fn is_app_window(&self, window: WindowId) -> bool {
+        let hwnd = window.into();
+        let tool = get_window_styles(hwnd).is_ok_and(|(_, ex_style)| ex_style.contains(WS_EX_TOOLWINDOW));
+        let shell = get_class_name(hwnd).is_ok_and(|class| SHELL_CLASSES.contains(&class.as_str()));
+        !tool && !shell && get_window_owner(hwnd).is_none() && !dwm_is_cloaked(hwnd)
+    }
+
+    fn window_info(&self, window: WindowId) -> eyre::Result<WindowInfo> {
+        WindowInfo::from_hwnd(window.into())
+    }
+}
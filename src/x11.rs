@@ -0,0 +1,557 @@
+use eyre::eyre;
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::shape::{ConnectionExt as _, SK, SO};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ClientMessageEvent, ClipOrdering, ConnectionExt as _, EventMask, MapState, PropMode, Window,
+    CLIENT_MESSAGE_EVENT,
+};
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{atom_manager, NONE};
+
+use crate::geometry::{Margins, Point, Rect};
+use crate::window_match::WindowInfo;
+use crate::window_system::{
+    monitor_from_rect, Decorations, Layering, Monitor, ShowState, Translucency, WindowId, WindowSystem, DEFAULT_DPI,
+};
+
+atom_manager! {
+    pub Atoms: AtomsCookie {
+        UTF8_STRING,
+        WM_CHANGE_STATE,
+        _MOTIF_WM_HINTS,
+        _GTK_FRAME_EXTENTS,
+        _NET_ACTIVE_WINDOW,
+        _NET_CLIENT_LIST_STACKING,
+        _NET_CURRENT_DESKTOP,
+        _NET_FRAME_EXTENTS,
+        _NET_MOVERESIZE_WINDOW,
+        _NET_RESTACK_WINDOW,
+        _NET_WM_DESKTOP,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        _NET_WM_STATE,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WM_WINDOW_OPACITY,
+        _NET_WM_STRUT_PARTIAL,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_NORMAL,
+    }
+}
+
+/// Tells the window manager that requests come from a pager or tool acting for the user, which it shouldn't refuse
+/// the way it might an application's own requests.
+const SOURCE_PAGER: u32 = 2;
+
+const NET_WM_STATE_REMOVE: u32 = 0;
+const NET_WM_STATE_ADD: u32 = 1;
+
+/// `_NET_MOVERESIZE_WINDOW` flags: static gravity, so that the coordinates are the client window's, and all of x, y,
+/// width and height set.
+const MOVERESIZE_STATIC_XYWH: u32 = 10 | 0xF << 8 | SOURCE_PAGER << 12;
+
+/// `_MOTIF_WM_HINTS` are flags, functions, decorations, input mode and status. The flag says the decorations are set.
+const MOTIF_HINTS_DECORATIONS: u32 = 2;
+const MOTIF_DECORATIONS_INDEX: usize = 2;
+
+/// The `_NET_WM_DESKTOP` of windows shown on every desktop.
+const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
+/// The ICCCM `IconicState` for `WM_CHANGE_STATE`.
+const ICONIC_STATE: u32 = 3;
+
+/// An X11 desktop with an EWMH window manager.
+///
+/// Window rects follow the Win32 meaning: the window rect is the window manager's frame around the client, and the
+/// frame bounds leave out the invisible shadows that client-side decorated windows report in `_GTK_FRAME_EXTENTS`.
+pub struct X11WindowSystem {
+    connection: RustConnection,
+    root: Window,
+    screen_size: (i32, i32),
+    atoms: Atoms,
+}
+
+impl X11WindowSystem {
+    /// Connects to `$DISPLAY`.
+    pub fn connect() -> eyre::Result<X11WindowSystem> {
+        let (connection, screen) = x11rb::connect(None)?;
+        let (root, screen_size) = {
+            let screen = &connection.setup().roots[screen];
+            (screen.root, (screen.width_in_pixels as i32, screen.height_in_pixels as i32))
+        };
+        let atoms = Atoms::new(&connection)?.reply()?;
+        Ok(X11WindowSystem {
+            connection,
+            root,
+            screen_size,
+            atoms,
+        })
+    }
+
+    pub fn connection(&self) -> &RustConnection {
+        &self.connection
+    }
+
+    pub fn root(&self) -> Window {
+        self.root
+    }
+
+    fn property32(&self, window: Window, property: Atom) -> eyre::Result<Vec<u32>> {
+        let reply = self
+            .connection
+            .get_property(false, window, property, AtomEnum::ANY, 0, 1024)?
+            .reply()?;
+        Ok(reply.value32().map(Iterator::collect).unwrap_or_default())
+    }
+
+    fn property_string(&self, window: Window, property: impl Into<Atom>) -> eyre::Result<Option<String>> {
+        let reply = self
+            .connection
+            .get_property(false, window, property, AtomEnum::ANY, 0, 1024)?
+            .reply()?;
+        Ok((reply.format == 8 && !reply.value.is_empty()).then(|| String::from_utf8_lossy(&reply.value).into_owned()))
+    }
+
+    /// `_NET_FRAME_EXTENTS` and `_GTK_FRAME_EXTENTS` are left, right, top, bottom.
+    fn extents(&self, window: Window, property: Atom) -> eyre::Result<Margins> {
+        Ok(match self.property32(window, property)?[..] {
+            [left, right, top, bottom] => Margins::new(left as i32, top as i32, right as i32, bottom as i32),
+            _ => Margins::default(),
+        })
+    }
+
+    fn client_rect(&self, window: Window) -> eyre::Result<Rect> {
+        let geometry = self.connection.get_geometry(window)?.reply()?;
+        let origin = self
+            .connection
+            .translate_coordinates(window, self.root, 0, 0)?
+            .reply()?;
+        Ok(Rect::from_size(
+            origin.dst_x as i32,
+            origin.dst_y as i32,
+            geometry.width as i32,
+            geometry.height as i32,
+        ))
+    }
+
+    fn net_wm_state(&self, window: Window) -> eyre::Result<Vec<Atom>> {
+        self.property32(window, self.atoms._NET_WM_STATE)
+    }
+
+    fn change_net_wm_state(&self, window: Window, add: bool, first: Atom, second: Atom) -> eyre::Result<()> {
+        let action = if add { NET_WM_STATE_ADD } else { NET_WM_STATE_REMOVE };
+        self.client_message(window, self.atoms._NET_WM_STATE, [action, first, second, SOURCE_PAGER, 0])
+    }
+
+    /// Sends a request to the window manager, which listens on the root window.
+    fn client_message(&self, window: Window, message_type: Atom, data: [u32; 5]) -> eyre::Result<()> {
+        let event = ClientMessageEvent {
+            response_type: CLIENT_MESSAGE_EVENT,
+            ..ClientMessageEvent::new(32, window, message_type, data)
+        };
+        self.connection.send_event(
+            false,
+            self.root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )?;
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    /// The space docks and panels reserve at each edge of the screen, from their `_NET_WM_STRUT_PARTIAL`.
+    fn struts(&self) -> eyre::Result<Vec<(Edge, Rect)>> {
+        let (width, height) = self.screen_size;
+        let mut struts = Vec::new();
+        for window in self.windows()? {
+            let values = self.property32(window.0 as Window, self.atoms._NET_WM_STRUT_PARTIAL)?;
+            struts.extend(strut_partial((width, height), &values));
+        }
+        Ok(struts)
+    }
+}
+
+/// The rects a `_NET_WM_STRUT_PARTIAL` reserves on a screen of `(width, height)`, if it has all 12 values.
+fn strut_partial((width, height): (i32, i32), values: &[u32]) -> Vec<(Edge, Rect)> {
+    let &[left, right, top, bottom, left_start, left_end, right_start, right_end, top_start, top_end, bottom_start, bottom_end] =
+        &values.iter().map(|&value| value as i32).collect::<Vec<i32>>()[..]
+    else {
+        return Vec::new();
+    };
+    // The ranges are inclusive
+    [
+        (Edge::Left, Rect::new(0, left_start, left, left_end + 1)),
+        (Edge::Right, Rect::new(width - right, right_start, width, right_end + 1)),
+        (Edge::Top, Rect::new(top_start, 0, top_end + 1, top)),
+        (Edge::Bottom, Rect::new(bottom_start, height - bottom, bottom_end + 1, height)),
+    ]
+    .into_iter()
+    .filter(|(_, rect)| !rect.is_empty())
+    .collect()
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// The monitor less the struts that overlap it.
+fn work_area(bounds: Rect, struts: &[(Edge, Rect)]) -> Rect {
+    let mut work_area = bounds;
+    for (edge, strut) in struts {
+        if strut.intersection(&bounds).is_none() {
+            continue;
+        }
+        match edge {
+            Edge::Left => work_area.left = work_area.left.max(strut.right),
+            Edge::Right => work_area.right = work_area.right.min(strut.left),
+            Edge::Top => work_area.top = work_area.top.max(strut.bottom),
+            Edge::Bottom => work_area.bottom = work_area.bottom.min(strut.top),
+        }
+    }
+    work_area
+}
+
+impl WindowSystem for X11WindowSystem {
+    fn foreground_window(&self) -> eyre::Result<WindowId> {
+        match self.property32(self.root, self.atoms._NET_ACTIVE_WINDOW)?.first() {
+            Some(&window) if window != NONE => Ok(WindowId(window as usize)),
+            _ => Err(eyre!("No active window")),
+        }
+    }
+
+    fn set_foreground_window(&self, window: WindowId) -> eyre::Result<()> {
+        // The timestamp is 0, for the current time
+        self.client_message(window.0 as Window, self.atoms._NET_ACTIVE_WINDOW, [SOURCE_PAGER, 0, 0, 0, 0])
+    }
+
+    fn monitors(&self) -> eyre::Result<Vec<Monitor>> {
+        let mut infos = self.connection.randr_get_monitors(self.root, true)?.reply()?.monitors;
+        // The primary monitor goes first
+        infos.sort_by_key(|info| !info.primary);
+
+        let struts = self.struts()?;
+        Ok(infos
+            .iter()
+            .map(|info| {
+                let bounds = Rect::from_size(info.x as i32, info.y as i32, info.width as i32, info.height as i32);
+                Monitor {
+                    bounds,
+                    work_area: work_area(bounds, &struts),
+                    // X scales every monitor alike, through the toolkits, so rects are always physical pixels
+                    dpi: DEFAULT_DPI,
+                }
+            })
+            .collect())
+    }
+
+    fn monitor_from_window(&self, window: WindowId) -> eyre::Result<Monitor> {
+        monitor_from_rect(&self.monitors()?, &self.window_rect(window)?).ok_or_else(|| eyre!("No monitors"))
+    }
+
+    fn window_rect(&self, window: WindowId) -> eyre::Result<Rect> {
+        let window = window.0 as Window;
+        Ok(self
+            .client_rect(window)?
+            .outset(self.extents(window, self.atoms._NET_FRAME_EXTENTS)?))
+    }
+
+    fn frame_bounds(&self, window: WindowId) -> eyre::Result<Rect> {
+        let shadow = self.extents(window.0 as Window, self.atoms._GTK_FRAME_EXTENTS)?;
+        Ok(self.window_rect(window)?.inset(shadow))
+    }
+
+    fn set_window_rect(&self, window: WindowId, rect: Rect) -> eyre::Result<()> {
+        let window = window.0 as Window;
+        let client = rect.inset(self.extents(window, self.atoms._NET_FRAME_EXTENTS)?);
+        self.client_message(
+            window,
+            self.atoms._NET_MOVERESIZE_WINDOW,
+            [
+                MOVERESIZE_STATIC_XYWH,
+                client.left as u32,
+                client.top as u32,
+                client.width() as u32,
+                client.height() as u32,
+            ],
+        )
+    }
+
+    fn show_state(&self, window: WindowId) -> ShowState {
+        let states = self.net_wm_state(window.0 as Window).unwrap_or_default();
+        let has = |state: Atom| states.contains(&state);
+        if has(self.atoms._NET_WM_STATE_HIDDEN) {
+            ShowState::Minimized
+        } else if has(self.atoms._NET_WM_STATE_MAXIMIZED_HORZ) && has(self.atoms._NET_WM_STATE_MAXIMIZED_VERT) {
+            ShowState::Maximized
+        } else {
+            ShowState::Normal
+        }
+    }
+
+    fn set_show_state(&self, window: WindowId, state: ShowState) -> eyre::Result<()> {
+        let (horizontal, vertical) = (self.atoms._NET_WM_STATE_MAXIMIZED_HORZ, self.atoms._NET_WM_STATE_MAXIMIZED_VERT);
+        // Activating a minimized window maps it again, as SW_RESTORE and SW_MAXIMIZE do on Windows
+        if state != ShowState::Minimized && self.show_state(window) == ShowState::Minimized {
+            self.set_foreground_window(window)?;
+        }
+        match state {
+            ShowState::Normal => self.change_net_wm_state(window.0 as Window, false, horizontal, vertical),
+            ShowState::Maximized => self.change_net_wm_state(window.0 as Window, true, horizontal, vertical),
+            ShowState::Minimized => {
+                self.client_message(window.0 as Window, self.atoms.WM_CHANGE_STATE, [ICONIC_STATE, 0, 0, 0, 0])
+            }
+        }
+    }
+
+    fn bring_to_top(&self, window: WindowId) -> eyre::Result<()> {
+        // No sibling, and Above
+        self.client_message(window.0 as Window, self.atoms._NET_RESTACK_WINDOW, [SOURCE_PAGER, 0, 0, 0, 0])
+    }
+
+    fn is_topmost(&self, window: WindowId) -> bool {
+        self.net_wm_state(window.0 as Window)
+            .is_ok_and(|states| states.contains(&self.atoms._NET_WM_STATE_ABOVE))
+    }
+
+    fn set_topmost(&self, window: WindowId, topmost: bool) -> eyre::Result<()> {
+        self.change_net_wm_state(window.0 as Window, topmost, self.atoms._NET_WM_STATE_ABOVE, 0)
+    }
+
+    fn remove_decorations(&self, window: WindowId) -> eyre::Result<Decorations> {
+        let hints = self.property32(window.0 as Window, self.atoms._MOTIF_WM_HINTS)?;
+        let mut borderless = match hints.len() {
+            5 => hints.clone(),
+            _ => vec![0; 5],
+        };
+        borderless[0] |= MOTIF_HINTS_DECORATIONS;
+        borderless[MOTIF_DECORATIONS_INDEX] = 0;
+        self.set_decorations(window, &Decorations(borderless))?;
+        Ok(Decorations(hints))
+    }
+
+    fn set_decorations(&self, window: WindowId, decorations: &Decorations) -> eyre::Result<()> {
+        let (window, property) = (window.0 as Window, self.atoms._MOTIF_WM_HINTS);
+        match decorations.0.is_empty() {
+            true => self.connection.delete_property(window, property)?,
+            false => {
+                self.connection
+                    .change_property32(PropMode::REPLACE, window, property, property, &decorations.0)?
+            }
+        };
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn layering(&self, window: WindowId) -> eyre::Result<Layering> {
+        self.property32(window.0 as Window, self.atoms._NET_WM_WINDOW_OPACITY)
+            .map(Layering)
+    }
+
+    fn set_layering(&self, window: WindowId, layering: &Layering) -> eyre::Result<()> {
+        let (window, property) = (window.0 as Window, self.atoms._NET_WM_WINDOW_OPACITY);
+        match layering.0.is_empty() {
+            true => self.connection.delete_property(window, property)?,
+            false => self.connection.change_property32(
+                PropMode::REPLACE,
+                window,
+                property,
+                AtomEnum::CARDINAL,
+                &layering.0,
+            )?,
+        };
+        // No mask resets the input region to the whole window
+        self.connection.shape_mask(SO::SET, SK::INPUT, window, 0, 0, NONE)?;
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    /// Compositors read the opacity from `_NET_WM_WINDOW_OPACITY`, and an empty input region lets clicks through.
+    fn set_translucency(&self, window: WindowId, translucency: Translucency) -> eyre::Result<()> {
+        let window = window.0 as Window;
+        let opacity = (translucency.opacity.min(100) as u64 * u32::MAX as u64 / 100) as u32;
+        self.connection.change_property32(
+            PropMode::REPLACE,
+            window,
+            self.atoms._NET_WM_WINDOW_OPACITY,
+            AtomEnum::CARDINAL,
+            &[opacity],
+        )?;
+        match translucency.click_through {
+            true => self
+                .connection
+                .shape_rectangles(SO::SET, SK::INPUT, ClipOrdering::UNSORTED, window, 0, 0, &[])?,
+            false => self.connection.shape_mask(SO::SET, SK::INPUT, window, 0, 0, NONE)?,
+        };
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn cursor_pos(&self) -> eyre::Result<Point> {
+        let pointer = self.connection.query_pointer(self.root)?.reply()?;
+        Ok(Point::new(pointer.root_x as i32, pointer.root_y as i32))
+    }
+
+    fn set_cursor_pos(&self, point: Point) -> eyre::Result<()> {
+        self.connection
+            .warp_pointer(NONE, self.root, 0, 0, 0, 0, point.x as i16, point.y as i16)?;
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn windows(&self) -> eyre::Result<Vec<WindowId>> {
+        // The stacking order is bottom to top
+        let stacking = self.property32(self.root, self.atoms._NET_CLIENT_LIST_STACKING)?;
+        Ok(stacking
+            .into_iter()
+            .rev()
+            .map(|window| WindowId(window as usize))
+            .collect())
+    }
+
+    fn is_visible(&self, window: WindowId) -> bool {
+        // Window managers unmap minimized windows, which Windows still counts as visible
+        self.connection
+            .get_window_attributes(window.0 as Window)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|attributes| attributes.map_state == MapState::VIEWABLE)
+            || self.show_state(window) == ShowState::Minimized
+    }
+
+    /// Docks, panels, the desktop and utility windows have a `_NET_WM_WINDOW_TYPE` other than normal, and dialogs
+    /// are transient for their owners.
+    fn is_app_window(&self, window: WindowId) -> bool {
+        let window = window.0 as Window;
+        let first = |window: Window, property: Atom| {
+            self.property32(window, property)
+                .ok()
+                .and_then(|values| values.first().copied())
+        };
+        let normal = first(window, self.atoms._NET_WM_WINDOW_TYPE)
+            .is_none_or(|window_type| window_type == self.atoms._NET_WM_WINDOW_TYPE_NORMAL);
+        let owned = first(window, AtomEnum::WM_TRANSIENT_FOR.into()).is_some_and(|owner| owner != NONE);
+        let elsewhere = match (
+            first(window, self.atoms._NET_WM_DESKTOP),
+            first(self.root, self.atoms._NET_CURRENT_DESKTOP),
+        ) {
+            (Some(desktop), Some(current)) => desktop != ALL_DESKTOPS && desktop != current,
+            _ => false,
+        };
+        normal && !owned && !elsewhere
+    }
+
+    fn window_info(&self, window: WindowId) -> eyre::Result<WindowInfo> {
+        let window = window.0 as Window;
+        let exe = match self.property32(window, self.atoms._NET_WM_PID)?.first() {
+            Some(pid) => std::fs::read_link(format!("/proc/{}/exe", pid))
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            None => String::new(),
+        };
+        // WM_CLASS is the instance name and then the class name, each null terminated
+        let class = self
+            .property_string(window, AtomEnum::WM_CLASS)?
+            .and_then(|class| class.split('\0').nth(1).map(str::to_owned))
+            .unwrap_or_default();
+        let title = match self.property_string(window, self.atoms._NET_WM_NAME)? {
+            Some(title) => title,
+            None => self.property_string(window, AtomEnum::WM_NAME)?.unwrap_or_default(),
+        };
+        Ok(WindowInfo { exe, class, title })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use x11rb::protocol::xproto::{CreateWindowAux, PropMode, WindowClass};
+    use x11rb::wrapper::ConnectionExt as _;
+    use x11rb::COPY_FROM_PARENT;
+
+    use super::*;
+
+    /// A 1920x1080 monitor with a 2560x1440 one to its right, on a 4480x1440 screen.
+    const LEFT: Rect = Rect::new(0, 0, 1920, 1080);
+    const RIGHT: Rect = Rect::new(1920, 0, 4480, 1440);
+    const SCREEN: (i32, i32) = (4480, 1440);
+
+    #[test]
+    fn struts_only_shrink_the_monitors_they_reserve_space_on() {
+        let struts = [
+            // A 48 pixel dock down the left of the left monitor
+            strut_partial(SCREEN, &[48, 0, 0, 0, 0, 1079, 0, 0, 0, 0, 0, 0]),
+            // A 64 pixel panel down the right of the right monitor
+            strut_partial(SCREEN, &[0, 64, 0, 0, 0, 0, 0, 1439, 0, 0, 0, 0]),
+            // A 40 pixel taskbar along the bottom of the left monitor, 360 pixels above the bottom of the screen
+            strut_partial(SCREEN, &[0, 0, 0, 400, 0, 0, 0, 0, 0, 0, 0, 1919]),
+            // Not a strut at all
+            strut_partial(SCREEN, &[1, 2, 3]),
+        ]
+        .concat();
+        assert_eq!(struts.len(), 3);
+        assert_eq!(struts[0], (Edge::Left, Rect::new(0, 0, 48, 1080)));
+        assert_eq!(struts[1], (Edge::Right, Rect::new(4416, 0, 4480, 1440)));
+        assert_eq!(struts[2], (Edge::Bottom, Rect::new(0, 1040, 1920, 1440)));
+
+        assert_eq!(work_area(LEFT, &struts), Rect::new(48, 0, 1920, 1040));
+        assert_eq!(work_area(RIGHT, &struts), Rect::new(1920, 0, 4416, 1440));
+    }
+
+    /// Needs an X server. Run it with `xvfb-run cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn xvfb() -> eyre::Result<()> {
+        std::env::var_os("DISPLAY").ok_or_else(|| eyre!("DISPLAY isn't set, run this under xvfb-run"))?;
+        let system = X11WindowSystem::connect()?;
+        let connection = system.connection();
+        let window = connection.generate_id()?;
+        connection.create_window(
+            COPY_FROM_PARENT as u8,
+            window,
+            system.root(),
+            40,
+            30,
+            200,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )?;
+        connection.change_property8(
+            PropMode::REPLACE,
+            window,
+            AtomEnum::WM_CLASS,
+            AtomEnum::STRING,
+            b"term\0Term\0",
+        )?;
+        connection.change_property8(
+            PropMode::REPLACE,
+            window,
+            system.atoms._NET_WM_NAME,
+            system.atoms.UTF8_STRING,
+            "Grist test".as_bytes(),
+        )?;
+        connection.map_window(window)?;
+        connection.sync()?;
+
+        let id = WindowId(window as usize);
+        let info = system.window_info(id)?;
+        assert_eq!((info.class.as_str(), info.title.as_str()), ("Term", "Grist test"));
+        assert_eq!(system.window_rect(id)?, Rect::from_size(40, 30, 200, 100));
+        assert_eq!(system.show_state(id), ShowState::Normal);
+        assert!(system.is_visible(id));
+
+        system.set_cursor_pos(Point::new(12, 34))?;
+        assert_eq!(system.cursor_pos()?, Point::new(12, 34));
+        assert!(!system.monitors()?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::Ordering;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::{ConnectionExt as _, NotifyMask};
+use x11rb::protocol::xproto::{Allow, ConnectionExt as _, GrabMode, Keycode, ModMask};
+use x11rb::protocol::Event;
+use x11rb::CURRENT_TIME;
+
+use crate::hotkey_action::{self, Action, HotkeyAction, VK};
+use crate::launch::ProcessLauncher;
+use crate::monitor;
+use crate::window_system::WindowSystem;
+use crate::x11::X11WindowSystem;
+use crate::{log, ACTIONS, PASSTHROUGH, PAUSED, RESCUE};
+
+/// The modifiers chords are made of. Caps Lock and Num Lock are ignored.
+const CHORD_MODIFIERS: [ModMask; 4] = [ModMask::SHIFT, ModMask::CONTROL, ModMask::M1, ModMask::M4];
+/// Caps Lock and, on nearly every keyboard map, Num Lock.
+const LOCK_MODIFIERS: [ModMask; 2] = [ModMask::LOCK, ModMask::M2];
+
+/// The X modifier a key sets. X doesn't tell left and right modifiers apart, so neither do grabs.
+pub fn modifier_mask(vk: VK) -> Option<ModMask> {
+    match vk {
+        VK::Shift | VK::LeftShift | VK::RightShift => Some(ModMask::SHIFT),
+        VK::Control | VK::LeftControl | VK::RightControl => Some(ModMask::CONTROL),
+        VK::Menu | VK::LeftMenu | VK::RightMenu => Some(ModMask::M1),
+        VK::LeftWindows | VK::RightWindows => Some(ModMask::M4),
+        _ => None,
+    }
+}
+
+/// The keysym for a virtual key, from `X11/keysymdef.h` and `X11/XF86keysym.h`, assuming a US layout for the
+/// punctuation keys.
+pub fn keysym(vk: VK) -> Option<u32> {
+    let code = vk as u32;
+    let offset = |first: VK, last: VK| {
+        (first as u32..=last as u32)
+            .contains(&code)
+            .then(|| code - first as u32)
+    };
+    if let Some(n) = offset(VK::A, VK::Z) {
+        return Some(0x61 + n);
+    } else if let Some(n) = offset(VK::N0, VK::N9) {
+        return Some(0x30 + n);
+    } else if let Some(n) = offset(VK::Numpad0, VK::Numpad9) {
+        return Some(0xffb0 + n);
+    } else if let Some(n) = offset(VK::Multiply, VK::Divide) {
+        return Some(0xffaa + n);
+    } else if let Some(n) = offset(VK::F1, VK::F24) {
+        return Some(0xffbe + n);
+    }
+    let keysym = match vk {
+        VK::Back => 0xff08,
+        VK::Tab => 0xff09,
+        VK::Clear => 0xff0b,
+        VK::Return => 0xff0d,
+        VK::Pause => 0xff13,
+        VK::ScrollLock => 0xff14,
+        VK::Escape => 0xff1b,
+        VK::Space => 0x20,
+        VK::Home => 0xff50,
+        VK::Left => 0xff51,
+        VK::Up => 0xff52,
+        VK::Right => 0xff53,
+        VK::Down => 0xff54,
+        VK::Prior => 0xff55,
+        VK::Next => 0xff56,
+        VK::End => 0xff57,
+        VK::Snapshot => 0xff61,
+        VK::Insert => 0xff63,
+        VK::Application => 0xff67,
+        VK::Delete => 0xffff,
+        VK::OEM1 => 0x3b,
+        VK::OEMPlus => 0x3d,
+        VK::OEMComma => 0x2c,
+        VK::OEMMinus => 0x2d,
+        VK::OEMPeriod => 0x2e,
+        VK::OEM2 => 0x2f,
+        VK::OEM3 => 0x60,
+        VK::OEM4 => 0x5b,
+        VK::OEM5 => 0x5c,
+        VK::OEM6 => 0x5d,
+        VK::OEM7 => 0x27,
+        VK::VolumeDown => 0x1008ff11,
+        VK::VolumeMute => 0x1008ff12,
+        VK::VolumeUp => 0x1008ff13,
+        VK::MediaPlayPause => 0x1008ff14,
+        VK::MediaStop => 0x1008ff15,
+        VK::MediaPrevTrack => 0x1008ff16,
+        VK::MediaNextTrack => 0x1008ff17,
+        _ => return None,
+    };
+    Some(keysym)
+}
+
+/// Splits a trigger into its modifier mask and its one other key, or `None` if X can't grab it.
+pub fn chord(trigger: &BTreeSet<VK>) -> Option<(ModMask, VK)> {
+    let mut modifiers = ModMask::from(0u16);
+    let mut keys = Vec::new();
+    for &vk in trigger {
+        match modifier_mask(vk) {
+            Some(mask) => modifiers |= mask,
+            None => keys.push(vk),
+        }
+    }
+    match keys[..] {
+        [key] => Some((modifiers, key)),
+        _ => None,
+    }
+}
+
+/// The grabbed chords, by keycode and modifiers, with the trigger to look bindings up by.
+type Grabs = BTreeMap<(Keycode, u16), BTreeSet<VK>>;
+
+/// Grabs every binding's chord on the root window. Chords X can't express are logged and skipped.
+fn grab(system: &X11WindowSystem, actions: &[HotkeyAction]) -> eyre::Result<Grabs> {
+    let connection = system.connection();
+    let setup = connection.setup();
+    let (min_keycode, max_keycode) = (setup.min_keycode, setup.max_keycode);
+    let mapping = connection
+        .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)?
+        .reply()?;
+    let keycode = |keysym: u32| {
+        mapping
+            .keysyms
+            .chunks(mapping.keysyms_per_keycode.max(1) as usize)
+            .position(|keysyms| keysyms.contains(&keysym))
+            .map(|i| min_keycode + i as Keycode)
+    };
+
+    let mut grabs = Grabs::new();
+    for action in actions {
+        let Some((modifiers, key)) = chord(&action.trigger) else {
+            log!(
+                Warn,
+                Keyboard,
+                "'{}' isn't modifiers and one key, so it can't be grabbed",
+                action.name
+            );
+            continue;
+        };
+        let Some(code) = keysym(key).and_then(keycode) else {
+            log!(
+                Warn,
+                Keyboard,
+                "'{}' uses {:?}, which isn't on the keyboard map",
+                action.name,
+                key
+            );
+            continue;
+        };
+        if grabs.insert((code, modifiers.into()), action.trigger.clone()).is_some() {
+            continue;
+        }
+        // The same chord with every combination of the lock keys
+        for locks in 0..1 << LOCK_MODIFIERS.len() {
+            let mut mask = modifiers;
+            for (i, lock) in LOCK_MODIFIERS.iter().enumerate() {
+                if locks & 1 << i != 0 {
+                    mask |= *lock;
+                }
+            }
+            // Sync keyboard, so that keys can still be passed on while bindings are suspended
+            connection.grab_key(false, system.root(), mask, code, GrabMode::ASYNC, GrabMode::SYNC)?;
+        }
+    }
+    connection.flush()?;
+    Ok(grabs)
+}
+
+/// Whether bindings should pass keys through, because the user paused them or the active window wants the chords.
+fn hotkeys_suspended(system: &X11WindowSystem) -> bool {
+    PAUSED.load(Ordering::Relaxed)
+        || system
+            .foreground_window()
+            .and_then(|window| system.window_info(window))
+            .is_ok_and(|info| PASSTHROUGH.read().unwrap().iter().any(|window| window.matches(&info)))
+}
+
+/// Grabs the bindings' chords and applies them until the connection to the X server closes.
+///
+/// Monitor changes rescue windows too, if the config asks for it.
+pub fn run(system: &X11WindowSystem) -> eyre::Result<()> {
+    let grabs = grab(system, &ACTIONS.read().unwrap())?;
+    log!(Info, Keyboard, "Grabbed {} chords", grabs.len());
+    system
+        .connection()
+        .randr_select_input(system.root(), NotifyMask::SCREEN_CHANGE)?;
+    system.connection().flush()?;
+
+    let chord_mask = CHORD_MODIFIERS
+        .iter()
+        .fold(0u16, |mask, modifier| mask | u16::from(*modifier));
+    loop {
+        let event = match system.connection().wait_for_event()? {
+            Event::KeyPress(event) => event,
+            Event::RandrScreenChangeNotify(_) => {
+                let rescue = *RESCUE.read().unwrap();
+                if rescue.on_monitor_change {
+                    if let Err(error) = monitor::rescue_windows(system, rescue.min_visible_percent) {
+                        log!(Error, Actions, "{:?}", error);
+                    }
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let trigger = grabs.get(&(event.detail, u16::from(event.state) & chord_mask));
+
+        let actions = ACTIONS.read().unwrap();
+        let foreground = system
+            .foreground_window()
+            .and_then(|window| system.window_info(window))
+            .ok();
+        let binding = trigger
+            .and_then(|trigger| hotkey_action::find_binding(&actions, trigger, foreground.as_ref()))
+            .filter(|action| action.action == Action::TogglePause || !hotkeys_suspended(system));
+        log!(
+            Debug,
+            Keyboard,
+            "Key {} with {:?}: {:?}",
+            event.detail,
+            event.state,
+            binding.map(|action| &action.name)
+        );
+
+        // Thaw the keyboard before applying, either swallowing the key or sending it on to the focused window
+        let mode = match binding {
+            Some(_) => Allow::ASYNC_KEYBOARD,
+            None => Allow::REPLAY_KEYBOARD,
+        };
+        system.connection().allow_events(mode, CURRENT_TIME)?;
+        system.connection().flush()?;
+
+        if let Some(action) = binding {
+            if let Err(error) = action.apply_with(system, &ProcessLauncher) {
+                log!(Error, Actions, "'{}' failed: {:?}", action.name, error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_bindings_can_be_grabbed() {
+        for action in crate::create_actions() {
+            let chord = chord(&action.trigger);
+            assert!(chord.is_some(), "'{}' isn't modifiers and one key", action.name);
+            assert!(
+                chord.and_then(|(_, key)| keysym(key)).is_some(),
+                "'{}' has no keysym",
+                action.name
+            );
+        }
+    }
+}